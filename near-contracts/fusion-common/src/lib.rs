@@ -0,0 +1,4 @@
+// Constants shared by the Fusion+ NEAR contracts
+
+// Highest fee any contract accepts, in basis points (10%)
+pub const MAX_FEE_BPS: u32 = 1000;
@@ -1,23 +1,46 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap};
-use near_sdk::json_types::{U128, U64};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, ext_contract, near_bindgen, AccountId, Gas, PanicOnDefault, Promise, PromiseOrValue,
-    Timestamp, NearToken,
+    env, ext_contract, near_bindgen, AccountId, CurveType, Gas, PanicOnDefault, Promise,
+    PromiseOrValue, PublicKey, Timestamp, NearToken,
 };
 use near_contract_standards::fungible_token::Balance;
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use fusion_common::MAX_FEE_BPS;
 
 // Gas constants
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_BALANCE: Gas = Gas::from_tgas(5);
+const GAS_FOR_RECOVER_CALLBACK: Gas = Gas::from_tgas(20);
 const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(20);
 const GAS_FOR_CLAIM: Gas = Gas::from_tgas(30);
+const GAS_FOR_EXECUTION: Gas = Gas::from_tgas(10); // Headroom for the calling method itself
+const GAS_FOR_TRANSFER_AND_RESOLVE: Gas = Gas::from_tgas(40); // Payout, resolve callback and scheduling both receipts
+const GAS_FOR_SOLVER_VIEW: Gas = Gas::from_tgas(5);
+const GAS_FOR_RESOLVE_ORDER: Gas = Gas::from_tgas(5);
+const GAS_FOR_NEAR_DEPOSIT: Gas = Gas::from_tgas(10);
 
 // Storage constants
 const STORAGE_COST_PER_BYTE: Balance = 1_000_000_000_000_000_000; // 1 NEAR
 const MIN_STORAGE_BALANCE: Balance = STORAGE_COST_PER_BYTE * 1000; // 1KB
+const STORAGE_RECORD_OVERHEAD: u64 = 40; // Bytes charged per trie record
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+const MAX_MEMO_LEN: usize = 256; // Bytes
+
+// Timelock constants
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const MAX_TIMELOCK_SECONDS: u64 = 365 * 86400; // 1 year
+
+// Oracle prices are fixed-point with 6 decimals
+const PRICE_DECIMALS: u32 = 6;
+const PRICE_DENOMINATOR: u128 = 10u128.pow(PRICE_DECIMALS);
+
+// View limits
+const MAX_SWAPS_PER_BATCH: usize = 100;
+const MAX_ORDERS_PER_CLEANUP: usize = 100;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -31,10 +54,15 @@ pub struct EscrowOrder {
     pub to_amount: U128,
     pub hashlock: String,
     pub secret: Option<String>,
+    pub secret_length: u32,
     pub timelock: U64,
+    pub refund_bounty_opt_in: bool,
+    pub cross_chain: bool, // Linked to a cross-chain swap, so only the NEAR share of the fee is taken here
     pub status: OrderStatus,
     pub created_at: U64,
+    pub claim_deadline: Option<U64>,
     pub expires_at: U64,
+    pub memo: Option<String>, // Opaque integrator reference, e.g. an off-chain order id
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, schemars::JsonSchema)]
@@ -45,6 +73,7 @@ pub enum OrderStatus {
     Claimed,
     Refunded,
     Expired,
+    Disputed, // Funds frozen until the arbiter resolves the dispute
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -103,10 +132,129 @@ pub struct SwapRoute {
     pub pool_id: Option<String>,
 }
 
+// Funded order awaiting claim, as seen by its maker
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActiveOrder {
+    #[serde(flatten)]
+    pub order: EscrowOrder,
+    pub refundable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OrderSource {
+    Escrow,
+    Solver,
+}
+
+// Order from either contract, normalized for clients
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderView {
+    pub id: String,
+    pub source: OrderSource,
+    pub maker: AccountId,
+    pub from_token: AccountId,
+    pub to_token: AccountId,
+    pub from_amount: U128,
+    pub to_amount: U128,
+    pub status: String,
+    pub created_at: U64,
+}
+
+// Fields of a fusion-solver order needed to build an `OrderView`
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct SolverOrder {
+    id: String,
+    user: AccountId,
+    from_token: AccountId,
+    to_token: AccountId,
+    from_amount: U128,
+    to_amount: U128,
+    status: String,
+    created_at: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OraclePrice {
+    pub price: U128, // to_token units per from_token unit, scaled by PRICE_DENOMINATOR
+    pub timestamp: U64, // Oracle report time in nanoseconds
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Dispute {
+    pub opened_by: AccountId,
+    pub bond: U128, // yoctoNEAR attached when the dispute was opened
+    pub winner: Option<AccountId>, // Set once the arbiter has ruled
+    pub opened_at: U64,
+}
+
+// Arguments of `create_order`; the optional ones may be left out of the JSON
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateOrderArgs {
+    pub taker: AccountId,
+    pub from_token: AccountId,
+    pub to_token: AccountId,
+    pub from_amount: U128,
+    pub to_amount: U128,
+    pub hashlock: String,
+    pub secret_length: u32, // Bytes, committed for the claim
+    pub timelock: U64, // Seconds
+    #[serde(default)]
+    pub refund_bounty_opt_in: bool,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+// `ft_on_transfer` message opening a funded order for a maker, sent by the solver contract
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowTransferMessage {
+    CreateOrder {
+        order_id: String, // Solver order id, reused so both contracts refer to the swap by one id
+        maker: AccountId,
+        taker: AccountId,
+        to_token: AccountId,
+        to_amount: U128,
+        hashlock: String,
+        secret_length: u32,
+        timelock: U64,
+    },
+}
+
+// Totals of terminal orders removed by `cleanup_terminal_orders`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ArchivedOrderStats {
+    pub claimed: u64,
+    pub refunded: u64,
+    pub expired: u64,
+    pub volume: U128, // Sum of `from_amount` over archived orders
+}
+
+// External contract interface for the solver contract
+#[ext_contract(ext_solver)]
+pub trait ExtSolver {
+    fn get_order(&self, order_id: String) -> String;
+}
+
+// External contract interface for the wNEAR contract
+#[ext_contract(ext_wnear)]
+pub trait ExtWrappedNear {
+    fn near_deposit(&mut self);
+}
+
 // External contract interface for fungible tokens
 #[ext_contract(ext_ft)]
 pub trait ExtFungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) -> Promise;
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
     fn ft_transfer_call(
         &mut self,
         receiver_id: AccountId,
@@ -121,51 +269,120 @@ pub trait ExtFungibleToken {
 pub struct FusionEscrow {
     pub owner: AccountId,
     pub fee_rate: u32, // Fee rate in basis points (e.g., 30 = 0.3%)
+    pub near_fee_share_bps: u32, // Share of a cross-chain fee collected on NEAR; the rest is taken on the other chain
     pub min_timelock: U64,
     pub max_timelock: U64,
+    pub min_secret_length: u32, // Bytes
+    pub refund_bounty_rate: u32, // Basis points paid to third-party refunders
+    pub refund_grace_period: U64, // Seconds after expiry before third-party refunds
+    pub relayer_reimbursement_rate: u32, // Basis points withheld from failed swap refunds for relayer costs
+    pub taker_rebate_bps: u32, // Share of the collected fee paid back to the taker on claim
+    pub oracle: Option<AccountId>,
+    pub solver_contract: Option<AccountId>, // Consulted by `resolve_order` for ids unknown here
+    pub wnear_contract: Option<AccountId>, // Wraps NEAR attached to `create_order_with_near`
+    pub arbiter: Option<AccountId>, // Resolves disputed orders
+    pub dispute_bond: U128, // yoctoNEAR a party must attach to open a dispute
+    pub open_disputes: u64, // Disputes still waiting on the arbiter
+    pub max_price_age: U64, // Seconds before an oracle price is considered stale
+    pub max_cross_chain_amount: U128, // Largest `from_amount` a cross-chain swap may carry; 0 means uncapped
+    pub order_retention_period: U64, // Seconds a terminal order is kept past expiry before it can be cleaned up
+    pub require_creator_proof: bool, // Frontends have makers confirm they hold the preimage before creating an order
     
     // Storage
     pub orders: UnorderedMap<String, EscrowOrder>,
     pub cross_chain_swaps: UnorderedMap<String, CrossChainSwap>,
+    pub evm_hash_index: LookupMap<(String, AccountId), String>, // (EVM order hash, NEAR account) -> swap id
     pub user_orders: LookupMap<AccountId, Vec<String>>,
     pub supported_tokens: LookupMap<AccountId, bool>,
+    pub supported_chains: UnorderedSet<String>, // Chain ids accepted by cross-chain swaps
+    pub paused_pairs: LookupMap<(AccountId, AccountId), bool>, // (from_token, to_token) pairs closed to new orders
+    pub oracle_prices: LookupMap<String, OraclePrice>,
+    pub disputes: LookupMap<String, Dispute>,
+    pub consent_keys: LookupMap<AccountId, PublicKey>, // Keys takers sign mutual cancellations with
+    pub token_obligations: LookupMap<AccountId, u128>, // `from_amount` of Funded and Disputed orders per token
     
     // Statistics
     pub total_swaps: u64,
     pub total_volume: U128,
     pub total_fees: U128,
+    pub cross_chain_fees: U128, // NEAR-side fees from cross-chain orders, also counted in total_fees
+    pub archived_orders: ArchivedOrderStats,
 }
 
 #[near_bindgen]
 impl FusionEscrow {
     #[init]
     pub fn new(owner: AccountId) -> Self {
+        let mut supported_chains = UnorderedSet::new(b"h");
+        supported_chains.insert(&"near".to_string());
+        supported_chains.insert(&"ethereum".to_string());
+        
         Self {
             owner,
             fee_rate: 30, // 0.3% default fee
+            near_fee_share_bps: 10000, // Whole fee on NEAR
             min_timelock: U64(3600), // 1 hour minimum
             max_timelock: U64(86400), // 24 hours maximum
+            min_secret_length: 32,
+            refund_bounty_rate: 10, // 0.1% default bounty
+            refund_grace_period: U64(3600), // 1 hour
+            relayer_reimbursement_rate: 0,
+            taker_rebate_bps: 0,
+            oracle: None,
+            solver_contract: None,
+            wnear_contract: None,
+            arbiter: None,
+            dispute_bond: U128(NearToken::from_near(1).as_yoctonear()),
+            open_disputes: 0,
+            max_price_age: U64(300), // 5 minutes
+            max_cross_chain_amount: U128(0),
+            order_retention_period: U64(30 * 86400), // 30 days
+            require_creator_proof: false,
             orders: UnorderedMap::new(b"o"),
             cross_chain_swaps: UnorderedMap::new(b"c"),
+            evm_hash_index: LookupMap::new(b"e"),
             user_orders: LookupMap::new(b"u"),
             supported_tokens: LookupMap::new(b"t"),
+            supported_chains,
+            paused_pairs: LookupMap::new(b"a"),
+            oracle_prices: LookupMap::new(b"p"),
+            disputes: LookupMap::new(b"d"),
+            consent_keys: LookupMap::new(b"k"),
+            token_obligations: LookupMap::new(b"b"),
             total_swaps: 0,
             total_volume: U128(0),
             total_fees: U128(0),
+            cross_chain_fees: U128(0),
+            archived_orders: ArchivedOrderStats::default(),
         }
     }
 
     // Create a new escrow order
-    pub fn create_order(
-        &mut self,
-        taker: AccountId,
-        from_token: AccountId,
-        to_token: AccountId,
-        from_amount: U128,
-        to_amount: U128,
-        hashlock: String,
-        timelock: U64,
-    ) -> String {
+    pub fn create_order(&mut self, args: CreateOrderArgs) -> String {
+        let maker = env::predecessor_account_id();
+        let order_id = format!("order_{}_{}", maker, env::block_timestamp());
+        self.internal_create_order(order_id, maker, args)
+    }
+
+    fn internal_create_order(&mut self, order_id: String, maker: AccountId, args: CreateOrderArgs) -> String {
+        let CreateOrderArgs {
+            taker,
+            from_token,
+            to_token,
+            from_amount,
+            to_amount,
+            hashlock,
+            secret_length,
+            timelock,
+            refund_bounty_opt_in,
+            memo,
+        } = args;
+        assert!(
+            secret_length >= self.min_secret_length,
+            "Secret must be at least {} bytes",
+            self.min_secret_length
+        );
+
         // Validate timelock
         assert!(
             timelock.0 >= self.min_timelock.0 && timelock.0 <= self.max_timelock.0,
@@ -174,6 +391,10 @@ impl FusionEscrow {
             self.max_timelock.0
         );
 
+        if let Some(memo) = &memo {
+            assert!(memo.len() <= MAX_MEMO_LEN, "Memo exceeds {} bytes", MAX_MEMO_LEN);
+        }
+
         // Validate tokens are supported
         assert!(
             self.supported_tokens.get(&from_token).unwrap_or(false),
@@ -183,10 +404,11 @@ impl FusionEscrow {
             self.supported_tokens.get(&to_token).unwrap_or(false),
             "To token not supported"
         );
+        assert!(
+            !self.paused_pairs.get(&(from_token.clone(), to_token.clone())).unwrap_or(false),
+            "Token pair is paused"
+        );
 
-        let maker = env::predecessor_account_id();
-        let order_id = format!("order_{}_{}", maker, env::block_timestamp());
-        
         let order = EscrowOrder {
             id: order_id.clone(),
             maker: maker.clone(),
@@ -197,13 +419,18 @@ impl FusionEscrow {
             to_amount,
             hashlock,
             secret: None,
+            secret_length,
             timelock,
+            refund_bounty_opt_in,
+            cross_chain: false,
             status: OrderStatus::Pending,
             created_at: U64(env::block_timestamp()),
-            expires_at: U64(env::block_timestamp() + timelock.0 * 1_000_000_000), // Convert to nanoseconds
+            claim_deadline: None,
+            expires_at: U64(timelock_expiry(timelock)),
+            memo,
         };
 
-        self.orders.insert(&order_id, &order);
+        self.internal_save_order(&order_id, &order);
         
         // Add to user's orders
         let mut user_orders = self.user_orders.get(&maker).unwrap_or_default();
@@ -215,6 +442,7 @@ impl FusionEscrow {
 
     // Fund an escrow order (deposit tokens)
     pub fn fund_order(&mut self, order_id: String) -> Promise {
+        assert_enough_gas(GAS_FOR_FT_TRANSFER);
         let mut order = self.orders.get(&order_id).expect("Order not found");
         assert_eq!(order.status, OrderStatus::Pending, "Order must be pending");
         assert_eq!(
@@ -224,7 +452,7 @@ impl FusionEscrow {
         );
 
         order.status = OrderStatus::Funded;
-        self.orders.insert(&order_id, &order);
+        self.internal_save_order(&order_id, &order);
 
         // Transfer tokens from maker to contract
         ext_ft::ext(order.from_token.clone())
@@ -233,8 +461,64 @@ impl FusionEscrow {
             .ft_transfer(env::current_account_id(), order.from_amount, Some(format!("Fund order {}", order_id)))
     }
 
+    // Create and fund an order with attached NEAR, wrapped into wNEAR
+    #[payable]
+    pub fn create_order_with_near(&mut self, args: CreateOrderArgs) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE);
+        let wnear = self.wnear_contract.clone().expect("No wNEAR contract configured");
+        let deposit = env::attached_deposit();
+        assert!(!deposit.is_zero(), "Attach NEAR to fund the order");
+        assert_eq!(args.from_token, wnear, "Order must be in wNEAR");
+        assert_eq!(args.from_amount.0, deposit.as_yoctonear(), "Order amount must match the attached deposit");
+
+        let order_id = self.create_order(args);
+
+        // Wrap the deposit; the minted wNEAR is credited to this contract.
+        // The order stays Pending, and unclaimable, until the wrap has succeeded.
+        ext_wnear::ext(wnear)
+            .with_attached_deposit(deposit)
+            .with_static_gas(GAS_FOR_NEAR_DEPOSIT)
+            .near_deposit()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_near_wrapped(order_id),
+            )
+    }
+
+    // Mark the order funded once its NEAR is wrapped, or refund the NEAR if wrapping failed
+    #[private]
+    pub fn on_near_wrapped(&mut self, order_id: String) -> bool {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        if order.status != OrderStatus::Pending {
+            return false;
+        }
+
+        if env::promise_result_checked(0, 0).is_ok() {
+            order.status = OrderStatus::Funded;
+            self.internal_save_order(&order_id, &order);
+            return true;
+        }
+
+        order.status = OrderStatus::Refunded;
+        self.internal_save_order(&order_id, &order);
+        drop(Promise::new(order.maker).transfer(NearToken::from_yoctonear(order.from_amount.0)));
+        false
+    }
+
     // Claim tokens using secret
     pub fn claim_order(&mut self, order_id: String, secret: String) -> Promise {
+        let taker = env::predecessor_account_id();
+        self.internal_claim_order(order_id, secret, taker)
+    }
+
+    // Claim tokens using secret, sending the payout to `recipient`
+    pub fn claim_order_to(&mut self, order_id: String, secret: String, recipient: AccountId) -> Promise {
+        self.internal_claim_order(order_id, secret, recipient)
+    }
+
+    fn internal_claim_order(&mut self, order_id: String, secret: String, recipient: AccountId) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE);
         let mut order = self.orders.get(&order_id).expect("Order not found");
         assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
         assert_eq!(
@@ -243,6 +527,17 @@ impl FusionEscrow {
             "Only taker can claim order"
         );
 
+        // Reject secrets shorter than committed at creation; later minimum changes don't apply
+        assert!(
+            secret.len() >= order.secret_length as usize,
+            "Secret must be at least {} bytes",
+            order.secret_length
+        );
+        assert!(env::block_timestamp() < order.expires_at.0, "Order expired");
+        if let Some(claim_deadline) = order.claim_deadline {
+            assert!(env::block_timestamp() < claim_deadline.0, "Claim deadline passed");
+        }
+
         // Verify hashlock matches secret
         let computed_hashlock = env::sha256(secret.as_bytes());
         assert_eq!(
@@ -253,26 +548,58 @@ impl FusionEscrow {
 
         order.status = OrderStatus::Claimed;
         order.secret = Some(secret);
-        self.orders.insert(&order_id, &order);
+        self.internal_save_order(&order_id, &order);
 
-        // Calculate fee
-        let fee_amount = (order.from_amount.0 * self.fee_rate as u128) / 10000;
+        // Calculate fee, net of the part rebated to the taker with the payout
+        let gross_fee = self.near_fee_for(&order);
+        let rebate_amount = gross_fee * self.taker_rebate_bps as u128 / 10000;
+        let fee_amount = gross_fee - rebate_amount;
         let transfer_amount = order.from_amount.0 - fee_amount;
 
         // Update statistics
         self.total_swaps += 1;
         self.total_volume = U128(self.total_volume.0 + order.from_amount.0);
         self.total_fees = U128(self.total_fees.0 + fee_amount);
+        if order.cross_chain {
+            self.cross_chain_fees = U128(self.cross_chain_fees.0 + fee_amount);
+        }
 
-        // Transfer tokens to taker
+        // Transfer tokens to recipient
         ext_ft::ext(order.from_token.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(order.taker.clone(), U128(transfer_amount), Some(format!("Claim order {}", order_id)))
+            .ft_transfer(recipient, U128(transfer_amount), Some(format!("Claim order {}", order_id.clone())))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_claim_transfer_resolved(order_id, U128(fee_amount)),
+            )
+    }
+
+    // Put a claimed order back to funded if the payout transfer failed
+    #[private]
+    pub fn on_claim_transfer_resolved(&mut self, order_id: String, fee_amount: U128) -> bool {
+        if env::promise_result_checked(0, 0).is_ok() {
+            return true;
+        }
+
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        order.status = OrderStatus::Funded;
+        order.secret = None;
+        self.internal_save_order(&order_id, &order);
+
+        self.total_swaps -= 1;
+        self.total_volume = U128(self.total_volume.0 - order.from_amount.0);
+        self.total_fees = U128(self.total_fees.0 - fee_amount.0);
+        if order.cross_chain {
+            self.cross_chain_fees = U128(self.cross_chain_fees.0 - fee_amount.0);
+        }
+        false
     }
 
     // Refund tokens if timelock expired
     pub fn refund_order(&mut self, order_id: String) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE);
         let mut order = self.orders.get(&order_id).expect("Order not found");
         assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
         assert_eq!(
@@ -286,13 +613,257 @@ impl FusionEscrow {
         );
 
         order.status = OrderStatus::Refunded;
-        self.orders.insert(&order_id, &order);
+        self.internal_save_order(&order_id, &order);
+
+        // Return tokens to maker
+        ext_ft::ext(order.from_token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(order.maker.clone(), order.from_amount, Some(format!("Refund order {}", order_id.clone())))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_refund_transfer_resolved(order_id),
+            )
+    }
+
+    // Put a refunded order back to funded if the refund transfer failed
+    #[private]
+    pub fn on_refund_transfer_resolved(&mut self, order_id: String) -> bool {
+        if env::promise_result_checked(0, 0).is_ok() {
+            return true;
+        }
+
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        order.status = OrderStatus::Funded;
+        self.internal_save_order(&order_id, &order);
+        false
+    }
+
+    // Freeze a funded order until the arbiter rules, escrowing the dispute bond
+    #[payable]
+    pub fn open_dispute(&mut self, order_id: String) {
+        assert!(self.arbiter.is_some(), "No arbiter configured");
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
+        
+        let opened_by = env::predecessor_account_id();
+        assert!(
+            opened_by == order.maker || opened_by == order.taker,
+            "Only maker or taker can open a dispute"
+        );
+        let bond = env::attached_deposit().as_yoctonear();
+        assert!(
+            bond >= self.dispute_bond.0,
+            "Attach at least {} yoctoNEAR as dispute bond",
+            self.dispute_bond.0
+        );
+
+        order.status = OrderStatus::Disputed;
+        self.internal_save_order(&order_id, &order);
+        self.disputes.insert(&order_id, &Dispute {
+            opened_by,
+            bond: U128(bond),
+            winner: None,
+            opened_at: U64(env::block_timestamp()),
+        });
+        self.open_disputes += 1;
+    }
+
+    // Release a disputed order to `winner`; the bond settles once the transfer succeeds
+    pub fn resolve_dispute(&mut self, order_id: String, winner: AccountId) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE);
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.arbiter,
+            "Only arbiter can resolve disputes"
+        );
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Disputed, "Order is not disputed");
+        assert!(
+            winner == order.maker || winner == order.taker,
+            "Winner must be maker or taker"
+        );
+
+        order.status = if winner == order.taker {
+            OrderStatus::Claimed
+        } else {
+            OrderStatus::Refunded
+        };
+        self.internal_save_order(&order_id, &order);
+        let mut dispute = self.disputes.get(&order_id).expect("Dispute not found");
+        dispute.winner = Some(winner.clone());
+        self.disputes.insert(&order_id, &dispute);
+
+        ext_ft::ext(order.from_token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(winner, order.from_amount, Some(format!("Resolve dispute {}", order_id.clone())))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_dispute_transfer_resolved(order_id),
+            )
+    }
+
+    // Pay out the dispute bond, or reopen the dispute if the transfer failed
+    #[private]
+    pub fn on_dispute_transfer_resolved(&mut self, order_id: String) -> bool {
+        let mut dispute = self.disputes.get(&order_id).expect("Dispute not found");
+        if env::promise_result_checked(0, 0).is_err() {
+            let mut order = self.orders.get(&order_id).expect("Order not found");
+            order.status = OrderStatus::Disputed;
+            self.internal_save_order(&order_id, &order);
+            dispute.winner = None;
+            self.disputes.insert(&order_id, &dispute);
+            return false;
+        }
+
+        // A losing opener forfeits the bond to the arbiter
+        let winner = dispute.winner.clone().expect("Dispute not resolved");
+        let bond_receiver = if winner == dispute.opened_by {
+            dispute.opened_by.clone()
+        } else {
+            self.arbiter.clone().unwrap_or(winner)
+        };
+        self.disputes.remove(&order_id);
+        self.open_disputes -= 1;
+        drop(Promise::new(bond_receiver).transfer(NearToken::from_yoctonear(dispute.bond.0)));
+        true
+    }
+
+    // Register the ed25519 key used to consent to mutual cancellations
+    pub fn register_consent_key(&mut self, public_key: PublicKey) {
+        assert!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Consent key must be an ed25519 key"
+        );
+        self.consent_keys.insert(&env::predecessor_account_id(), &public_key);
+    }
+
+    // Digest the taker must sign for `mutual_cancel` to accept their consent
+    pub fn mutual_cancel_hash(&self, order_id: String) -> String {
+        hex::encode(mutual_cancel_digest(&order_id))
+    }
+
+    // Refund a funded order before expiry, with the taker's signed consent (called by maker)
+    pub fn mutual_cancel(&mut self, order_id: String, taker_signature: Base64VecU8) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE);
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
+        assert_eq!(
+            env::predecessor_account_id(),
+            order.maker,
+            "Only maker can cancel order"
+        );
+
+        let public_key = self.consent_keys.get(&order.taker).expect("Taker consent key not registered");
+        let signature: [u8; 64] = taker_signature.0.try_into().expect("Invalid signature length");
+        let key_bytes: [u8; 32] = public_key.as_bytes()[1..].try_into().expect("Invalid consent key");
+        assert!(
+            env::ed25519_verify(&signature, mutual_cancel_digest(&order_id), &key_bytes),
+            "Invalid taker signature"
+        );
+
+        order.status = OrderStatus::Refunded;
+        self.internal_save_order(&order_id, &order);
 
         // Return tokens to maker
         ext_ft::ext(order.from_token.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(order.maker.clone(), order.from_amount, Some(format!("Refund order {}", order_id)))
+            .ft_transfer(order.maker.clone(), order.from_amount, Some(format!("Cancel order {}", order_id.clone())))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_refund_transfer_resolved(order_id),
+            )
+    }
+
+    // Reassign the taker of an order that has not been funded yet
+    pub fn set_taker(&mut self, order_id: String, new_taker: AccountId) {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order must be pending");
+        assert_eq!(
+            env::predecessor_account_id(),
+            order.maker,
+            "Only maker can set taker"
+        );
+
+        order.taker = new_taker;
+        self.internal_save_order(&order_id, &order);
+    }
+
+    // Require the taker to claim within `claim_window` seconds of creation; refund still waits for expiry
+    pub fn set_claim_deadline(&mut self, order_id: String, claim_window: U64) {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order must be pending");
+        assert_eq!(
+            env::predecessor_account_id(),
+            order.maker,
+            "Only maker can set claim deadline"
+        );
+
+        let claim_deadline = claim_window
+            .0
+            .checked_mul(NANOS_PER_SECOND)
+            .and_then(|nanos| order.created_at.0.checked_add(nanos))
+            .expect("Claim deadline overflow");
+        assert!(claim_deadline < order.expires_at.0, "Claim deadline must be before expiry");
+
+        order.claim_deadline = Some(U64(claim_deadline));
+        self.internal_save_order(&order_id, &order);
+    }
+
+    // Refund an expired order on the maker's behalf, paying the caller a bounty
+    pub fn refund_order_for(&mut self, order_id: String) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE.saturating_add(GAS_FOR_FT_TRANSFER));
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
+        assert!(order.refund_bounty_opt_in, "Maker has not opted in to refund bounty");
+        let grace_period = self.refund_grace_period.0.saturating_mul(NANOS_PER_SECOND);
+        assert!(
+            env::block_timestamp() >= order.expires_at.0.saturating_add(grace_period),
+            "Refund grace period not over"
+        );
+
+        order.status = OrderStatus::Refunded;
+        self.internal_save_order(&order_id, &order);
+
+        let bounty = (order.from_amount.0 * self.refund_bounty_rate as u128) / 10000;
+        let refund_amount = order.from_amount.0 - bounty;
+
+        // Return the remainder to maker; the bounty is paid once that transfer succeeds
+        ext_ft::ext(order.from_token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(order.maker.clone(), U128(refund_amount), Some(format!("Refund order {}", order_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER.saturating_add(GAS_FOR_FT_TRANSFER))
+                    .on_refund_for_resolved(order_id, env::predecessor_account_id(), U128(bounty)),
+            )
+    }
+
+    // Pay the refunder's bounty, or put the order back to funded if the maker's refund failed
+    #[private]
+    pub fn on_refund_for_resolved(&mut self, order_id: String, refunder: AccountId, bounty: U128) -> bool {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        if env::promise_result_checked(0, 0).is_err() {
+            order.status = OrderStatus::Funded;
+            self.internal_save_order(&order_id, &order);
+            return false;
+        }
+
+        if bounty.0 > 0 {
+            drop(
+                ext_ft::ext(order.from_token)
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .with_static_gas(GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(refunder, bounty, Some(format!("Refund bounty for order {}", order_id))),
+            );
+        }
+        true
     }
 
     // Create cross-chain swap
@@ -309,8 +880,28 @@ impl FusionEscrow {
         hashlock: String,
         timelock: U64,
     ) -> String {
+        assert!(timelock.0 <= MAX_TIMELOCK_SECONDS, "Timelock exceeds maximum");
+        assert!(self.supported_chains.contains(&from_chain), "Unsupported from chain");
+        assert!(self.supported_chains.contains(&to_chain), "Unsupported to chain");
+        assert!(
+            self.max_cross_chain_amount.0 == 0 || from_amount.0 <= self.max_cross_chain_amount.0,
+            "Swap amount exceeds the cross-chain maximum"
+        );
+        
         let near_account = env::predecessor_account_id();
-        let swap_id = format!("swap_{}_{}", near_account, env::block_timestamp());
+        // Keyed by account too, so nobody can claim another user's EVM order hash first
+        let index_key = (evm_order_hash.clone(), near_account.clone());
+        assert!(
+            !self.evm_hash_index.contains_key(&index_key),
+            "Swap already exists for EVM order"
+        );
+        
+        // Relayers can derive the id from the EVM order hash and the NEAR account
+        let swap_id = format!(
+            "swap_{}",
+            hex::encode(env::sha256(format!("{}:{}", evm_order_hash, near_account).as_bytes()))
+        );
+        self.evm_hash_index.insert(&index_key, &swap_id);
         
         let swap = CrossChainSwap {
             evm_order_hash,
@@ -328,13 +919,34 @@ impl FusionEscrow {
             timelock,
             status: SwapStatus::Initiated,
             created_at: U64(env::block_timestamp()),
-            expires_at: U64(env::block_timestamp() + timelock.0 * 1_000_000_000),
+            expires_at: U64(timelock_expiry(timelock)),
         };
 
         self.cross_chain_swaps.insert(&swap_id, &swap);
         swap_id
     }
 
+    // Link a cross-chain swap to the NEAR escrow order backing it
+    pub fn link_swap_order(&mut self, swap_id: String, order_id: String) {
+        let mut swap = self.cross_chain_swaps.get(&swap_id).expect("Swap not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            swap.near_account,
+            "Only swap owner can link order"
+        );
+        assert!(swap.near_order_id.is_empty(), "Swap already linked");
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        // Linking lowers the fee to the NEAR share, so only the maker may do it, before funding
+        assert_eq!(order.maker, swap.near_account, "Only order maker can link order");
+        assert_eq!(order.status, OrderStatus::Pending, "Only pending orders can be linked");
+        assert!(!order.cross_chain, "Order already linked to a swap");
+
+        order.cross_chain = true;
+        self.internal_save_order(&order_id, &order);
+        swap.near_order_id = order_id;
+        self.cross_chain_swaps.insert(&swap_id, &swap);
+    }
+
     // Update cross-chain swap status
     pub fn update_swap_status(&mut self, swap_id: String, status: SwapStatus) {
         assert_eq!(
@@ -348,6 +960,64 @@ impl FusionEscrow {
         self.cross_chain_swaps.insert(&swap_id, &swap);
     }
 
+    // Refund the NEAR leg of a failed swap to its maker, withholding the relayer reimbursement
+    pub fn refund_failed_swap(&mut self, swap_id: String) -> Promise {
+        assert_enough_gas(GAS_FOR_TRANSFER_AND_RESOLVE.saturating_add(GAS_FOR_FT_TRANSFER));
+        let swap = self.cross_chain_swaps.get(&swap_id).expect("Swap not found");
+        assert_eq!(swap.status, SwapStatus::Failed, "Swap must be failed");
+        assert!(!swap.near_order_id.is_empty(), "Swap has no linked order");
+        let mut order = self.orders.get(&swap.near_order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Funded, "Order must be funded");
+        assert_eq!(
+            env::predecessor_account_id(),
+            order.maker,
+            "Only maker can refund failed swap"
+        );
+
+        order.status = OrderStatus::Refunded;
+        self.internal_save_order(&order.id, &order);
+
+        let reimbursement = (order.from_amount.0 * self.relayer_reimbursement_rate as u128) / 10000;
+        let refund_amount = order.from_amount.0 - reimbursement;
+
+        // The relayer is reimbursed only once the maker's refund has succeeded
+        ext_ft::ext(order.from_token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(order.maker.clone(), U128(refund_amount), Some(format!("Refund failed swap {}", swap_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER.saturating_add(GAS_FOR_FT_TRANSFER))
+                    .on_failed_swap_refund_resolved(swap_id, order.id, U128(reimbursement)),
+            )
+    }
+
+    // Reimburse the relayer, or put the order back to funded if the maker's refund failed
+    #[private]
+    pub fn on_failed_swap_refund_resolved(&mut self, swap_id: String, order_id: String, reimbursement: U128) -> bool {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        if env::promise_result_checked(0, 0).is_err() {
+            order.status = OrderStatus::Funded;
+            self.internal_save_order(&order_id, &order);
+            return false;
+        }
+
+        // The owner relays swap status, so it is reimbursed for the failed leg
+        if reimbursement.0 > 0 {
+            drop(
+                ext_ft::ext(order.from_token)
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .with_static_gas(GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(
+                        self.owner.clone(),
+                        reimbursement,
+                        Some(format!("Relayer reimbursement for swap {}", swap_id)),
+                    ),
+            );
+        }
+        true
+    }
+
     // Get quote for swap
     pub fn get_quote(
         &self,
@@ -355,9 +1025,27 @@ impl FusionEscrow {
         to_token: AccountId,
         from_amount: U128,
     ) -> String {
-        // Mock quote - in production this would query DEX APIs
-        let to_amount = U128((from_amount.0 * 98) / 100); // 2% slippage
-        let price = "1.0".to_string();
+        // Use a fresh oracle price when one is reported, otherwise fall back to the mock quote
+        let (to_amount, price) = match self.oracle_prices.get(&price_key(&from_token, &to_token)) {
+            Some(oracle_price) => {
+                let age = env::block_timestamp().saturating_sub(oracle_price.timestamp.0);
+                assert!(
+                    age <= self.max_price_age.0.saturating_mul(NANOS_PER_SECOND),
+                    "Oracle price is stale"
+                );
+                (
+                    U128(
+                        from_amount.0
+                            .checked_mul(oracle_price.price.0)
+                            .expect("Quote overflow")
+                            / PRICE_DENOMINATOR,
+                    ),
+                    format_price(oracle_price.price.0),
+                )
+            }
+            // Mock quote - in production this would query DEX APIs
+            None => (U128((from_amount.0 * 98) / 100), "1.0".to_string()), // 2% slippage
+        };
         let gas_estimate = U128(30_000_000_000_000); // 30 TGas
         
         serde_json::to_string(&FusionQuote {
@@ -379,23 +1067,336 @@ impl FusionEscrow {
         }).unwrap_or_default()
     }
 
-    // View methods
-    pub fn get_order(&self, order_id: String) -> String {
-        serde_json::to_string(&self.orders.get(&order_id)).unwrap_or_default()
-    }
-
-    pub fn get_swap(&self, swap_id: String) -> String {
-        serde_json::to_string(&self.cross_chain_swaps.get(&swap_id)).unwrap_or_default()
-    }
+    // Record the latest oracle price for a token pair
+    pub fn report_price(
+        &mut self,
+        from_token: AccountId,
+        to_token: AccountId,
+        price: U128,
+        timestamp: U64,
+    ) {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.oracle,
+            "Only oracle can report prices"
+        );
+        assert!(price.0 > 0, "Price must be positive");
+        assert!(timestamp.0 <= env::block_timestamp(), "Price timestamp is in the future");
+        
+        self.oracle_prices.insert(
+            &price_key(&from_token, &to_token),
+            &OraclePrice { price, timestamp },
+        );
+    }
+
+    pub fn get_oracle_price(&self, from_token: AccountId, to_token: AccountId) -> Option<OraclePrice> {
+        self.oracle_prices.get(&price_key(&from_token, &to_token))
+    }
+
+    // View methods
+    pub fn get_order(&self, order_id: String) -> String {
+        serde_json::to_string(&self.orders.get(&order_id)).unwrap_or_default()
+    }
+
+    // Look an order id up here first, then in the solver contract
+    pub fn resolve_order(&self, order_id: String) -> PromiseOrValue<Option<OrderView>> {
+        if let Some(order) = self.orders.get(&order_id) {
+            return PromiseOrValue::Value(Some(OrderView {
+                id: order.id,
+                source: OrderSource::Escrow,
+                maker: order.maker,
+                from_token: order.from_token,
+                to_token: order.to_token,
+                from_amount: order.from_amount,
+                to_amount: order.to_amount,
+                status: format!("{:?}", order.status),
+                created_at: order.created_at,
+            }));
+        }
+        
+        let solver_contract = self.solver_contract.clone().expect("Solver contract not set");
+        ext_solver::ext(solver_contract)
+            .with_static_gas(GAS_FOR_SOLVER_VIEW)
+            .get_order(order_id)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_ORDER)
+                    .on_solver_order(),
+            )
+            .into()
+    }
+
+    #[private]
+    pub fn on_solver_order(&self, #[callback_unwrap] order_json: String) -> Option<OrderView> {
+        let order = serde_json::from_str::<Option<SolverOrder>>(&order_json).ok().flatten()?;
+        Some(OrderView {
+            id: order.id,
+            source: OrderSource::Solver,
+            maker: order.user,
+            from_token: order.from_token,
+            to_token: order.to_token,
+            from_amount: order.from_amount,
+            to_amount: order.to_amount,
+            status: order.status,
+            created_at: order.created_at,
+        })
+    }
+
+    pub fn get_swap(&self, swap_id: String) -> String {
+        serde_json::to_string(&self.cross_chain_swaps.get(&swap_id)).unwrap_or_default()
+    }
+
+    pub fn get_swap_statuses(&self, ids: Vec<String>) -> Vec<Option<SwapStatus>> {
+        assert!(
+            ids.len() <= MAX_SWAPS_PER_BATCH,
+            "Cannot fetch more than {} swaps at once",
+            MAX_SWAPS_PER_BATCH
+        );
+        ids.iter()
+            .map(|id| self.cross_chain_swaps.get(id).map(|swap| swap.status))
+            .collect()
+    }
+
+    pub fn get_swap_by_evm_hash(&self, evm_order_hash: String, near_account: AccountId) -> Option<CrossChainSwap> {
+        self.evm_hash_index
+            .get(&(evm_order_hash, near_account))
+            .and_then(|swap_id| self.cross_chain_swaps.get(&swap_id))
+    }
+
+    pub fn get_swap_with_order(&self, swap_id: String) -> (CrossChainSwap, Option<EscrowOrder>) {
+        let swap = self.cross_chain_swaps.get(&swap_id).expect("Swap not found");
+        let order = if swap.near_order_id.is_empty() {
+            None
+        } else {
+            self.orders.get(&swap.near_order_id)
+        };
+        (swap, order)
+    }
 
     pub fn get_user_orders(&self, account_id: AccountId) -> Vec<String> {
         self.user_orders.get(&account_id).unwrap_or_default()
     }
 
+    // Whether `caller` could claim the order right now
+    pub fn is_claimable(&self, order_id: String, caller: AccountId) -> bool {
+        let Some(order) = self.orders.get(&order_id) else {
+            return false;
+        };
+        let now = env::block_timestamp();
+        order.status == OrderStatus::Funded
+            && caller == order.taker
+            && now < order.expires_at.0
+            && order.claim_deadline.is_none_or(|deadline| now < deadline.0)
+    }
+
+    // Estimated storage staking cost of a new order and its maker index entry
+    pub fn storage_cost_for_order(&self) -> U128 {
+        let bytes = self.storage_bytes_for_order();
+        U128(bytes as u128 * env::storage_byte_cost().as_yoctonear())
+    }
+
+    // Fee taken on NEAR when claiming `order`; cross-chain orders only pay the NEAR share
+    fn near_fee_for(&self, order: &EscrowOrder) -> u128 {
+        let fee_amount = (order.from_amount.0 * self.fee_rate as u128) / 10000;
+        if order.cross_chain {
+            fee_amount * self.near_fee_share_bps as u128 / 10000
+        } else {
+            fee_amount
+        }
+    }
+
+    // Upper bound on the bytes `create_order` writes, sized for the longest account ids
+    fn storage_bytes_for_order(&self) -> u64 {
+        let any_account: AccountId = "a".repeat(MAX_ACCOUNT_ID_LEN).parse().unwrap();
+        let order_id = format!("order_{}_{}", any_account, u64::MAX);
+        let order = EscrowOrder {
+            id: order_id.clone(),
+            maker: any_account.clone(),
+            taker: any_account.clone(),
+            from_token: any_account.clone(),
+            to_token: any_account.clone(),
+            from_amount: U128(0),
+            to_amount: U128(0),
+            hashlock: "0".repeat(64),
+            secret: None,
+            secret_length: 0,
+            timelock: U64(0),
+            refund_bounty_opt_in: false,
+            cross_chain: false,
+            status: OrderStatus::Pending,
+            created_at: U64(0),
+            claim_deadline: None,
+            expires_at: U64(0),
+            memo: Some("a".repeat(MAX_MEMO_LEN)), // Quote for the largest memo
+        };
+        let key_len = borsh::to_vec(&order_id).unwrap().len() as u64;
+        let value_len = borsh::to_vec(&order).unwrap().len() as u64;
+        
+        // UnorderedMap keeps key -> index, index -> key and index -> value records
+        let order_bytes = (1 + key_len + 8) + (1 + 8 + key_len) + (1 + 8 + value_len) + 3 * STORAGE_RECORD_OVERHEAD;
+        
+        // Covers a maker's first order, which creates their order index rather than growing it by one id
+        let index_bytes = 1 + borsh::to_vec(&any_account).unwrap().len() as u64 + 4 + key_len + STORAGE_RECORD_OVERHEAD;
+        
+        order_bytes + index_bytes
+    }
+
+    // Funded orders of `account_id`, flagging those past expiry as refundable
+    pub fn get_user_active_orders(&self, account_id: AccountId) -> Vec<ActiveOrder> {
+        self.user_orders
+            .get(&account_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|order_id| self.orders.get(order_id))
+            .filter(|order| order.status == OrderStatus::Funded)
+            .map(|order| ActiveOrder {
+                refundable: env::block_timestamp() >= order.expires_at.0,
+                order,
+            })
+            .collect()
+    }
+
+    // Total amount of `token` the contract currently owes to funded orders
+    pub fn get_token_obligations(&self, token: AccountId) -> U128 {
+        U128(self.token_obligations.get(&token).unwrap_or(0))
+    }
+
+    // Store an order, moving its escrowed amount in or out of the token obligations as its status changes
+    fn internal_save_order(&mut self, order_id: &str, order: &EscrowOrder) {
+        if let Some(previous) = self.orders.insert(&order_id.to_string(), order) {
+            self.internal_release_obligation(&previous.from_token, order_obligation(&previous));
+        }
+        self.internal_add_obligation(&order.from_token, order_obligation(order));
+    }
+
+    fn internal_add_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.get_token_obligations(token.clone()).0;
+            self.token_obligations.insert(token, &(obligations + amount));
+        }
+    }
+
+    fn internal_release_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.get_token_obligations(token.clone()).0;
+            self.token_obligations.insert(token, &obligations.saturating_sub(amount));
+        }
+    }
+
+    pub fn get_dispute(&self, order_id: String) -> Option<Dispute> {
+        self.disputes.get(&order_id)
+    }
+
     pub fn get_statistics(&self) -> (u64, U128, U128) {
         (self.total_swaps, self.total_volume, self.total_fees)
     }
 
+    pub fn get_cross_chain_fees(&self) -> U128 {
+        self.cross_chain_fees
+    }
+
+    pub fn get_archived_order_stats(&self) -> ArchivedOrderStats {
+        self.archived_orders.clone()
+    }
+
+    pub fn get_require_creator_proof(&self) -> bool {
+        self.require_creator_proof
+    }
+
+    pub fn get_wnear_contract(&self) -> Option<AccountId> {
+        self.wnear_contract.clone()
+    }
+
+    pub fn get_supported_chains(&self) -> Vec<String> {
+        self.supported_chains.to_vec()
+    }
+
+    pub fn is_pair_paused(&self, from_token: AccountId, to_token: AccountId) -> bool {
+        self.paused_pairs.get(&(from_token, to_token)).unwrap_or(false)
+    }
+
+    // Configuration problems a deploy script should fix before opening the contract; empty when healthy
+    pub fn self_test(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.fee_rate > MAX_FEE_BPS {
+            problems.push(format!("fee_rate {} exceeds {} bps", self.fee_rate, MAX_FEE_BPS));
+        }
+        if self.near_fee_share_bps > 10000 {
+            problems.push(format!("near_fee_share_bps {} exceeds 10000 bps", self.near_fee_share_bps));
+        }
+        if self.refund_bounty_rate > MAX_FEE_BPS {
+            problems.push(format!("refund_bounty_rate {} exceeds {} bps", self.refund_bounty_rate, MAX_FEE_BPS));
+        }
+        if self.relayer_reimbursement_rate > MAX_FEE_BPS {
+            problems.push(format!(
+                "relayer_reimbursement_rate {} exceeds {} bps",
+                self.relayer_reimbursement_rate, MAX_FEE_BPS
+            ));
+        }
+        if self.taker_rebate_bps > 10000 {
+            problems.push(format!("taker_rebate_bps {} exceeds 10000 bps", self.taker_rebate_bps));
+        }
+        if self.min_timelock.0 >= self.max_timelock.0 {
+            problems.push(format!(
+                "min_timelock {} is not below max_timelock {}",
+                self.min_timelock.0, self.max_timelock.0
+            ));
+        }
+        if self.max_timelock.0 > MAX_TIMELOCK_SECONDS {
+            problems.push(format!("max_timelock {} exceeds {} seconds", self.max_timelock.0, MAX_TIMELOCK_SECONDS));
+        }
+        if let Some(wnear) = &self.wnear_contract {
+            if !self.supported_tokens.get(wnear).unwrap_or(false) {
+                problems.push(format!("wnear_contract {} is not a supported token", wnear));
+            }
+        }
+
+        problems
+    }
+
+    // Remove claimed, refunded or expired orders past the retention period, keeping only their totals.
+    // Ids that are unknown, still live or too recent are skipped; returns how many were removed.
+    pub fn cleanup_terminal_orders(&mut self, order_ids: Vec<String>) -> u64 {
+        assert!(order_ids.len() <= MAX_ORDERS_PER_CLEANUP, "Too many orders in one cleanup");
+        let usage_before = env::storage_usage();
+        let now = env::block_timestamp();
+        let retention = self.order_retention_period.0.saturating_mul(NANOS_PER_SECOND);
+        let mut removed = 0;
+        
+        for order_id in order_ids {
+            let Some(order) = self.orders.get(&order_id) else {
+                continue;
+            };
+            if now < order.expires_at.0.saturating_add(retention) {
+                continue;
+            }
+            match order.status {
+                OrderStatus::Claimed => self.archived_orders.claimed += 1,
+                OrderStatus::Refunded => self.archived_orders.refunded += 1,
+                OrderStatus::Expired => self.archived_orders.expired += 1,
+                _ => continue,
+            }
+            self.archived_orders.volume = U128(self.archived_orders.volume.0 + order.from_amount.0);
+            
+            self.orders.remove(&order_id);
+            if let Some(mut user_orders) = self.user_orders.get(&order.maker) {
+                user_orders.retain(|id| id != &order_id);
+                if user_orders.is_empty() {
+                    self.user_orders.remove(&order.maker);
+                } else {
+                    self.user_orders.insert(&order.maker, &user_orders);
+                }
+            }
+            removed += 1;
+        }
+        
+        // Makers never prepaid storage, so the freed bytes return to the contract's own balance
+        let freed = usage_before.saturating_sub(env::storage_usage());
+        env::log_str(&format!("Cleaned up {} orders, freeing {} bytes", removed, freed));
+        removed
+    }
+
     // Admin methods
     pub fn add_supported_token(&mut self, token: AccountId) {
         assert_eq!(
@@ -415,16 +1416,258 @@ impl FusionEscrow {
         self.supported_tokens.remove(&token);
     }
 
+    pub fn add_supported_chain(&mut self, chain: String) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can add supported chains"
+        );
+        self.supported_chains.insert(&chain);
+    }
+
+    pub fn remove_supported_chain(&mut self, chain: String) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can remove supported chains"
+        );
+        self.supported_chains.remove(&chain);
+    }
+
+    // Stop new orders from `from_token` to `to_token`; existing orders are unaffected
+    pub fn pause_pair(&mut self, from_token: AccountId, to_token: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can pause token pairs"
+        );
+        self.paused_pairs.insert(&(from_token, to_token), &true);
+    }
+
+    pub fn unpause_pair(&mut self, from_token: AccountId, to_token: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can unpause token pairs"
+        );
+        self.paused_pairs.remove(&(from_token, to_token));
+    }
+
+    pub fn add_supported_tokens(&mut self, tokens: Vec<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can add supported tokens"
+        );
+        let mut tokens = tokens;
+        tokens.sort();
+        tokens.dedup();
+        for token in tokens {
+            self.supported_tokens.insert(&token, &true);
+        }
+    }
+
+    pub fn remove_supported_tokens(&mut self, tokens: Vec<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can remove supported tokens"
+        );
+        let mut tokens = tokens;
+        tokens.sort();
+        tokens.dedup();
+        for token in tokens {
+            self.supported_tokens.remove(&token);
+        }
+    }
+
+    // Rescue tokens sent to the contract that no tracked obligation accounts for
+    pub fn recover_tokens(&mut self, token: AccountId, amount: U128, to: AccountId) -> Promise {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can recover tokens"
+        );
+        assert_enough_gas(GAS_FOR_FT_BALANCE.saturating_add(GAS_FOR_RECOVER_CALLBACK));
+
+        ext_ft::ext(token.clone())
+            .with_static_gas(GAS_FOR_FT_BALANCE)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RECOVER_CALLBACK)
+                    .on_recover_tokens_balance(token, amount, to),
+            )
+    }
+
+    #[private]
+    pub fn on_recover_tokens_balance(
+        &mut self,
+        token: AccountId,
+        amount: U128,
+        to: AccountId,
+        #[callback_unwrap] balance: U128,
+    ) -> Promise {
+        let obligations = self.get_token_obligations(token.clone()).0;
+        assert!(
+            balance.0.saturating_sub(obligations) >= amount.0,
+            "Recovery would dip into tracked obligations"
+        );
+
+        ext_ft::ext(token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(to, amount, Some("Recover tokens".to_string()))
+    }
+
     pub fn set_fee_rate(&mut self, fee_rate: u32) {
         assert_eq!(
             env::predecessor_account_id(),
             self.owner,
             "Only owner can set fee rate"
         );
-        assert!(fee_rate <= 1000, "Fee rate cannot exceed 10%");
+        assert!(fee_rate <= MAX_FEE_BPS, "Fee rate cannot exceed 10%");
         self.fee_rate = fee_rate;
     }
 
+    pub fn set_near_fee_share(&mut self, near_fee_share_bps: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set NEAR fee share"
+        );
+        assert!(near_fee_share_bps <= 10000, "NEAR fee share cannot exceed 100%");
+        self.near_fee_share_bps = near_fee_share_bps;
+    }
+
+    pub fn set_min_secret_length(&mut self, min_secret_length: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set min secret length"
+        );
+        self.min_secret_length = min_secret_length;
+    }
+
+    pub fn set_refund_bounty(&mut self, refund_bounty_rate: u32, refund_grace_period: U64) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set refund bounty"
+        );
+        assert!(refund_bounty_rate <= MAX_FEE_BPS, "Refund bounty cannot exceed 10%");
+        self.refund_bounty_rate = refund_bounty_rate;
+        self.refund_grace_period = refund_grace_period;
+    }
+
+    pub fn set_relayer_reimbursement(&mut self, relayer_reimbursement_rate: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set relayer reimbursement"
+        );
+        assert!(relayer_reimbursement_rate <= MAX_FEE_BPS, "Relayer reimbursement cannot exceed 10%");
+        self.relayer_reimbursement_rate = relayer_reimbursement_rate;
+    }
+
+    pub fn set_taker_rebate(&mut self, taker_rebate_bps: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set taker rebate"
+        );
+        assert!(taker_rebate_bps <= 10000, "Taker rebate cannot exceed the fee");
+        self.taker_rebate_bps = taker_rebate_bps;
+    }
+
+    // Ask frontends to have makers confirm they hold the preimage; the contract cannot check it
+    pub fn set_require_creator_proof(&mut self, require_creator_proof: bool) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set creator proof requirement"
+        );
+        self.require_creator_proof = require_creator_proof;
+    }
+
+    pub fn set_oracle(&mut self, oracle: Option<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set oracle"
+        );
+        self.oracle = oracle;
+    }
+
+    pub fn set_solver_contract(&mut self, solver_contract: Option<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set solver contract"
+        );
+        self.solver_contract = solver_contract;
+    }
+
+    pub fn set_wnear_contract(&mut self, wnear_contract: Option<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set wNEAR contract"
+        );
+        self.wnear_contract = wnear_contract;
+    }
+
+    pub fn set_arbiter(&mut self, arbiter: Option<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set arbiter"
+        );
+        // Disputed funds can only leave through the arbiter
+        assert!(
+            arbiter.is_some() || self.open_disputes == 0,
+            "Cannot remove the arbiter while {} disputes are open",
+            self.open_disputes
+        );
+        self.arbiter = arbiter;
+    }
+
+    pub fn set_dispute_bond(&mut self, dispute_bond: U128) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set dispute bond"
+        );
+        self.dispute_bond = dispute_bond;
+    }
+
+    pub fn set_max_price_age(&mut self, max_price_age: U64) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set max price age"
+        );
+        self.max_price_age = max_price_age;
+    }
+
+    pub fn set_max_cross_chain_amount(&mut self, max_cross_chain_amount: U128) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set max cross-chain amount"
+        );
+        self.max_cross_chain_amount = max_cross_chain_amount;
+    }
+
+    pub fn set_order_retention_period(&mut self, order_retention_period: U64) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can set order retention period"
+        );
+        self.order_retention_period = order_retention_period;
+    }
+
     pub fn set_timelock_limits(&mut self, min_timelock: U64, max_timelock: U64) {
         assert_eq!(
             env::predecessor_account_id(),
@@ -432,11 +1675,58 @@ impl FusionEscrow {
             "Only owner can set timelock limits"
         );
         assert!(min_timelock.0 < max_timelock.0, "Min timelock must be less than max");
+        assert!(max_timelock.0 <= MAX_TIMELOCK_SECONDS, "Timelock exceeds maximum");
         self.min_timelock = min_timelock;
         self.max_timelock = max_timelock;
     }
 }
 
+// Amount the contract holds for an order while it is Funded or Disputed
+fn order_obligation(order: &EscrowOrder) -> u128 {
+    match order.status {
+        OrderStatus::Funded | OrderStatus::Disputed => order.from_amount.0,
+        _ => 0,
+    }
+}
+
+// Convert a timelock in seconds to an absolute expiry timestamp in nanoseconds
+fn timelock_expiry(timelock: U64) -> u64 {
+    timelock
+        .0
+        .checked_mul(NANOS_PER_SECOND)
+        .and_then(|nanos| env::block_timestamp().checked_add(nanos))
+        .expect("Timelock overflow")
+}
+
+// Bound to this contract so consent cannot be replayed against another escrow deployment
+fn mutual_cancel_digest(order_id: &str) -> Vec<u8> {
+    env::sha256(format!("mutual_cancel:{}:{}", env::current_account_id(), order_id).as_bytes())
+}
+
+// Fail early when the attached gas cannot cover the promises a method schedules
+fn assert_enough_gas(promise_gas: Gas) {
+    let needed = promise_gas.saturating_add(GAS_FOR_EXECUTION);
+    assert!(
+        env::prepaid_gas() >= needed,
+        "Insufficient gas: attach at least {} TGas",
+        needed.as_tgas()
+    );
+}
+
+fn price_key(from_token: &AccountId, to_token: &AccountId) -> String {
+    format!("{}:{}", from_token, to_token)
+}
+
+// Render a fixed-point oracle price as a decimal string
+fn format_price(price: u128) -> String {
+    format!(
+        "{}.{:0width$}",
+        price / PRICE_DENOMINATOR,
+        price % PRICE_DENOMINATOR,
+        width = PRICE_DECIMALS as usize
+    )
+}
+
 // Implement FungibleTokenReceiver for handling token transfers
 #[near_bindgen]
 impl FungibleTokenReceiver for FusionEscrow {
@@ -446,17 +1736,55 @@ impl FungibleTokenReceiver for FusionEscrow {
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        // Handle incoming token transfers
-        // This would be used for funding orders or other token operations
-        PromiseOrValue::Value(U128(0))
-    }
-}
-
+        // The solver contract forwards a user's tokens with msg {"create_order":{...}} to open a funded
+        // order on the user's behalf; other transfers are ignored
+        if let Ok(EscrowTransferMessage::CreateOrder {
+            order_id,
+            maker,
+            taker,
+            to_token,
+            to_amount,
+            hashlock,
+            secret_length,
+            timelock,
+        }) = serde_json::from_str(&msg) {
+            assert_eq!(
+                Some(sender_id),
+                self.solver_contract,
+                "Only the solver contract can create orders on behalf of a maker"
+            );
+            assert!(self.orders.get(&order_id).is_none(), "Order already exists");
+            let order_id = self.internal_create_order(
+                order_id,
+                maker,
+                CreateOrderArgs {
+                    taker,
+                    from_token: env::predecessor_account_id(),
+                    to_token,
+                    from_amount: amount,
+                    to_amount,
+                    hashlock,
+                    secret_length,
+                    timelock,
+                    refund_bounty_opt_in: false,
+                    memo: None,
+                },
+            );
+            let mut order = self.orders.get(&order_id).expect("Order not found");
+            order.status = OrderStatus::Funded;
+            self.internal_save_order(&order_id, &order);
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, AccountId};
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId, PromiseResult};
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -469,22 +1797,24 @@ mod tests {
 
     #[test]
     fn test_create_order() {
-        let context = get_context(accounts(1));
-        testing_env!(context.build());
-        
+        testing_env!(get_context(accounts(0)).build());
         let mut contract = FusionEscrow::new(accounts(0));
         contract.add_supported_token(accounts(2));
         contract.add_supported_token(accounts(3));
         
-        let order_id = contract.create_order(
-            accounts(4),
-            accounts(2),
-            accounts(3),
-            U128(1000),
-            U128(950),
-            "hashlock123".to_string(),
-            U64(3600),
-        );
+        testing_env!(get_context(accounts(1)).build());
+        let order_id = contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(1000),
+            to_amount: U128(950),
+            hashlock: "hashlock123".to_string(),
+            secret_length: 32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        });
         
         assert!(!order_id.is_empty());
     }
@@ -501,8 +1831,1622 @@ mod tests {
             accounts(3),
             U128(1000),
         );
+        let quote: FusionQuote = serde_json::from_str(&quote).unwrap();
         
         assert_eq!(quote.from_amount, U128(1000));
         assert_eq!(quote.to_amount, U128(980));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    #[should_panic(expected = "Timelock overflow")]
+    fn test_timelock_expiry_overflow() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        
+        timelock_expiry(U64(u64::MAX / NANOS_PER_SECOND + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Timelock exceeds maximum")]
+    fn test_cross_chain_swap_timelock_cap() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.create_cross_chain_swap(
+            "0xorder".to_string(),
+            "0xuser".to_string(),
+            "ethereum".to_string(),
+            "near".to_string(),
+            "usdc".to_string(),
+            "usdc.near".to_string(),
+            U128(1000),
+            U128(1000),
+            "hashlock123".to_string(),
+            U64(u64::MAX),
+        );
+    }
+
+    #[test]
+    fn test_get_token_obligations() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        
+        let orders = [
+            (accounts(2), 1000),
+            (accounts(2), 2500),
+            (accounts(3), 700),
+            (accounts(2), 400),
+        ];
+        let mut order_ids = vec![];
+        for (i, (from_token, amount)) in orders.into_iter().enumerate() {
+            testing_env!(get_context(accounts(1)).block_timestamp(i as u64).build());
+            let to_token = if from_token == accounts(2) { accounts(3) } else { accounts(2) };
+            order_ids.push(contract.create_order(CreateOrderArgs {
+                taker: accounts(4),
+                from_token,
+                to_token,
+                from_amount: U128(amount),
+                to_amount: U128(amount),
+                hashlock: "hashlock123".to_string(),
+                secret_length: 32,
+                timelock: U64(3600),
+                refund_bounty_opt_in: false,
+                memo: None,
+            }));
+        }
+        
+        // The last order stays pending and must not count
+        for order_id in &order_ids[..3] {
+            drop(contract.fund_order(order_id.clone()));
+        }
+        
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(3500));
+        assert_eq!(contract.get_token_obligations(accounts(3)), U128(700));
+        assert_eq!(contract.get_token_obligations(accounts(5)), U128(0));
+    }
+
+    #[test]
+    fn test_token_obligations_follow_order_status() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(10000));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(0));
+        
+        // A failed payout puts the order, and its obligation, back
+        resolve_transfer_with(PromiseResult::Failed);
+        contract.on_claim_transfer_resolved(order_id, U128(30));
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(10000));
+    }
+
+    #[test]
+    fn test_add_supported_tokens() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        
+        let tokens: Vec<AccountId> = (1..6).map(accounts).collect();
+        let mut with_duplicates = tokens.clone();
+        with_duplicates.push(accounts(1));
+        contract.add_supported_tokens(with_duplicates);
+        
+        for token in &tokens {
+            assert!(contract.supported_tokens.get(token).unwrap_or(false));
+        }
+        
+        contract.remove_supported_tokens(vec![accounts(1), accounts(2)]);
+        assert!(contract.supported_tokens.get(&accounts(1)).is_none());
+        assert!(contract.supported_tokens.get(&accounts(2)).is_none());
+        assert!(contract.supported_tokens.get(&accounts(3)).unwrap_or(false));
+    }
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    // Receiver and amount of every ft_transfer scheduled by the contract
+    fn ft_transfers() -> Vec<(AccountId, U128)> {
+        get_created_receipts()
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. }
+                    if method_name == b"ft_transfer" =>
+                {
+                    let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                    Some((
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        U128(args["amount"].as_str().unwrap().parse().unwrap()),
+                    ))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn last_ft_transfer() -> (AccountId, U128) {
+        ft_transfers().pop().expect("No ft_transfer call")
+    }
+
+    // Funded order from accounts(1) to taker accounts(4), claimable with `secret`
+    fn setup_funded_order(secret: &str) -> (FusionEscrow, String) {
+        setup_funded_order_with(secret, |_, _| {})
+    }
+
+    fn setup_funded_order_with(
+        secret: &str,
+        before_funding: impl FnOnce(&mut FusionEscrow, &str),
+    ) -> (FusionEscrow, String) {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        
+        testing_env!(get_context(accounts(1)).build());
+        let order_id = contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(secret.as_bytes())),
+            secret_length: secret.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        });
+        before_funding(&mut contract, &order_id);
+        drop(contract.fund_order(order_id.clone()));
+        (contract, order_id)
+    }
+
+    #[test]
+    fn test_claim_order_to_recipient() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order_to(order_id.clone(), SECRET.to_string(), accounts(5)));
+        
+        // 0.3% fee is retained exactly as for a regular claim
+        assert_eq!(last_ft_transfer(), (accounts(5), U128(9970)));
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Claimed);
+        assert_eq!(contract.get_statistics().2, U128(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "Secret must be at least 32 bytes")]
+    fn test_create_order_rejects_short_secret_length() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(b"short")),
+            secret_length: 5,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Secret must be at least 32 bytes")]
+    fn test_claim_order_rejects_short_secret() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, "short".to_string()));
+    }
+
+    #[test]
+    fn test_claim_ignores_later_minimum() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        contract.min_secret_length = SECRET.len() as u32 + 1;
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Claimed);
+    }
+
+    #[test]
+    fn test_claim_order_accepts_long_secret() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Claimed);
+        assert_eq!(order.secret, Some(SECRET.to_string()));
+    }
+
+    fn create_test_swap(contract: &mut FusionEscrow, evm_order_hash: &str) -> String {
+        contract.create_cross_chain_swap(
+            evm_order_hash.to_string(),
+            "0xuser".to_string(),
+            "ethereum".to_string(),
+            "near".to_string(),
+            "usdc".to_string(),
+            "usdc.near".to_string(),
+            U128(10000),
+            U128(9500),
+            hex::encode(env::sha256(SECRET.as_bytes())),
+            U64(3600),
+        )
+    }
+
+    // Order linked to a cross-chain swap before being funded
+    fn setup_linked_order() -> (FusionEscrow, String, String) {
+        let mut swap_id = String::new();
+        let (contract, order_id) = setup_funded_order_with(SECRET, |contract, order_id| {
+            testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+            swap_id = create_test_swap(contract, "0xorder");
+            contract.link_swap_order(swap_id.clone(), order_id.to_string());
+        });
+        (contract, swap_id, order_id)
+    }
+
+    #[test]
+    fn test_get_swap_with_order() {
+        let (mut contract, linked_swap_id, order_id) = setup_linked_order();
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(2).build());
+        let unlinked_swap_id = create_test_swap(&mut contract, "0xother");
+        
+        let (swap, order) = contract.get_swap_with_order(linked_swap_id);
+        assert_eq!(swap.near_order_id, order_id);
+        assert_eq!(order.unwrap().id, order_id);
+        
+        let (swap, order) = contract.get_swap_with_order(unlinked_swap_id);
+        assert!(swap.near_order_id.is_empty());
+        assert!(order.is_none());
+    }
+
+    // Funded order linked to a swap that the owner has marked failed
+    fn setup_failed_swap() -> (FusionEscrow, String, String) {
+        let (mut contract, swap_id, order_id) = setup_linked_order();
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.update_swap_status(swap_id.clone(), SwapStatus::Failed);
+        (contract, swap_id, order_id)
+    }
+
+    #[test]
+    fn test_refund_failed_swap_withholds_reimbursement() {
+        let (mut contract, swap_id, order_id) = setup_failed_swap();
+        contract.set_relayer_reimbursement(50);
+        
+        // No need to wait for the timelock once the swap has failed
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.refund_failed_swap(swap_id.clone()));
+        assert_eq!(ft_transfers(), vec![(accounts(1), U128(9950))]);
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Refunded);
+        
+        // The relayer is reimbursed once the maker's refund has landed
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_failed_swap_refund_resolved(swap_id, order_id, U128(50)));
+        assert_eq!(ft_transfers(), vec![(accounts(0), U128(50))]);
+    }
+
+    #[test]
+    fn test_failed_swap_refund_transfer_restores_order() {
+        let (mut contract, swap_id, order_id) = setup_failed_swap();
+        contract.set_relayer_reimbursement(50);
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.refund_failed_swap(swap_id.clone()));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_failed_swap_refund_resolved(swap_id.clone(), order_id.clone(), U128(50)));
+        assert!(ft_transfers().is_empty());
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Funded);
+        
+        // The maker can retry the refund
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.refund_failed_swap(swap_id));
+        assert_eq!(ft_transfers(), vec![(accounts(1), U128(9950))]);
+    }
+
+    #[test]
+    fn test_refund_failed_swap_without_reimbursement() {
+        let (mut contract, swap_id, _) = setup_failed_swap();
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.refund_failed_swap(swap_id));
+        
+        assert_eq!(ft_transfers(), vec![(accounts(1), U128(10000))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Swap must be failed")]
+    fn test_refund_failed_swap_requires_failed_status() {
+        let (mut contract, swap_id, _) = setup_failed_swap();
+        contract.update_swap_status(swap_id.clone(), SwapStatus::NEAROrderFunded);
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.refund_failed_swap(swap_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only maker can refund failed swap")]
+    fn test_refund_failed_swap_only_maker() {
+        let (mut contract, swap_id, _) = setup_failed_swap();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.refund_failed_swap(swap_id));
+    }
+
+    #[test]
+    fn test_cross_chain_claim_takes_near_fee_share() {
+        let (mut contract, _, order_id) = setup_linked_order();
+        
+        // 1% fee, 40% of it collected on NEAR
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_fee_rate(100);
+        contract.set_near_fee_share(4000);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+        
+        // Total fee on 10000 is 100, of which 40 is taken here
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(9960)));
+        assert_eq!(contract.get_statistics().2, U128(40));
+        assert_eq!(contract.get_cross_chain_fees(), U128(40));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only pending orders can be linked")]
+    fn test_link_swap_order_rejects_funded_order() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let swap_id = create_test_swap(&mut contract, "0xorder");
+        contract.link_swap_order(swap_id, order_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only order maker can link order")]
+    fn test_link_swap_order_rejects_other_makers_order() {
+        drop(setup_funded_order_with(SECRET, |contract, order_id| {
+            testing_env!(get_context(accounts(5)).block_timestamp(1).build());
+            let swap_id = create_test_swap(contract, "0xorder");
+            contract.link_swap_order(swap_id, order_id.to_string());
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Order already linked to a swap")]
+    fn test_link_swap_order_rejects_linked_order() {
+        drop(setup_funded_order_with(SECRET, |contract, order_id| {
+            testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+            let first = create_test_swap(contract, "0xorder");
+            contract.link_swap_order(first, order_id.to_string());
+            testing_env!(get_context(accounts(1)).block_timestamp(2).build());
+            let second = create_test_swap(contract, "0xother");
+            contract.link_swap_order(second, order_id.to_string());
+        }));
+    }
+
+    #[test]
+    fn test_same_chain_claim_takes_full_fee() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_fee_rate(100);
+        contract.set_near_fee_share(4000);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(9900)));
+        assert_eq!(contract.get_statistics().2, U128(100));
+        assert_eq!(contract.get_cross_chain_fees(), U128(0));
+    }
+
+    #[test]
+    fn test_get_swap_by_evm_hash() {
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        let swap_id = create_test_swap(&mut contract, "0xorder");
+        
+        // Same inputs always derive the same id
+        let expected = format!("0xorder:{}", accounts(1));
+        assert_eq!(swap_id, format!("swap_{}", hex::encode(env::sha256(expected.as_bytes()))));
+        
+        let swap = contract.get_swap_by_evm_hash("0xorder".to_string(), accounts(1)).unwrap();
+        assert_eq!(swap.evm_order_hash, "0xorder");
+        assert_eq!(swap.near_account, accounts(1));
+        assert!(contract.get_swap_by_evm_hash("0xother".to_string(), accounts(1)).is_none());
+        assert!(contract.get_swap_by_evm_hash("0xorder".to_string(), accounts(2)).is_none());
+    }
+
+    #[test]
+    fn test_evm_order_hash_cannot_be_front_run() {
+        testing_env!(get_context(accounts(2)).block_timestamp(1).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        let squatted = create_test_swap(&mut contract, "0xorder");
+        
+        // The real owner of the EVM order can still open their swap
+        testing_env!(get_context(accounts(1)).block_timestamp(2).build());
+        let swap_id = create_test_swap(&mut contract, "0xorder");
+        assert_ne!(swap_id, squatted);
+        let swap = contract.get_swap_by_evm_hash("0xorder".to_string(), accounts(1)).unwrap();
+        assert_eq!(swap.near_account, accounts(1));
+    }
+
+    fn create_swap_between(contract: &mut FusionEscrow, from_chain: &str, to_chain: &str) -> String {
+        contract.create_cross_chain_swap(
+            format!("0x{}-{}", from_chain, to_chain),
+            "0xuser".to_string(),
+            from_chain.to_string(),
+            to_chain.to_string(),
+            "usdc".to_string(),
+            "usdc.near".to_string(),
+            U128(10000),
+            U128(9500),
+            hex::encode(env::sha256(SECRET.as_bytes())),
+            U64(3600),
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported from chain")]
+    fn test_cross_chain_swap_rejects_unregistered_chain() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        create_swap_between(&mut contract, "polygon", "near");
+    }
+
+    #[test]
+    fn test_supported_chain_registry() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        let mut chains = contract.get_supported_chains();
+        chains.sort();
+        assert_eq!(chains, vec!["ethereum".to_string(), "near".to_string()]);
+        
+        contract.add_supported_chain("polygon".to_string());
+        contract.remove_supported_chain("ethereum".to_string());
+        let mut chains = contract.get_supported_chains();
+        chains.sort();
+        assert_eq!(chains, vec!["near".to_string(), "polygon".to_string()]);
+        
+        testing_env!(get_context(accounts(1)).build());
+        let swap_id = create_swap_between(&mut contract, "near", "polygon");
+        assert_eq!(contract.get_swap_with_order(swap_id).0.to_chain, "polygon");
+        
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            create_swap_between(&mut contract, "ethereum", "near")
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can add supported chains")]
+    fn test_add_supported_chain_owner_only() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_chain("polygon".to_string());
+    }
+
+    #[test]
+    fn test_get_swap_statuses_in_input_order() {
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        let first = create_test_swap(&mut contract, "0xfirst");
+        let second = create_test_swap(&mut contract, "0xsecond");
+        testing_env!(get_context(accounts(0)).build());
+        contract.update_swap_status(second.clone(), SwapStatus::Completed);
+        
+        let statuses = contract.get_swap_statuses(vec![second, "unknown".to_string(), first]);
+        assert_eq!(statuses, vec![Some(SwapStatus::Completed), None, Some(SwapStatus::Initiated)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Swap already exists for EVM order")]
+    fn test_duplicate_evm_order_hash_rejected() {
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        create_test_swap(&mut contract, "0xorder");
+        testing_env!(get_context(accounts(1)).block_timestamp(2).build());
+        create_test_swap(&mut contract, "0xorder");
+    }
+
+    #[test]
+    fn test_resolve_order_finds_local_order() {
+        let (contract, order_id) = setup_funded_order(SECRET);
+        
+        let view = match contract.resolve_order(order_id.clone()) {
+            PromiseOrValue::Value(view) => view.unwrap(),
+            PromiseOrValue::Promise(_) => panic!("Local order was not resolved directly"),
+        };
+        assert_eq!(view.id, order_id);
+        assert_eq!(view.source, OrderSource::Escrow);
+        assert_eq!(view.maker, accounts(1));
+        assert_eq!(view.status, "Funded");
+    }
+
+    #[test]
+    fn test_resolve_order_falls_back_to_solver() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_solver_contract(Some(accounts(5)));
+        
+        drop(contract.resolve_order("order_bob_1".to_string()));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(5));
+        assert!(matches!(
+            &receipts[0].actions[0],
+            MockAction::FunctionCallWeight { method_name, .. } if method_name == b"get_order"
+        ));
+        
+        // fusion-solver returns its order as a JSON string
+        let solver_order = r#"{"id":"order_bob_1","user":"bob","from_token":"danny","to_token":"eugene","from_amount":"1000","to_amount":"990","min_to_amount":"980","filled_amount":"0","deadline":"500","solver":"charlie","status":"Pending","created_at":"1","filled_at":null,"tx_hash":null}"#;
+        let view = contract.on_solver_order(solver_order.to_string()).unwrap();
+        assert_eq!(view.source, OrderSource::Solver);
+        assert_eq!(view.maker, accounts(1));
+        assert_eq!(view.from_amount, U128(1000));
+        assert_eq!(view.status, "Pending");
+        
+        assert!(contract.on_solver_order("null".to_string()).is_none());
+    }
+
+    // Run the recovery callback as if ft_balance_of returned `balance`
+    fn recover_with_balance(contract: &mut FusionEscrow, token: AccountId, amount: u128, balance: u128) {
+        testing_env!(get_context(accounts(0)).build());
+        drop(contract.on_recover_tokens_balance(token, U128(amount), accounts(5), U128(balance)));
+    }
+
+    #[test]
+    fn test_recover_stray_tokens() {
+        // accounts(2) backs a funded order of 10000
+        let (mut contract, _) = setup_funded_order(SECRET);
+        
+        recover_with_balance(&mut contract, accounts(3), 500, 500);
+        assert_eq!(last_ft_transfer(), (accounts(5), U128(500)));
+        
+        // Only the surplus above the funded order can be rescued
+        recover_with_balance(&mut contract, accounts(2), 100, 10100);
+        assert_eq!(last_ft_transfer(), (accounts(5), U128(100)));
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(10000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery would dip into tracked obligations")]
+    fn test_recover_tokens_rejects_obligated_balance() {
+        let (mut contract, _) = setup_funded_order(SECRET);
+        
+        recover_with_balance(&mut contract, accounts(2), 101, 10100);
+    }
+
+    // Funded order whose maker opted in to third-party refunds at creation
+    fn setup_bounty_order() -> (FusionEscrow, String) {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        
+        testing_env!(get_context(accounts(1)).build());
+        let order_id = contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: true,
+            memo: None,
+        });
+        drop(contract.fund_order(order_id.clone()));
+        (contract, order_id)
+    }
+
+    #[test]
+    fn test_refund_order_for_pays_bounty() {
+        let (mut contract, order_id) = setup_bounty_order();
+        
+        // Past the 1 hour timelock plus the 1 hour grace period
+        testing_env!(get_context(accounts(5)).block_timestamp(7200 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order_for(order_id.clone()));
+        assert_eq!(ft_transfers(), vec![(accounts(1), U128(9990))]);
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Refunded);
+        
+        // The bounty follows once the maker's refund has landed
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_refund_for_resolved(order_id, accounts(5), U128(10)));
+        assert_eq!(ft_transfers(), vec![(accounts(5), U128(10))]);
+    }
+
+    #[test]
+    fn test_refund_order_for_failed_refund_pays_no_bounty() {
+        let (mut contract, order_id) = setup_bounty_order();
+        testing_env!(get_context(accounts(5)).block_timestamp(7200 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order_for(order_id.clone()));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_refund_for_resolved(order_id.clone(), accounts(5), U128(10)));
+        assert!(ft_transfers().is_empty());
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Funded);
+    }
+
+    #[test]
+    fn test_refund_order_for_skips_zero_bounty() {
+        let (mut contract, order_id) = setup_bounty_order();
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_refund_bounty(0, U64(3600));
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(7200 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order_for(order_id.clone()));
+        assert_eq!(ft_transfers(), vec![(accounts(1), U128(10000))]);
+        
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_refund_for_resolved(order_id, accounts(5), U128(0)));
+        assert!(ft_transfers().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Maker has not opted in to refund bounty")]
+    fn test_refund_order_for_requires_opt_in() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(7200 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order_for(order_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Refund grace period not over")]
+    fn test_refund_order_for_waits_for_grace_period() {
+        let (mut contract, order_id) = setup_bounty_order();
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(7199 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order_for(order_id));
+    }
+
+    fn setup_oracle_price(reported_at: u64) -> FusionEscrow {
+        testing_env!(get_context(accounts(0)).block_timestamp(reported_at).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_oracle(Some(accounts(5)));
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(reported_at).build());
+        contract.report_price(accounts(2), accounts(3), U128(1_500_000), U64(reported_at));
+        contract
+    }
+
+    #[test]
+    fn test_get_quote_uses_fresh_oracle_price() {
+        let contract = setup_oracle_price(1000 * NANOS_PER_SECOND);
+        
+        // Exactly at the default 5 minute limit
+        testing_env!(get_context(accounts(1)).block_timestamp(1300 * NANOS_PER_SECOND).build());
+        let quote: FusionQuote =
+            serde_json::from_str(&contract.get_quote(accounts(2), accounts(3), U128(1000))).unwrap();
+        
+        assert_eq!(quote.to_amount, U128(1500));
+        assert_eq!(quote.price, "1.500000");
+    }
+
+    #[test]
+    #[should_panic(expected = "Oracle price is stale")]
+    fn test_get_quote_rejects_stale_oracle_price() {
+        let contract = setup_oracle_price(1000 * NANOS_PER_SECOND);
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1301 * NANOS_PER_SECOND).build());
+        contract.get_quote(accounts(2), accounts(3), U128(1000));
+    }
+
+    #[test]
+    fn test_max_price_age_is_configurable() {
+        let mut contract = setup_oracle_price(1000 * NANOS_PER_SECOND);
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_max_price_age(U64(600));
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1600 * NANOS_PER_SECOND).build());
+        let quote: FusionQuote =
+            serde_json::from_str(&contract.get_quote(accounts(2), accounts(3), U128(1000))).unwrap();
+        assert_eq!(quote.to_amount, U128(1500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only oracle can report prices")]
+    fn test_report_price_requires_oracle() {
+        let mut contract = setup_oracle_price(1000 * NANOS_PER_SECOND);
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1000 * NANOS_PER_SECOND).build());
+        contract.report_price(accounts(2), accounts(3), U128(1), U64(1000 * NANOS_PER_SECOND));
+    }
+
+    #[test]
+    fn test_require_creator_proof_flag() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        assert!(!contract.get_require_creator_proof());
+        
+        contract.set_require_creator_proof(true);
+        assert!(contract.get_require_creator_proof());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can set creator proof requirement")]
+    fn test_require_creator_proof_owner_only() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_require_creator_proof(true);
+    }
+
+    fn setup_memo_contract() -> FusionEscrow {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        contract
+    }
+
+    fn create_order_with_memo(contract: &mut FusionEscrow, memo: Option<String>) -> String {
+        testing_env!(get_context(accounts(1)).build());
+        contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo,
+        })
+    }
+
+    #[test]
+    fn test_create_order_with_memo() {
+        let mut contract = setup_memo_contract();
+        let order_id = create_order_with_memo(&mut contract, Some("offchain-42".to_string()));
+        
+        let order: EscrowOrder = serde_json::from_str(&contract.get_order(order_id)).unwrap();
+        assert_eq!(order.memo, Some("offchain-42".to_string()));
+        
+        let order_id = create_order_with_memo(&mut contract, Some("a".repeat(MAX_MEMO_LEN)));
+        assert_eq!(contract.orders.get(&order_id).unwrap().memo.unwrap().len(), MAX_MEMO_LEN);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo exceeds 256 bytes")]
+    fn test_create_order_rejects_long_memo() {
+        let mut contract = setup_memo_contract();
+        create_order_with_memo(&mut contract, Some("a".repeat(MAX_MEMO_LEN + 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas: attach at least 20 TGas")]
+    fn test_fund_order_requires_enough_gas() {
+        setup_funded_order_with(SECRET, |_, _| {
+            testing_env!(get_context(accounts(1)).prepaid_gas(Gas::from_tgas(19)).build());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas: attach at least 50 TGas")]
+    fn test_claim_order_requires_enough_gas() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).prepaid_gas(Gas::from_tgas(49)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+    }
+
+    #[test]
+    fn test_low_gas_claim_leaves_order_untouched() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).prepaid_gas(Gas::from_tgas(49)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        }));
+        assert!(result.is_err());
+        
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Funded);
+        assert!(order.secret.is_none());
+        assert_eq!(contract.total_swaps, 0);
+    }
+
+    // Resolve the payout transfer of the current receipt with `result`
+    fn resolve_transfer_with(result: PromiseResult) {
+        testing_env!(
+            get_context(accounts(0)).predecessor_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_failed_claim_transfer_restores_order() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_claim_transfer_resolved(order_id.clone(), U128(0)));
+        
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Funded);
+        assert!(order.secret.is_none());
+        assert_eq!(contract.total_swaps, 0);
+        assert_eq!(contract.total_volume, U128(0));
+    }
+
+    const BOND: u128 = 1_000_000_000_000_000_000_000_000; // 1 NEAR
+
+    // Funded order with accounts(5) as arbiter and a dispute opened by `opener`
+    fn setup_disputed_order(opener: AccountId) -> (FusionEscrow, String) {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_arbiter(Some(accounts(5)));
+        
+        testing_env!(get_context(opener)
+            .attached_deposit(NearToken::from_yoctonear(BOND))
+            .build());
+        contract.open_dispute(order_id.clone());
+        (contract, order_id)
+    }
+
+    // Receiver and amount of every NEAR transfer scheduled by the contract
+    fn near_transfers() -> Vec<(AccountId, u128)> {
+        get_created_receipts()
+            .iter()
+            .flat_map(|receipt| {
+                receipt.actions.iter().filter_map(move |action| match action {
+                    MockAction::Transfer { deposit, .. } => {
+                        Some((receipt.receiver_id.clone(), deposit.as_yoctonear()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_open_dispute_escrows_bond() {
+        let (contract, order_id) = setup_disputed_order(accounts(1));
+        
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Disputed);
+        let dispute = contract.get_dispute(order_id).unwrap();
+        assert_eq!(dispute.opened_by, accounts(1));
+        assert_eq!(dispute.bond, U128(BOND));
+        assert!(dispute.winner.is_none());
+        // Disputed funds stay out of reach of recover_tokens
+        assert_eq!(contract.get_token_obligations(accounts(2)), U128(10000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attach at least")]
+    fn test_open_dispute_requires_bond() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_arbiter(Some(accounts(5)));
+        
+        testing_env!(get_context(accounts(4))
+            .attached_deposit(NearToken::from_yoctonear(BOND - 1))
+            .build());
+        contract.open_dispute(order_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order must be funded")]
+    fn test_disputed_order_cannot_be_claimed() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(1));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+    }
+
+    #[test]
+    fn test_resolve_dispute_returns_bond_to_winning_opener() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(1));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.resolve_dispute(order_id.clone(), accounts(1)));
+        assert_eq!(last_ft_transfer(), (accounts(1), U128(10000)));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Refunded);
+        
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_dispute_transfer_resolved(order_id.clone()));
+        assert_eq!(near_transfers(), vec![(accounts(1), BOND)]);
+        assert!(contract.get_dispute(order_id).is_none());
+    }
+
+    #[test]
+    fn test_resolve_dispute_forfeits_bond_of_losing_opener() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(4));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.resolve_dispute(order_id.clone(), accounts(1)));
+        
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_dispute_transfer_resolved(order_id));
+        assert_eq!(near_transfers(), vec![(accounts(5), BOND)]);
+    }
+
+    #[test]
+    fn test_failed_dispute_transfer_reopens_dispute() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(4));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.resolve_dispute(order_id.clone(), accounts(4)));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_dispute_transfer_resolved(order_id.clone()));
+        assert!(near_transfers().is_empty());
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Disputed);
+        assert!(contract.get_dispute(order_id).unwrap().winner.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot remove the arbiter while 1 disputes are open")]
+    fn test_arbiter_cannot_be_removed_with_open_disputes() {
+        let (mut contract, _) = setup_disputed_order(accounts(1));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_arbiter(None);
+    }
+
+    #[test]
+    fn test_arbiter_can_be_removed_once_disputes_resolve() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(1));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.resolve_dispute(order_id.clone(), accounts(1)));
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_dispute_transfer_resolved(order_id));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_arbiter(None);
+        assert_eq!(contract.open_disputes, 0);
+        assert!(contract.arbiter.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only arbiter can resolve disputes")]
+    fn test_resolve_dispute_requires_arbiter() {
+        let (mut contract, order_id) = setup_disputed_order(accounts(1));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.resolve_dispute(order_id, accounts(4)));
+    }
+
+    // Taker accounts(4) registers `signing_key` and signs consent to cancel `order_id`
+    fn taker_consent(contract: &mut FusionEscrow, signing_key: &SigningKey, order_id: &str) -> Base64VecU8 {
+        testing_env!(get_context(accounts(4)).build());
+        let public_key = PublicKey::from_parts(
+            CurveType::ED25519,
+            signing_key.verifying_key().to_bytes().to_vec(),
+        )
+        .unwrap();
+        contract.register_consent_key(public_key);
+        
+        let digest = hex::decode(contract.mutual_cancel_hash(order_id.to_string())).unwrap();
+        Base64VecU8(signing_key.sign(&digest).to_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_mutual_cancel_with_taker_consent() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        let signature = taker_consent(&mut contract, &SigningKey::from_bytes(&[7u8; 32]), &order_id);
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.mutual_cancel(order_id.clone(), signature));
+        assert_eq!(last_ft_transfer(), (accounts(1), U128(10000)));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid taker signature")]
+    fn test_mutual_cancel_rejects_signature_by_other_key() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        taker_consent(&mut contract, &SigningKey::from_bytes(&[7u8; 32]), &order_id);
+        
+        // The maker signs in place of the taker
+        let digest = hex::decode(contract.mutual_cancel_hash(order_id.clone())).unwrap();
+        let forged = SigningKey::from_bytes(&[8u8; 32]).sign(&digest).to_bytes().to_vec();
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.mutual_cancel(order_id, Base64VecU8(forged)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid taker signature")]
+    fn test_mutual_cancel_rejects_consent_for_other_order() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        let signature = taker_consent(&mut contract, &SigningKey::from_bytes(&[7u8; 32]), "order_other");
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.mutual_cancel(order_id, signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Taker consent key not registered")]
+    fn test_mutual_cancel_requires_taker_key() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(1)).build());
+        drop(contract.mutual_cancel(order_id, Base64VecU8(vec![0; 64])));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas: attach at least 50 TGas")]
+    fn test_refund_order_requires_enough_gas() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(1))
+            .block_timestamp(3600 * NANOS_PER_SECOND)
+            .prepaid_gas(Gas::from_tgas(49))
+            .build());
+        drop(contract.refund_order(order_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas: attach at least 60 TGas")]
+    fn test_refund_order_for_requires_enough_gas() {
+        let (mut contract, order_id) = setup_bounty_order();
+        
+        testing_env!(get_context(accounts(5))
+            .block_timestamp(7200 * NANOS_PER_SECOND)
+            .prepaid_gas(Gas::from_tgas(59))
+            .build());
+        drop(contract.refund_order_for(order_id));
+    }
+
+    #[test]
+    fn test_claim_order_with_exact_minimum_gas() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).prepaid_gas(Gas::from_tgas(50)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Claimed);
+    }
+
+    fn setup_order_with_claim_deadline() -> (FusionEscrow, String) {
+        setup_funded_order_with(SECRET, |contract, order_id| {
+            contract.set_claim_deadline(order_id.to_string(), U64(1800));
+        })
+    }
+
+    #[test]
+    fn test_claim_before_claim_deadline() {
+        let (mut contract, order_id) = setup_order_with_claim_deadline();
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(1799 * NANOS_PER_SECOND).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Claimed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim deadline passed")]
+    fn test_claim_after_claim_deadline_rejected() {
+        let (mut contract, order_id) = setup_order_with_claim_deadline();
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(1800 * NANOS_PER_SECOND).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Timelock not expired")]
+    fn test_refund_blocked_between_claim_deadline_and_expiry() {
+        let (mut contract, order_id) = setup_order_with_claim_deadline();
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1800 * NANOS_PER_SECOND).build());
+        drop(contract.refund_order(order_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim deadline must be before expiry")]
+    fn test_claim_deadline_must_precede_expiry() {
+        setup_funded_order_with(SECRET, |contract, order_id| {
+            contract.set_claim_deadline(order_id.to_string(), U64(3600));
+        });
+    }
+
+    #[test]
+    fn test_get_user_active_orders() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        // A second, unfunded order is not active
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(2),
+            to_token: accounts(3),
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        });
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(3599 * NANOS_PER_SECOND).build());
+        let active = contract.get_user_active_orders(accounts(1));
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].order.id, order_id);
+        assert!(!active[0].refundable);
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(3600 * NANOS_PER_SECOND).build());
+        let active = contract.get_user_active_orders(accounts(1));
+        assert_eq!(active.len(), 1);
+        assert!(active[0].refundable);
+    }
+
+    #[test]
+    fn test_set_taker_before_funding() {
+        let (mut contract, order_id) = setup_funded_order_with(SECRET, |contract, order_id| {
+            contract.set_taker(order_id.to_string(), accounts(5));
+        });
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Claimed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order must be pending")]
+    fn test_set_taker_rejected_after_funding() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_taker(order_id, accounts(5));
+    }
+
+    #[test]
+    fn test_fee_rate_accepts_shared_cap() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_fee_rate(MAX_FEE_BPS);
+        contract.set_refund_bounty(MAX_FEE_BPS, U64(3600));
+        assert_eq!(contract.fee_rate, MAX_FEE_BPS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee rate cannot exceed 10%")]
+    fn test_fee_rate_above_shared_cap_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_fee_rate(MAX_FEE_BPS + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Refund bounty cannot exceed 10%")]
+    fn test_refund_bounty_above_shared_cap_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_refund_bounty(MAX_FEE_BPS + 1, U64(3600));
+    }
+
+    #[test]
+    fn test_is_claimable() {
+        let (contract, order_id) = setup_funded_order_with(SECRET, |contract, order_id| {
+            assert!(!contract.is_claimable(order_id.to_string(), accounts(4)));
+        });
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3599 * NANOS_PER_SECOND).build());
+        assert!(contract.is_claimable(order_id.clone(), accounts(4)));
+        assert!(!contract.is_claimable(order_id.clone(), accounts(1)));
+        assert!(!contract.is_claimable("missing".to_string(), accounts(4)));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3600 * NANOS_PER_SECOND).build());
+        assert!(!contract.is_claimable(order_id, accounts(4)));
+    }
+
+    #[test]
+    fn test_is_claimable_respects_claim_deadline() {
+        let (contract, order_id) = setup_order_with_claim_deadline();
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(1799 * NANOS_PER_SECOND).build());
+        assert!(contract.is_claimable(order_id.clone(), accounts(4)));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(1800 * NANOS_PER_SECOND).build());
+        assert!(!contract.is_claimable(order_id, accounts(4)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Order expired")]
+    fn test_claim_after_expiry_rejected() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3600 * NANOS_PER_SECOND).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+    }
+
+    #[test]
+    fn test_storage_cost_for_order() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        
+        let cost = contract.storage_cost_for_order();
+        assert!(cost.0 > 0);
+        
+        let create_order = |contract: &mut FusionEscrow, timestamp: u64| {
+            testing_env!(get_context(accounts(1)).block_timestamp(timestamp).build());
+            let usage_before = env::storage_usage();
+            contract.create_order(CreateOrderArgs {
+                taker: accounts(4),
+                from_token: accounts(2),
+                to_token: accounts(3),
+                from_amount: U128(10000),
+                to_amount: U128(9500),
+                hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+                secret_length: SECRET.len() as u32,
+                timelock: U64(3600),
+                refund_bounty_opt_in: false,
+                memo: None,
+            });
+            env::storage_usage() - usage_before
+        };
+        
+        // The first order also creates the maker's index entry, later ones grow it
+        let first_used = create_order(&mut contract, 1);
+        assert!(first_used as u128 * env::storage_byte_cost().as_yoctonear() <= cost.0);
+        
+        let next_used = create_order(&mut contract, 2);
+        assert!(next_used < first_used);
+        assert!(next_used as u128 * env::storage_byte_cost().as_yoctonear() <= cost.0);
+    }
+
+    // Order from accounts(1) funded with NEAR, wrapped through accounts(5) as wNEAR
+    fn setup_near_order(deposit: u128) -> (FusionEscrow, String) {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(3));
+        contract.add_supported_token(accounts(5));
+        contract.set_wnear_contract(Some(accounts(5)));
+        
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(deposit))
+            .build());
+        drop(contract.create_order_with_near(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(5),
+            to_token: accounts(3),
+            from_amount: U128(deposit),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        }));
+        let order_id = contract.user_orders.get(&accounts(1)).unwrap().pop().unwrap();
+        (contract, order_id)
+    }
+
+    #[test]
+    fn test_create_order_with_near_wraps_deposit() {
+        let (mut contract, order_id) = setup_near_order(BOND);
+        
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Pending);
+        assert_eq!(order.from_token, accounts(5));
+        assert_eq!(order.from_amount, U128(BOND));
+        let wraps: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == accounts(5))
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, attached_deposit, .. }
+                    if method_name == b"near_deposit" =>
+                {
+                    Some(attached_deposit.as_yoctonear())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(wraps, vec![BOND]);
+        
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_near_wrapped(order_id.clone()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Funded);
+        assert!(near_transfers().is_empty());
+    }
+
+    #[test]
+    fn test_failed_wrap_refunds_near() {
+        let (mut contract, order_id) = setup_near_order(BOND);
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_near_wrapped(order_id.clone()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Refunded);
+        assert_eq!(near_transfers(), vec![(accounts(1), BOND)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order must be funded")]
+    fn test_near_order_not_claimable_before_wrap() {
+        let (mut contract, order_id) = setup_near_order(BOND);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+    }
+
+    #[test]
+    fn test_failed_wrap_skips_refund_when_order_moved_on() {
+        let (mut contract, order_id) = setup_near_order(BOND);
+        let mut order = contract.orders.get(&order_id).unwrap();
+        order.status = OrderStatus::Claimed;
+        contract.orders.insert(&order_id, &order);
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_near_wrapped(order_id.clone()));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Claimed);
+        assert!(near_transfers().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "No wNEAR contract configured")]
+    fn test_create_order_with_near_requires_wnear_contract() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(3));
+        
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(BOND))
+            .build());
+        drop(contract.create_order_with_near(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(5),
+            to_token: accounts(3),
+            from_amount: U128(BOND),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Order amount must match the attached deposit")]
+    fn test_create_order_with_near_requires_matching_amount() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(3));
+        contract.add_supported_token(accounts(5));
+        contract.set_wnear_contract(Some(accounts(5)));
+
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(BOND))
+            .build());
+        drop(contract.create_order_with_near(CreateOrderArgs {
+            taker: accounts(4),
+            from_token: accounts(5),
+            to_token: accounts(3),
+            from_amount: U128(BOND * 2),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        }));
+    }
+
+    // Order from accounts(1) swapping `from_token` for `to_token`
+    fn create_pair_order(contract: &mut FusionEscrow, from_token: AccountId, to_token: AccountId) -> String {
+        testing_env!(get_context(accounts(1)).build());
+        contract.create_order(CreateOrderArgs {
+            taker: accounts(4),
+            from_token,
+            to_token,
+            from_amount: U128(10000),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+            refund_bounty_opt_in: false,
+            memo: None,
+        })
+    }
+
+    fn setup_paused_pair() -> FusionEscrow {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_tokens(vec![accounts(2), accounts(3), accounts(5)]);
+        contract.pause_pair(accounts(2), accounts(3));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Token pair is paused")]
+    fn test_paused_pair_rejects_orders() {
+        let mut contract = setup_paused_pair();
+        create_pair_order(&mut contract, accounts(2), accounts(3));
+    }
+
+    #[test]
+    fn test_other_pairs_trade_while_pair_paused() {
+        let mut contract = setup_paused_pair();
+        assert!(contract.is_pair_paused(accounts(2), accounts(3)));
+        assert!(!contract.is_pair_paused(accounts(3), accounts(2)));
+        
+        let order_id = create_pair_order(&mut contract, accounts(2), accounts(5));
+        assert_eq!(contract.orders.get(&order_id).unwrap().to_token, accounts(5));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.unpause_pair(accounts(2), accounts(3));
+        let order_id = create_pair_order(&mut contract, accounts(2), accounts(3));
+        assert_eq!(contract.orders.get(&order_id).unwrap().to_token, accounts(3));
+    }
+
+    #[test]
+    fn test_self_test_passes_on_fresh_contract() {
+        testing_env!(get_context(accounts(0)).build());
+        let contract = FusionEscrow::new(accounts(0));
+        assert!(contract.self_test().is_empty());
+    }
+
+    #[test]
+    fn test_self_test_reports_misconfiguration() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.fee_rate = MAX_FEE_BPS + 1;
+        contract.min_timelock = U64(86400);
+        contract.max_timelock = U64(3600);
+        contract.wnear_contract = Some(accounts(4));
+        
+        let problems = contract.self_test();
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("fee_rate"));
+        assert!(problems[1].starts_with("min_timelock"));
+        assert!(problems[2].starts_with("wnear_contract"));
+    }
+
+    #[test]
+    fn test_claim_pays_taker_rebate() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_taker_rebate(5000);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id, SECRET.to_string()));
+        
+        // Half of the 30 fee goes back to the taker with the principal
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(9985)));
+        assert_eq!(contract.get_statistics().2, U128(15));
+    }
+
+    #[test]
+    #[should_panic(expected = "Taker rebate cannot exceed the fee")]
+    fn test_taker_rebate_capped_at_fee() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_taker_rebate(10001);
+    }
+
+    #[test]
+    fn test_cleanup_removes_old_terminal_orders() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_order(order_id.clone(), SECRET.to_string()));
+        let stats_before = contract.get_statistics();
+        
+        // Expires after 3600s and is retained for 30 days past that
+        let retained_until = (3600 + 30 * 86400) * NANOS_PER_SECOND;
+        testing_env!(get_context(accounts(5)).block_timestamp(retained_until).build());
+        assert_eq!(contract.cleanup_terminal_orders(vec![order_id.clone(), "unknown".to_string()]), 1);
+        
+        assert!(contract.orders.get(&order_id).is_none());
+        assert!(contract.user_orders.get(&accounts(1)).is_none());
+        assert_eq!(contract.get_statistics(), stats_before);
+        assert_eq!(
+            contract.get_archived_order_stats(),
+            ArchivedOrderStats { claimed: 1, refunded: 0, expired: 0, volume: U128(10000) }
+        );
+    }
+
+    #[test]
+    fn test_cleanup_keeps_live_and_recent_orders() {
+        let (mut contract, order_id) = setup_funded_order(SECRET);
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_order_retention_period(U64(60));
+        
+        // Funded orders are never removed, however old
+        testing_env!(get_context(accounts(5)).block_timestamp(7200 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.cleanup_terminal_orders(vec![order_id.clone()]), 0);
+        
+        let mut order = contract.orders.get(&order_id).unwrap();
+        order.status = OrderStatus::Refunded;
+        contract.orders.insert(&order_id, &order);
+        testing_env!(get_context(accounts(5)).block_timestamp(3659 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.cleanup_terminal_orders(vec![order_id.clone()]), 0);
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(3660 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.cleanup_terminal_orders(vec![order_id.clone()]), 1);
+        assert_eq!(contract.get_archived_order_stats().refunded, 1);
+    }
+
+    #[test]
+    fn test_cross_chain_swap_at_max_amount_accepted() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_max_cross_chain_amount(U128(10000));
+        
+        testing_env!(get_context(accounts(1)).build());
+        let swap_id = create_test_swap(&mut contract, "0xorder");
+        assert_eq!(contract.cross_chain_swaps.get(&swap_id).unwrap().from_amount, U128(10000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Swap amount exceeds the cross-chain maximum")]
+    fn test_cross_chain_swap_over_max_amount_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.set_max_cross_chain_amount(U128(9999));
+        
+        testing_env!(get_context(accounts(1)).build());
+        create_test_swap(&mut contract, "0xorder");
+    }
+
+    fn create_order_msg(order_id: &str) -> String {
+        serde_json::to_string(&EscrowTransferMessage::CreateOrder {
+            order_id: order_id.to_string(),
+            maker: accounts(1),
+            taker: accounts(4),
+            to_token: accounts(3),
+            to_amount: U128(9500),
+            hashlock: hex::encode(env::sha256(SECRET.as_bytes())),
+            secret_length: SECRET.len() as u32,
+            timelock: U64(3600),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_solver_transfer_creates_funded_order() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        contract.set_solver_contract(Some(accounts(5)));
+        
+        // Token accounts(2) forwards the solver contract's transfer
+        testing_env!(get_context(accounts(2)).build());
+        drop(contract.ft_on_transfer(accounts(5), U128(10000), create_order_msg("order_bob_1")));
+        
+        let order = contract.orders.get(&"order_bob_1".to_string()).unwrap();
+        assert_eq!(order.status, OrderStatus::Funded);
+        assert_eq!(order.maker, accounts(1));
+        assert_eq!(order.from_token, accounts(2));
+        assert_eq!(order.from_amount, U128(10000));
+        assert_eq!(contract.user_orders.get(&accounts(1)).unwrap(), vec!["order_bob_1".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the solver contract can create orders on behalf of a maker")]
+    fn test_transfer_from_other_sender_cannot_create_order() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionEscrow::new(accounts(0));
+        contract.add_supported_token(accounts(2));
+        contract.add_supported_token(accounts(3));
+        contract.set_solver_contract(Some(accounts(5)));
+        
+        testing_env!(get_context(accounts(2)).build());
+        drop(contract.ft_on_transfer(accounts(1), U128(10000), create_order_msg("order_bob_1")));
+    }
+}
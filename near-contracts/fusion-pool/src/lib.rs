@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LookupMap, UnorderedMap};
 use near_sdk::json_types::{U128, U64};
@@ -9,12 +10,39 @@ use near_sdk::{
 use near_contract_standards::fungible_token::Balance;
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use fusion_common::MAX_FEE_BPS;
 
 // Gas constants
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_BALANCE: Gas = Gas::from_tgas(5);
+const GAS_FOR_RECOVER_CALLBACK: Gas = Gas::from_tgas(20);
 const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(20);
 const GAS_FOR_POOL_OPERATION: Gas = Gas::from_tgas(30);
 
+// Internal share precision, independent of the pool token's decimals
+const SHARE_DECIMALS: u8 = 18;
+const DEFAULT_TOKEN_DECIMALS: u8 = 24; // Deposits are attached yoctoNEAR
+
+// Reward accrual
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const NANOS_PER_DAY: u128 = 86_400_000_000_000;
+const NANOS_PER_WEEK: u64 = 7 * 86_400 * NANOS_PER_SECOND;
+const REWARD_PER_SHARE_SCALE: u128 = 1_000_000_000_000_000_000_000_000; // 10^24
+const MAX_REWARD_CLAIM_HISTORY: usize = 100; // Claims kept per provider, oldest dropped first
+const MAX_BOOST_BPS: u32 = 10000; // Boost can at most double a provider's rewards
+
+// View limits
+const MAX_POOLS_PER_BATCH: usize = 100;
+const MAX_INVARIANT_RECORDS: u64 = 500; // Providers plus queued withdrawals scanned per invariant page
+
+mod u256 {
+    #![allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+pub use u256::U256;
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct LiquidityPool {
@@ -26,10 +54,19 @@ pub struct LiquidityPool {
     pub total_liquidity: U128,
     pub available_liquidity: U128,
     pub total_shares: U128,
+    pub token_decimals: u8,
     pub fee_rate: u32, // Basis points
     pub min_deposit: U128,
     pub max_deposit: U128,
+    pub max_providers: u32, // 0 = unlimited
+    pub max_total_liquidity: U128, // Ceiling on total_liquidity, 0 = uncapped
+    pub provider_count: u32,
     pub is_active: bool,
+    pub deposits_paused: bool, // Blocks deposits only; withdrawals and claims continue
+    pub claim_cooldown_seconds: U64, // Minimum time between a provider's reward claims
+    pub large_withdrawal_threshold: U128, // Withdrawals above this are queued, 0 = disabled
+    pub withdrawal_delay_seconds: U64,
+    pub dust_threshold: U128, // Remainders worth less than this are swept into the withdrawal
     pub created_at: U64,
     pub last_updated: U64,
 }
@@ -41,20 +78,74 @@ pub struct LiquidityProvider {
     pub pool_id: String,
     pub shares: U128,
     pub deposited_amount: U128,
+    pub avg_entry_price: U128, // yoctoNEAR per whole share
     pub claimed_rewards: U128,
     pub joined_at: U64,
     pub last_claim: U64,
+    pub reward_dust: U128, // Scheduled accrual remainder, in 1 / (10000 * NANOS_PER_DAY) units, carried into the next claim
+    pub accrued_rewards: U128, // Scheduled accrual banked at balance changes, paid with the next claim
+    pub reward_checkpoint: U128, // Pool `accrued_rate_time` when this provider's accrual was last settled
+    pub reward_per_share_paid: U128, // Pool `reward_per_share` when this provider's allocation was last settled
+    pub claim_delegate: Option<AccountId>, // May claim rewards on this provider's behalf
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PoolReward {
     pub pool_id: String,
+    pub reward_token: AccountId,
     pub total_rewards: U128,
     pub distributed_rewards: U128,
     pub reward_rate: u32, // Basis points per day
+    pub reward_start: U64, // Accrual window in nanoseconds, unscheduled when both are 0
+    pub reward_end: U64,
+    pub accrued_rate_time: U128, // Sum of reward_rate * elapsed nanoseconds inside the window
+    pub last_accrual: U64,
+    pub reward_per_share: U128, // Unscheduled rewards allocated per share, scaled by REWARD_PER_SHARE_SCALE
+    pub allocated_rewards: U128, // Part of total_rewards already folded into reward_per_share
+    pub boost_bps_per_week: u32, // Extra rewards per full week a provider has held shares
+    pub max_boost_bps: u32,
+    pub boost_rewards: U128, // Separate budget boosts are paid from, so they never dilute other providers
     pub last_distribution: U64,
-    pub next_distribution: U64,
+    pub next_distribution: U64, // When `distribute_due_rewards` may next release from the undistributed balance
+    pub undistributed_rewards: U128, // Funded by `schedule_rewards`, not yet part of total_rewards
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingWithdrawal {
+    pub id: String,
+    pub pool_id: String,
+    pub provider: AccountId,
+    pub shares: U128, // Held out of the provider's balance until executed
+    pub executable_at: U64,
+}
+
+// Reward budget and projected runout of a pool
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardAnalytics {
+    pub total: U128,
+    pub distributed: U128,
+    pub remaining: U128,
+    pub rate: u32, // Basis points per day
+    pub projected_end: Option<U64>, // None when the pool has no scheduled emission
+}
+
+// Per-pool sums carried between invariant check pages
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantTotals {
+    pub shares: HashMap<String, U128>, // Provider and queued withdrawal shares
+    pub deposited: HashMap<String, U128>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantPage {
+    pub totals: InvariantTotals,
+    pub next_index: Option<u64>, // Pass back with `totals` to continue, None once every record is counted
+    pub holds: Option<bool>, // Set on the last page
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -77,12 +168,97 @@ pub enum PoolAction {
     Withdraw,
     ClaimRewards,
     FeeCollection,
+    EmergencyWithdraw,
+}
+
+// Arguments of `create_pool`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreatePoolArgs {
+    pub pool_id: String,
+    pub name: String,
+    pub description: String,
+    pub token: AccountId,
+    pub fee_rate: u32,
+    pub min_deposit: U128,
+    pub max_deposit: U128,
+    #[serde(default)]
+    pub max_total_liquidity: Option<U128>, // Uncapped when omitted
+}
+
+// `msg` payload of `ft_transfer_call` into the pool contract
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum PoolTransferMessage {
+    AddRewards { pool_id: String },
+    ScheduleRewards { pool_id: String },
+    AddBoostRewards { pool_id: String },
+}
+
+// Balance of a pool's rewards that incoming reward tokens are credited to
+#[derive(Clone, Copy, PartialEq)]
+enum RewardBucket {
+    Claimable, // `total_rewards`
+    Scheduled, // `undistributed_rewards`, released by `distribute_due_rewards`
+    Boost, // `boost_rewards`, only paid out as long-term holder boosts
+}
+
+// NEP-297 events emitted for indexers and frontends
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum PoolEvent {
+    Deposit {
+        pool_id: String,
+        account: AccountId,
+        amount: U128,
+        shares: U128,
+    },
+    Withdraw {
+        pool_id: String,
+        account: AccountId,
+        amount: U128,
+        shares: U128,
+    },
+    ClaimRewards {
+        pool_id: String,
+        account: AccountId,
+        amount: U128,
+        shares: U128,
+    },
+    AddRewards {
+        pool_id: String,
+        account: AccountId,
+        amount: U128,
+        shares: U128,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a PoolEvent,
+}
+
+impl PoolEvent {
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: "fusion-pool",
+            version: "1.0.0",
+            event: self,
+        };
+        env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()));
+    }
 }
 
 // External contract interface for fungible tokens
 #[ext_contract(ext_ft)]
 pub trait ExtFungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) -> Promise;
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
     fn ft_transfer_call(
         &mut self,
         receiver_id: AccountId,
@@ -105,12 +281,19 @@ pub struct FusionPool {
     // Providers
     pub providers: UnorderedMap<String, LiquidityProvider>,
     pub user_pools: LookupMap<AccountId, Vec<String>>,
+    pub pool_providers: LookupMap<String, Vec<AccountId>>, // Accounts holding shares in each pool
+    pub approvals: LookupMap<(AccountId, AccountId, String), U128>, // (owner, spender, pool_id) -> shares the spender may withdraw
     
     // Rewards
     pub rewards: UnorderedMap<String, PoolReward>,
+    pub token_obligations: LookupMap<AccountId, u128>, // Pool liquidity plus unpaid rewards owed in each token
+    pub reward_claims: LookupMap<String, Vec<(U64, U128)>>, // Provider key -> (timestamp, amount) of recent claims
     
     // Transactions
     pub transactions: UnorderedMap<String, PoolTransaction>,
+    pub pending_withdrawals: UnorderedMap<String, PendingWithdrawal>,
+    pub withdrawal_nonce: u64, // Keeps queued withdrawal ids unique within a block
+    pub provider_pending_withdrawals: LookupMap<String, u32>, // Provider key -> queued withdrawals not yet executed
     
     // Statistics
     pub total_pools: u64,
@@ -123,6 +306,7 @@ pub struct FusionPool {
     pub max_pool_fee: u32,
     pub reward_distribution_interval: U64,
     pub min_deposit_amount: U128,
+    pub paused: bool, // Blocks deposits and reward claims; withdrawals stay open so principal is never locked
 }
 
 #[near_bindgen]
@@ -136,31 +320,40 @@ impl FusionPool {
             solver_pools: LookupMap::new(b"s"),
             providers: UnorderedMap::new(b"r"),
             user_pools: LookupMap::new(b"u"),
+            pool_providers: LookupMap::new(b"v"),
+            approvals: LookupMap::new(b"a"),
             rewards: UnorderedMap::new(b"w"),
+            token_obligations: LookupMap::new(b"o"),
+            reward_claims: LookupMap::new(b"c"),
             transactions: UnorderedMap::new(b"t"),
+            pending_withdrawals: UnorderedMap::new(b"q"),
+            withdrawal_nonce: 0,
+            provider_pending_withdrawals: LookupMap::new(b"d"),
             total_pools: 0,
             total_providers: 0,
             total_liquidity: U128(0),
             total_rewards_distributed: U128(0),
             min_pool_fee: 10, // 0.1%
-            max_pool_fee: 1000, // 10%
+            max_pool_fee: MAX_FEE_BPS,
             reward_distribution_interval: U64(86400_000_000_000), // 1 day in nanoseconds
             min_deposit_amount: U128(1_000_000_000_000_000_000_000), // 1 NEAR
+            paused: false,
         }
     }
 
     // Create a new liquidity pool
-    pub fn create_pool(
-        &mut self,
-        pool_id: String,
-        name: String,
-        description: String,
-        token: AccountId,
-        fee_rate: u32,
-        min_deposit: U128,
-        max_deposit: U128,
-    ) -> bool {
+    pub fn create_pool(&mut self, args: CreatePoolArgs) -> bool {
         let solver = env::predecessor_account_id();
+        let CreatePoolArgs {
+            pool_id,
+            name,
+            description,
+            token,
+            fee_rate,
+            min_deposit,
+            max_deposit,
+            max_total_liquidity,
+        } = args;
         
         // Validate fee rate
         assert!(
@@ -173,6 +366,7 @@ impl FusionPool {
         // Validate deposit limits
         assert!(min_deposit.0 <= max_deposit.0, "Min deposit must be less than max deposit");
         assert!(min_deposit.0 >= self.min_deposit_amount.0, "Min deposit too low");
+        assert!(self.pools.get(&pool_id).is_none(), "Pool already exists");
 
         let pool = LiquidityPool {
             id: pool_id.clone(),
@@ -183,15 +377,24 @@ impl FusionPool {
             total_liquidity: U128(0),
             available_liquidity: U128(0),
             total_shares: U128(0),
+            token_decimals: DEFAULT_TOKEN_DECIMALS,
             fee_rate,
             min_deposit,
             max_deposit,
+            max_providers: 0,
+            max_total_liquidity: max_total_liquidity.unwrap_or(U128(0)),
+            provider_count: 0,
             is_active: true,
+            deposits_paused: false,
+            claim_cooldown_seconds: U64(0),
+            large_withdrawal_threshold: U128(0),
+            withdrawal_delay_seconds: U64(0),
+            dust_threshold: U128(0),
             created_at: U64(env::block_timestamp()),
             last_updated: U64(env::block_timestamp()),
         };
 
-        self.pools.insert(&pool_id, &pool);
+        self.internal_save_pool(&pool_id, &pool);
         
         // Add to solver's pools
         let mut solver_pools = self.solver_pools.get(&solver).unwrap_or_default();
@@ -201,13 +404,24 @@ impl FusionPool {
         // Initialize rewards
         let reward = PoolReward {
             pool_id: pool_id.clone(),
+            reward_token: pool.token.clone(),
             total_rewards: U128(0),
             distributed_rewards: U128(0),
             reward_rate: 100, // 1% per day default
+            reward_start: U64(0),
+            reward_end: U64(0),
+            accrued_rate_time: U128(0),
+            last_accrual: U64(env::block_timestamp()),
+            reward_per_share: U128(0),
+            allocated_rewards: U128(0),
+            boost_bps_per_week: 0,
+            max_boost_bps: 0,
+            boost_rewards: U128(0),
             last_distribution: U64(env::block_timestamp()),
             next_distribution: U64(env::block_timestamp() + self.reward_distribution_interval.0),
+            undistributed_rewards: U128(0),
         };
-        self.rewards.insert(&pool_id, &reward);
+        self.internal_save_reward(&pool_id, &reward);
         
         self.total_pools += 1;
         
@@ -215,21 +429,49 @@ impl FusionPool {
     }
 
     // Deposit liquidity into a pool
-    pub fn deposit_liquidity(&mut self, pool_id: String) -> Promise {
+    pub fn deposit_liquidity(&mut self, pool_id: String, min_shares_out: Option<U128>) -> Promise {
         let provider = env::predecessor_account_id();
         let attached_deposit = env::attached_deposit();
+        assert!(!self.paused, "Contract is paused");
         
         let mut pool = self.pools.get(&pool_id).expect("Pool not found");
         assert!(pool.is_active, "Pool is not active");
+        assert!(!pool.deposits_paused, "Pool deposits are paused");
         assert!(attached_deposit >= NearToken::from_yoctonear(pool.min_deposit.0), "Deposit too small");
         assert!(attached_deposit <= NearToken::from_yoctonear(pool.max_deposit.0), "Deposit too large");
+        assert!(
+            pool.max_total_liquidity.0 == 0
+                || pool.total_liquidity.0 + attached_deposit.as_yoctonear() <= pool.max_total_liquidity.0,
+            "Deposit would exceed the pool liquidity cap"
+        );
+        let reward = self.update_pool_rewards(&pool_id);
+        
+        // Enforce provider cap for new providers only
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let is_new_provider = self.providers.get(&provider_key).is_none();
+        if is_new_provider {
+            assert!(
+                pool.max_providers == 0 || pool.provider_count < pool.max_providers,
+                "Pool provider limit reached"
+            );
+            pool.provider_count += 1;
+        }
         
         // Calculate shares to mint
         let shares_to_mint = if pool.total_shares.0 == 0 {
-            attached_deposit.as_yoctonear()
+            to_share_precision(attached_deposit.as_yoctonear(), pool.token_decimals)
         } else {
-            (attached_deposit.as_yoctonear() * pool.total_shares.0) / pool.total_liquidity.0
+            mul_div(attached_deposit.as_yoctonear(), pool.total_shares.0, pool.total_liquidity.0)
         };
+        assert!(shares_to_mint > 0, "Deposit too small to mint shares");
+        if let Some(min_shares_out) = min_shares_out {
+            assert!(
+                shares_to_mint >= min_shares_out.0,
+                "Deposit would mint {} shares, below the minimum of {}",
+                shares_to_mint,
+                min_shares_out.0
+            );
+        }
         
         // Update pool
         pool.total_liquidity = U128(pool.total_liquidity.0 + attached_deposit.as_yoctonear());
@@ -237,27 +479,33 @@ impl FusionPool {
         pool.total_shares = U128(pool.total_shares.0 + shares_to_mint);
         pool.last_updated = U64(env::block_timestamp());
         
-        self.pools.insert(&pool_id, &pool);
+        self.internal_save_pool(&pool_id, &pool);
         
         // Update or create provider
-        let provider_key = format!("{}_{}", provider, pool_id);
-        let mut liquidity_provider = self.providers.get(&provider_key).unwrap_or_else(|| {
-            LiquidityProvider {
-                account_id: provider.clone(),
-                pool_id: pool_id.clone(),
-                shares: U128(0),
-                deposited_amount: U128(0),
-                claimed_rewards: U128(0),
-                joined_at: U64(env::block_timestamp()),
-                last_claim: U64(env::block_timestamp()),
-            }
-        });
+        let mut liquidity_provider = self.providers
+            .get(&provider_key)
+            .unwrap_or_else(|| new_liquidity_provider(&provider, &pool_id, &reward));
+        settle_accrual(&reward, &mut liquidity_provider);
+        
+        // Weighted average of the existing position and this deposit
+        let share_unit = 10u128.pow(SHARE_DECIMALS as u32);
+        let total_cost = U256::from(liquidity_provider.avg_entry_price.0) * U256::from(liquidity_provider.shares.0)
+            + U256::from(attached_deposit.as_yoctonear()) * U256::from(share_unit);
+        let new_shares = liquidity_provider.shares.0 + shares_to_mint;
+        liquidity_provider.avg_entry_price = U128((total_cost / U256::from(new_shares)).as_u128());
         
-        liquidity_provider.shares = U128(liquidity_provider.shares.0 + shares_to_mint);
+        liquidity_provider.shares = U128(new_shares);
         liquidity_provider.deposited_amount = U128(liquidity_provider.deposited_amount.0 + attached_deposit.as_yoctonear());
         
         self.providers.insert(&provider_key, &liquidity_provider);
         
+        // Index the provider under the pool, again if they had withdrawn everything
+        let mut pool_providers = self.pool_providers.get(&pool_id).unwrap_or_default();
+        if !pool_providers.contains(&provider) {
+            pool_providers.push(provider.clone());
+            self.pool_providers.insert(&pool_id, &pool_providers);
+        }
+        
         // Add to user's pools
         let mut user_pools = self.user_pools.get(&provider).unwrap_or_default();
         if !user_pools.contains(&pool_id) {
@@ -278,46 +526,243 @@ impl FusionPool {
             tx_hash: None,
         };
         self.transactions.insert(&tx_id, &transaction);
+        PoolEvent::Deposit {
+            pool_id: pool_id.clone(),
+            account: provider.clone(),
+            amount: transaction.amount,
+            shares: transaction.shares,
+        }
+        .emit();
         
         // Update global statistics
         self.total_liquidity = U128(self.total_liquidity.0 + attached_deposit.as_yoctonear());
-        self.total_providers += 1;
+        if is_new_provider {
+            self.total_providers += 1;
+        }
         
         // Transfer tokens to pool
         ext_ft::ext(pool.token.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
             .ft_transfer(env::current_account_id(), U128(attached_deposit.as_yoctonear()), Some(format!("Deposit to pool {}", pool_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_deposit_transfer_resolved(
+                        pool_id,
+                        provider,
+                        U128(attached_deposit.as_yoctonear()),
+                        U128(shares_to_mint),
+                    ),
+            )
     }
 
-    // Withdraw liquidity from a pool
-    pub fn withdraw_liquidity(&mut self, pool_id: String, shares: U128) -> Promise {
-        let provider = env::predecessor_account_id();
+    // Burn the minted shares and refund the attached NEAR if the deposit transfer failed
+    #[private]
+    pub fn on_deposit_transfer_resolved(
+        &mut self,
+        pool_id: String,
+        provider: AccountId,
+        amount: U128,
+        shares: U128,
+    ) -> bool {
+        if env::promise_result_checked(0, 0).is_ok() {
+            return true;
+        }
+        
+        let reward = self.update_pool_rewards(&pool_id);
+        
+        // The shares may have moved on before this callback ran, so only burn what the provider still holds
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let mut burned = 0;
+        if let Some(mut liquidity_provider) = self.providers.get(&provider_key) {
+            settle_accrual(&reward, &mut liquidity_provider);
+            burned = shares.0.min(liquidity_provider.shares.0);
+            liquidity_provider.shares = U128(liquidity_provider.shares.0 - burned);
+            liquidity_provider.deposited_amount = U128(liquidity_provider.deposited_amount.0.saturating_sub(amount.0));
+            self.providers.insert(&provider_key, &liquidity_provider);
+            if liquidity_provider.shares.0 == 0 {
+                let mut pool_providers = self.pool_providers.get(&pool_id).unwrap_or_default();
+                pool_providers.retain(|account| account != &provider);
+                self.pool_providers.insert(&pool_id, &pool_providers);
+            }
+        }
         
         let mut pool = self.pools.get(&pool_id).expect("Pool not found");
-        assert!(pool.is_active, "Pool is not active");
+        pool.total_liquidity = U128(pool.total_liquidity.0.saturating_sub(amount.0));
+        pool.available_liquidity = U128(pool.available_liquidity.0.saturating_sub(amount.0));
+        pool.total_shares = U128(pool.total_shares.0.saturating_sub(burned));
+        self.internal_save_pool(&pool_id, &pool);
+        
+        self.total_liquidity = U128(self.total_liquidity.0.saturating_sub(amount.0));
+        drop(Promise::new(provider).transfer(NearToken::from_yoctonear(amount.0)));
+        false
+    }
+
+    // Withdraw liquidity from a pool; large withdrawals are queued and return the pending withdrawal id.
+    // Unless `auto_claim` is false, pending rewards are paid out first.
+    pub fn withdraw_liquidity(
+        &mut self,
+        pool_id: String,
+        shares: U128,
+        auto_claim: Option<bool>,
+    ) -> PromiseOrValue<String> {
+        let provider = env::predecessor_account_id();
+        self.internal_withdraw_liquidity(pool_id, provider, None, shares, auto_claim)
+    }
+
+    // Withdraw `owner`'s liquidity within the caller's approval; principal and rewards still go to `owner`
+    pub fn withdraw_liquidity_from(&mut self, owner: AccountId, pool_id: String, shares: U128) -> PromiseOrValue<String> {
+        let spender = env::predecessor_account_id();
+        self.internal_withdraw_liquidity(pool_id, owner, Some(spender), shares, None)
+    }
+
+    fn internal_withdraw_liquidity(
+        &mut self,
+        pool_id: String,
+        provider: AccountId,
+        spender: Option<AccountId>,
+        shares: U128,
+        auto_claim: Option<bool>,
+    ) -> PromiseOrValue<String> {
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        self.update_pool_rewards(&pool_id);
+        let shares = self.sweep_dust_shares(&pool, &provider, shares);
+        
+        // Charged after the dust sweep, so a sweep can't take a spender past its approval
+        if let Some(spender) = spender {
+            let key = (provider.clone(), spender, pool_id.clone());
+            let approved = self.approvals.get(&key).unwrap_or(U128(0));
+            assert!(approved.0 >= shares.0, "Insufficient share approval");
+            if approved.0 == shares.0 {
+                self.approvals.remove(&key);
+            } else {
+                self.approvals.insert(&key, &U128(approved.0 - shares.0));
+            }
+        }
+        
+        // Rewards accrue on the shares about to leave, so claim before they are burned or queued
+        let reward_payout = if auto_claim.unwrap_or(true) {
+            self.internal_auto_claim(&pool, &provider)
+        } else {
+            None
+        };
+        
+        let threshold = pool.large_withdrawal_threshold.0;
+        if threshold > 0
+            && pool.total_shares.0 > 0
+            && mul_div(shares.0, pool.total_liquidity.0, pool.total_shares.0) > threshold
+        {
+            // The reward transfer is still scheduled when dropped
+            drop(reward_payout);
+            return PromiseOrValue::Value(self.internal_queue_withdrawal(pool, provider, shares));
+        }
+        
+        // Each transfer resolves on its own, so a failed reward payout doesn't block the principal
+        let principal = self.internal_withdraw(pool_id, provider, shares);
+        match reward_payout {
+            Some(payout) => PromiseOrValue::Promise(payout.and(principal)),
+            None => PromiseOrValue::Promise(principal),
+        }
+    }
+
+    // Withdraw a token amount instead of a share count; the shares burned are rounded up so the pool never over-pays
+    pub fn withdraw_liquidity_amount(&mut self, pool_id: String, amount: U128) -> PromiseOrValue<String> {
+        assert!(amount.0 > 0, "Amount must be positive");
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert!(pool.total_liquidity.0 > 0, "Pool has no liquidity");
         
+        let shares = mul_div_ceil(amount.0, pool.total_shares.0, pool.total_liquidity.0);
+        let provider_key = format!("{}_{}", env::predecessor_account_id(), pool_id);
+        let held = self.providers.get(&provider_key).expect("Provider not found").shares.0;
+        assert!(held >= shares, "Insufficient shares");
+        self.withdraw_liquidity(pool_id, U128(shares), None)
+    }
+
+    // Let `spender` withdraw up to `shares` of the caller's position in `pool_id`, replacing any earlier approval.
+    // Approvals are pool-scoped since shares of different pools aren't comparable.
+    pub fn approve_shares(&mut self, pool_id: String, spender: AccountId, shares: U128) {
+        assert!(self.pools.get(&pool_id).is_some(), "Pool not found");
+        let owner = env::predecessor_account_id();
+        assert_ne!(owner, spender, "Cannot approve yourself");
+        self.approvals.insert(&(owner, spender, pool_id), &shares);
+    }
+
+    pub fn revoke_shares(&mut self, pool_id: String, spender: AccountId) {
+        self.approvals.remove(&(env::predecessor_account_id(), spender, pool_id));
+    }
+
+    pub fn get_share_approval(&self, owner: AccountId, spender: AccountId, pool_id: String) -> U128 {
+        self.approvals.get(&(owner, spender, pool_id)).unwrap_or(U128(0))
+    }
+
+    // Claim pending rewards and withdraw every share, closing the caller's position in the pool
+    pub fn exit_pool(&mut self, pool_id: String) -> Promise {
+        assert!(!self.paused, "Contract is paused, use withdraw_liquidity");
+        let provider = env::predecessor_account_id();
         let provider_key = format!("{}_{}", provider, pool_id);
-        let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
-        assert!(liquidity_provider.shares.0 >= shares.0, "Insufficient shares");
+        let liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        assert!(liquidity_provider.shares.0 > 0, "No shares to withdraw");
+        assert_eq!(
+            self.provider_pending_withdrawals.get(&provider_key).unwrap_or(0),
+            0,
+            "Execute pending withdrawals before exiting"
+        );
         
-        // Calculate withdrawal amount
-        let withdrawal_amount = (shares.0 * pool.total_liquidity.0) / pool.total_shares.0;
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        let cooldown_remaining = claim_cooldown_remaining(&pool, &liquidity_provider);
+        assert!(
+            cooldown_remaining == 0,
+            "Claim cooldown active, retry after {} seconds or use withdraw_liquidity",
+            cooldown_remaining.div_ceil(NANOS_PER_SECOND)
+        );
+        let threshold = pool.large_withdrawal_threshold.0;
+        assert!(
+            threshold == 0
+                || mul_div(liquidity_provider.shares.0, pool.total_liquidity.0, pool.total_shares.0) <= threshold,
+            "Position exceeds the large withdrawal threshold, use withdraw_liquidity"
+        );
+        self.update_pool_rewards(&pool_id);
+        
+        let (reward_token, reward_amount, boost) = self.internal_claim_rewards(&pool, &provider);
+        let principal = self.internal_withdraw(pool_id.clone(), provider.clone(), liquidity_provider.shares);
+        
+        self.internal_remove_provider(&pool_id, &provider);
+        
+        if reward_amount == 0 {
+            return principal;
+        }
+        reward_transfer(reward_token, provider, reward_amount, boost, &pool_id).and(principal)
+    }
+
+    // Withdraw the caller's whole position without touching reward accounting, forfeiting pending rewards.
+    // A way out for principal if reward state is ever corrupted, so it also works in deactivated pools.
+    pub fn emergency_withdraw(&mut self, pool_id: String) -> Promise {
+        let provider = env::predecessor_account_id();
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        let shares = liquidity_provider.shares;
+        assert!(shares.0 > 0, "No shares to withdraw");
+        assert_eq!(
+            self.provider_pending_withdrawals.get(&provider_key).unwrap_or(0),
+            0,
+            "Execute pending withdrawals before exiting"
+        );
+        
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        let withdrawal_amount = mul_div(shares.0, pool.total_liquidity.0, pool.total_shares.0);
         assert!(withdrawal_amount <= pool.available_liquidity.0, "Insufficient liquidity");
         
-        // Update pool
         pool.total_liquidity = U128(pool.total_liquidity.0 - withdrawal_amount);
         pool.available_liquidity = U128(pool.available_liquidity.0 - withdrawal_amount);
         pool.total_shares = U128(pool.total_shares.0 - shares.0);
         pool.last_updated = U64(env::block_timestamp());
+        self.internal_save_pool(&pool_id, &pool);
         
-        self.pools.insert(&pool_id, &pool);
-        
-        // Update provider
-        liquidity_provider.shares = U128(liquidity_provider.shares.0 - shares.0);
-        liquidity_provider.deposited_amount = U128(liquidity_provider.deposited_amount.0 - withdrawal_amount);
-        
-        self.providers.insert(&provider_key, &liquidity_provider);
+        self.internal_remove_provider(&pool_id, &provider);
+        let mut pool_providers = self.pool_providers.get(&pool_id).unwrap_or_default();
+        pool_providers.retain(|account| account != &provider);
+        self.pool_providers.insert(&pool_id, &pool_providers);
         
         // Record transaction
         let tx_id = format!("tx_{}_{}", provider, env::block_timestamp());
@@ -325,47 +770,174 @@ impl FusionPool {
             id: tx_id.clone(),
             pool_id: pool_id.clone(),
             user: provider.clone(),
-            action: PoolAction::Withdraw,
+            action: PoolAction::EmergencyWithdraw,
             amount: U128(withdrawal_amount),
             shares,
             timestamp: U64(env::block_timestamp()),
             tx_hash: None,
         };
         self.transactions.insert(&tx_id, &transaction);
+        PoolEvent::Withdraw {
+            pool_id: pool_id.clone(),
+            account: provider.clone(),
+            amount: transaction.amount,
+            shares,
+        }
+        .emit();
         
-        // Update global statistics
         self.total_liquidity = U128(self.total_liquidity.0 - withdrawal_amount);
         
-        // Transfer tokens back to provider
-        ext_ft::ext(pool.token.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(provider, U128(withdrawal_amount), Some(format!("Withdraw from pool {}", pool_id)))
+        withdrawal_transfer(pool.token, provider, withdrawal_amount, shares, &pool_id)
     }
 
-    // Claim rewards from a pool
-    pub fn claim_rewards(&mut self, pool_id: String) -> Promise {
-        let provider = env::predecessor_account_id();
+    // Drop a closed position's record and its pool from the provider's list
+    fn internal_remove_provider(&mut self, pool_id: &str, provider: &AccountId) {
+        self.providers.remove(&format!("{}_{}", provider, pool_id));
+        let mut pool = self.pools.get(&pool_id.to_string()).expect("Pool not found");
+        pool.provider_count = pool.provider_count.saturating_sub(1);
+        self.internal_save_pool(pool_id, &pool);
+        
+        let mut user_pools = self.user_pools.get(provider).unwrap_or_default();
+        user_pools.retain(|id| id != pool_id);
+        if user_pools.is_empty() {
+            self.user_pools.remove(provider);
+        } else {
+            self.user_pools.insert(provider, &user_pools);
+        }
+    }
+
+    // Claim rewards as part of a withdrawal, skipping quietly when nothing is claimable yet
+    fn internal_auto_claim(&mut self, pool: &LiquidityPool, provider: &AccountId) -> Option<Promise> {
+        if self.paused {
+            return None;
+        }
+        let provider_key = format!("{}_{}", provider, pool.id);
+        let liquidity_provider = self.providers.get(&provider_key)?;
+        if claim_cooldown_remaining(pool, &liquidity_provider) > 0 {
+            return None;
+        }
+        
+        let (reward_token, reward_amount, boost) = self.internal_claim_rewards(pool, provider);
+        if reward_amount == 0 {
+            return None;
+        }
+        Some(reward_transfer(reward_token, provider.clone(), reward_amount, boost, &pool.id))
+    }
+
+    // Complete a queued withdrawal once its delay has passed
+    pub fn execute_pending_withdrawal(&mut self, id: String) -> Promise {
+        let pending = self.pending_withdrawals.get(&id).expect("Pending withdrawal not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            pending.provider,
+            "Only provider can execute withdrawal"
+        );
+        assert!(
+            env::block_timestamp() >= pending.executable_at.0,
+            "Withdrawal delay not over"
+        );
+        self.pending_withdrawals.remove(&id);
+        
+        // Return the held shares so the regular withdrawal path can burn them
+        let provider_key = format!("{}_{}", pending.provider, pending.pool_id);
+        let queued = self.provider_pending_withdrawals.get(&provider_key).unwrap_or(0);
+        if queued > 1 {
+            self.provider_pending_withdrawals.insert(&provider_key, &(queued - 1));
+        } else {
+            self.provider_pending_withdrawals.remove(&provider_key);
+        }
+        let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        let reward = self.update_pool_rewards(&pending.pool_id);
+        settle_accrual(&reward, &mut liquidity_provider);
+        liquidity_provider.shares = U128(liquidity_provider.shares.0 + pending.shares.0);
+        self.providers.insert(&provider_key, &liquidity_provider);
+        
+        self.internal_withdraw(pending.pool_id, pending.provider, pending.shares)
+    }
+
+    // Widen a withdrawal to a full exit if it would leave shares worth less than the dust threshold
+    fn sweep_dust_shares(&self, pool: &LiquidityPool, provider: &AccountId, shares: U128) -> U128 {
+        if pool.dust_threshold.0 == 0 || pool.total_shares.0 == 0 {
+            return shares;
+        }
+        
+        let provider_key = format!("{}_{}", provider, pool.id);
+        let held = match self.providers.get(&provider_key) {
+            Some(liquidity_provider) => liquidity_provider.shares.0,
+            None => return shares,
+        };
+        let remaining = held.saturating_sub(shares.0);
+        if remaining > 0 && mul_div(remaining, pool.total_liquidity.0, pool.total_shares.0) < pool.dust_threshold.0 {
+            U128(held)
+        } else {
+            shares
+        }
+    }
+
+    fn internal_queue_withdrawal(
+        &mut self,
+        pool: LiquidityPool,
+        provider: AccountId,
+        shares: U128,
+    ) -> String {
+        assert!(pool.is_active, "Pool is not active");
+        
+        let provider_key = format!("{}_{}", provider, pool.id);
+        let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        assert!(liquidity_provider.shares.0 >= shares.0, "Insufficient shares");
+        let reward = self.update_pool_rewards(&pool.id);
+        settle_accrual(&reward, &mut liquidity_provider);
+        liquidity_provider.shares = U128(liquidity_provider.shares.0 - shares.0);
+        self.providers.insert(&provider_key, &liquidity_provider);
+        
+        let id = format!("withdrawal_{}_{}_{}", provider, env::block_timestamp(), self.withdrawal_nonce);
+        self.withdrawal_nonce += 1;
+        let pending = PendingWithdrawal {
+            id: id.clone(),
+            pool_id: pool.id,
+            provider,
+            shares,
+            executable_at: U64(env::block_timestamp() + pool.withdrawal_delay_seconds.0 * NANOS_PER_SECOND),
+        };
+        self.pending_withdrawals.insert(&id, &pending);
+        let queued = self.provider_pending_withdrawals.get(&provider_key).unwrap_or(0);
+        self.provider_pending_withdrawals.insert(&provider_key, &(queued + 1));
         
+        id
+    }
+
+    fn internal_withdraw(&mut self, pool_id: String, provider: AccountId, shares: U128) -> Promise {
         let mut pool = self.pools.get(&pool_id).expect("Pool not found");
-        let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
+        assert!(pool.is_active, "Pool is not active");
         
         let provider_key = format!("{}_{}", provider, pool_id);
         let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        assert!(liquidity_provider.shares.0 >= shares.0, "Insufficient shares");
         
-        // Calculate rewards
-        let reward_amount = self.calculate_rewards(&pool, &reward, &liquidity_provider);
-        assert!(reward_amount > 0, "No rewards to claim");
+        // Calculate withdrawal amount
+        let withdrawal_amount = mul_div(shares.0, pool.total_liquidity.0, pool.total_shares.0);
+        assert!(withdrawal_amount <= pool.available_liquidity.0, "Insufficient liquidity");
+        let reward = self.update_pool_rewards(&pool_id);
+        settle_accrual(&reward, &mut liquidity_provider);
         
-        // Update reward
-        reward.distributed_rewards = U128(reward.distributed_rewards.0 + reward_amount);
-        reward.last_distribution = U64(env::block_timestamp());
-        self.rewards.insert(&pool_id, &reward);
+        // Update pool
+        pool.total_liquidity = U128(pool.total_liquidity.0 - withdrawal_amount);
+        pool.available_liquidity = U128(pool.available_liquidity.0 - withdrawal_amount);
+        pool.total_shares = U128(pool.total_shares.0 - shares.0);
+        pool.last_updated = U64(env::block_timestamp());
+        
+        self.internal_save_pool(&pool_id, &pool);
         
         // Update provider
-        liquidity_provider.claimed_rewards = U128(liquidity_provider.claimed_rewards.0 + reward_amount);
-        liquidity_provider.last_claim = U64(env::block_timestamp());
+        liquidity_provider.shares = U128(liquidity_provider.shares.0 - shares.0);
+        liquidity_provider.deposited_amount = U128(liquidity_provider.deposited_amount.0 - withdrawal_amount);
+        
         self.providers.insert(&provider_key, &liquidity_provider);
+        if liquidity_provider.shares.0 == 0 {
+            let mut pool_providers = self.pool_providers.get(&pool_id).unwrap_or_default();
+            pool_providers.retain(|account| account != &provider);
+            self.pool_providers.insert(&pool_id, &pool_providers);
+        }
         
         // Record transaction
         let tx_id = format!("tx_{}_{}", provider, env::block_timestamp());
@@ -373,58 +945,366 @@ impl FusionPool {
             id: tx_id.clone(),
             pool_id: pool_id.clone(),
             user: provider.clone(),
-            action: PoolAction::ClaimRewards,
-            amount: U128(reward_amount),
-            shares: U128(0),
+            action: PoolAction::Withdraw,
+            amount: U128(withdrawal_amount),
+            shares,
             timestamp: U64(env::block_timestamp()),
             tx_hash: None,
         };
         self.transactions.insert(&tx_id, &transaction);
+        PoolEvent::Withdraw {
+            pool_id: pool_id.clone(),
+            account: provider.clone(),
+            amount: transaction.amount,
+            shares,
+        }
+        .emit();
         
         // Update global statistics
-        self.total_rewards_distributed = U128(self.total_rewards_distributed.0 + reward_amount);
+        self.total_liquidity = U128(self.total_liquidity.0 - withdrawal_amount);
         
-        // Transfer rewards to provider
-        ext_ft::ext(pool.token.clone())
-            .with_attached_deposit(NearToken::from_yoctonear(1))
-            .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer(provider, U128(reward_amount), Some(format!("Claim rewards from pool {}", pool_id)))
+        // Transfer tokens back to provider
+        withdrawal_transfer(pool.token, provider, withdrawal_amount, shares, &pool_id)
     }
 
-    // Calculate rewards for a provider
-    fn calculate_rewards(
-        &self,
-        pool: &LiquidityPool,
-        reward: &PoolReward,
-        provider: &LiquidityProvider,
-    ) -> u128 {
-        if pool.total_shares.0 == 0 || provider.shares.0 == 0 {
-            return 0;
+    // Give the burned shares back if the withdrawal transfer failed
+    #[private]
+    pub fn on_withdraw_transfer_resolved(
+        &mut self,
+        pool_id: String,
+        provider: AccountId,
+        amount: U128,
+        shares: U128,
+    ) -> bool {
+        if env::promise_result_checked(0, 0).is_ok() {
+            return true;
         }
         
-        let provider_share = provider.shares.0 as f64 / pool.total_shares.0 as f64;
-        let total_rewards = reward.total_rewards.0 - reward.distributed_rewards.0;
+        // Accrue at the post-withdrawal share count before the shares return
+        let reward = self.update_pool_rewards(&pool_id);
+        let mut liquidity_provider = self.internal_restore_provider(&pool_id, &provider, &reward);
+        settle_accrual(&reward, &mut liquidity_provider);
+        liquidity_provider.shares = U128(liquidity_provider.shares.0 + shares.0);
+        liquidity_provider.deposited_amount = U128(liquidity_provider.deposited_amount.0 + amount.0);
+        self.providers.insert(&format!("{}_{}", provider, pool_id), &liquidity_provider);
         
-        (total_rewards as f64 * provider_share) as u128
-    }
-
-    // Add rewards to a pool (called by solver)
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        pool.total_liquidity = U128(pool.total_liquidity.0 + amount.0);
+        pool.available_liquidity = U128(pool.available_liquidity.0 + amount.0);
+        pool.total_shares = U128(pool.total_shares.0 + shares.0);
+        self.internal_save_pool(&pool_id, &pool);
+        
+        let mut pool_providers = self.pool_providers.get(&pool_id).unwrap_or_default();
+        if !pool_providers.contains(&provider) {
+            pool_providers.push(provider);
+            self.pool_providers.insert(&pool_id, &pool_providers);
+        }
+        
+        self.total_liquidity = U128(self.total_liquidity.0 + amount.0);
+        false
+    }
+
+    // The provider's record, re-registered if `exit_pool` removed it before a transfer failed
+    fn internal_restore_provider(&mut self, pool_id: &str, provider: &AccountId, reward: &PoolReward) -> LiquidityProvider {
+        let provider_key = format!("{}_{}", provider, pool_id);
+        if let Some(liquidity_provider) = self.providers.get(&provider_key) {
+            return liquidity_provider;
+        }
+        
+        let mut pool = self.pools.get(&pool_id.to_string()).expect("Pool not found");
+        pool.provider_count += 1;
+        self.internal_save_pool(pool_id, &pool);
+        
+        let mut user_pools = self.user_pools.get(provider).unwrap_or_default();
+        if !user_pools.iter().any(|id| id == pool_id) {
+            user_pools.push(pool_id.to_string());
+            self.user_pools.insert(provider, &user_pools);
+        }
+        new_liquidity_provider(provider, pool_id, reward)
+    }
+
+    // Claim rewards from a pool
+    pub fn claim_rewards(&mut self, pool_id: String) -> Promise {
+        let provider = env::predecessor_account_id();
+        self.internal_claim_for(pool_id, provider)
+    }
+
+    // Let `delegate` claim rewards for the caller's position; payouts still go to the caller
+    pub fn set_claim_delegate(&mut self, pool_id: String, delegate: Option<AccountId>) {
+        let provider_key = format!("{}_{}", env::predecessor_account_id(), pool_id);
+        let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        liquidity_provider.claim_delegate = delegate;
+        self.providers.insert(&provider_key, &liquidity_provider);
+    }
+
+    // Claim rewards on behalf of `provider` (called by their claim delegate)
+    pub fn claim_rewards_for(&mut self, pool_id: String, provider: AccountId) -> Promise {
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        assert_eq!(
+            liquidity_provider.claim_delegate,
+            Some(env::predecessor_account_id()),
+            "Only the provider's claim delegate can claim"
+        );
+        self.internal_claim_for(pool_id, provider)
+    }
+
+    fn internal_claim_for(&mut self, pool_id: String, provider: AccountId) -> Promise {
+        assert!(!self.paused, "Contract is paused");
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        
+        let cooldown_remaining = claim_cooldown_remaining(&pool, &liquidity_provider);
+        assert!(
+            cooldown_remaining == 0,
+            "Claim cooldown active, retry after {} seconds",
+            cooldown_remaining.div_ceil(NANOS_PER_SECOND)
+        );
+        self.update_pool_rewards(&pool_id);
+        
+        let (reward_token, reward_amount, boost) = self.internal_claim_rewards(&pool, &provider);
+        assert!(reward_amount > 0, "No rewards to claim");
+        
+        // Transfer rewards to provider
+        reward_transfer(reward_token, provider, reward_amount, boost, &pool_id)
+    }
+
+    // Book `provider`'s pending rewards as claimed; returns the reward token, the amount (0 if none)
+    // and the part of it paid from the boost budget
+    fn internal_claim_rewards(&mut self, pool: &LiquidityPool, provider: &AccountId) -> (AccountId, u128, u128) {
+        let pool_id = pool.id.clone();
+        let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
+        
+        let provider_key = format!("{}_{}", provider, pool_id);
+        let mut liquidity_provider = self.providers.get(&provider_key).expect("Provider not found");
+        
+        // Calculate rewards
+        let (base, boost, reward_dust) = self.calculate_rewards(pool, &reward, &liquidity_provider);
+        let reward_amount = base + boost;
+        if reward_amount == 0 {
+            return (reward.reward_token, 0, 0);
+        }
+        
+        // Update reward
+        reward.distributed_rewards = U128(reward.distributed_rewards.0 + base);
+        reward.boost_rewards = U128(reward.boost_rewards.0 - boost);
+        reward.last_distribution = U64(env::block_timestamp());
+        self.internal_save_reward(&pool_id, &reward);
+        
+        // Update provider
+        liquidity_provider.claimed_rewards = U128(liquidity_provider.claimed_rewards.0 + reward_amount);
+        liquidity_provider.last_claim = U64(env::block_timestamp());
+        liquidity_provider.reward_dust = U128(reward_dust);
+        liquidity_provider.accrued_rewards = U128(0);
+        liquidity_provider.reward_checkpoint = U128(accrued_rate_time(&reward));
+        liquidity_provider.reward_per_share_paid = reward.reward_per_share;
+        self.providers.insert(&provider_key, &liquidity_provider);
+        
+        let mut claims = self.reward_claims.get(&provider_key).unwrap_or_default();
+        if claims.len() == MAX_REWARD_CLAIM_HISTORY {
+            claims.remove(0);
+        }
+        claims.push((U64(env::block_timestamp()), U128(reward_amount)));
+        self.reward_claims.insert(&provider_key, &claims);
+        
+        // Record transaction
+        let tx_id = format!("tx_{}_{}", provider, env::block_timestamp());
+        let transaction = PoolTransaction {
+            id: tx_id.clone(),
+            pool_id: pool_id.clone(),
+            user: provider.clone(),
+            action: PoolAction::ClaimRewards,
+            amount: U128(reward_amount),
+            shares: U128(0),
+            timestamp: U64(env::block_timestamp()),
+            tx_hash: None,
+        };
+        self.transactions.insert(&tx_id, &transaction);
+        PoolEvent::ClaimRewards {
+            pool_id: pool_id.clone(),
+            account: provider.clone(),
+            amount: transaction.amount,
+            shares: liquidity_provider.shares,
+        }
+        .emit();
+        
+        // Update global statistics
+        self.total_rewards_distributed = U128(self.total_rewards_distributed.0 + reward_amount);
+        
+        (reward.reward_token, reward_amount, boost)
+    }
+
+    // Return a failed reward payout to the provider's claimable balance
+    #[private]
+    pub fn on_reward_transfer_resolved(&mut self, pool_id: String, provider: AccountId, amount: U128, boost: U128) -> bool {
+        if env::promise_result_checked(0, 0).is_ok() {
+            return true;
+        }
+        
+        // The boost goes back to its budget; it is recomputed on the next claim
+        let base = amount.0 - boost.0;
+        let mut reward = self.update_pool_rewards(&pool_id);
+        reward.distributed_rewards = U128(reward.distributed_rewards.0 - base);
+        reward.boost_rewards = U128(reward.boost_rewards.0 + boost.0);
+        self.internal_save_reward(&pool_id, &reward);
+        
+        let mut liquidity_provider = self.internal_restore_provider(&pool_id, &provider, &reward);
+        liquidity_provider.claimed_rewards = U128(liquidity_provider.claimed_rewards.0.saturating_sub(amount.0));
+        liquidity_provider.accrued_rewards = U128(liquidity_provider.accrued_rewards.0 + base);
+        let provider_key = format!("{}_{}", provider, pool_id);
+        self.providers.insert(&provider_key, &liquidity_provider);
+        
+        // The claim was never paid, so drop it from the history
+        let mut claims = self.reward_claims.get(&provider_key).unwrap_or_default();
+        if let Some(index) = claims.iter().rposition(|(_, claimed)| *claimed == amount) {
+            claims.remove(index);
+            self.reward_claims.insert(&provider_key, &claims);
+        }
+        
+        self.total_rewards_distributed = U128(self.total_rewards_distributed.0 - amount.0);
+        false
+    }
+
+    // Bring the pool's reward accumulator up to now; must run before any provider balance changes
+    fn update_pool_rewards(&mut self, pool_id: &str) -> PoolReward {
+        let mut reward = self.rewards.get(&pool_id.to_string()).expect("Reward not found");
+        reward.accrued_rate_time = U128(accrued_rate_time(&reward));
+        reward.last_accrual = U64(env::block_timestamp());
+
+        let pool = self.pools.get(&pool_id.to_string()).expect("Pool not found");
+        allocate_rewards(&mut reward, pool.total_shares.0);
+        self.internal_save_reward(pool_id, &reward);
+        reward
+    }
+
+    // Refresh a pool's reward accrual; callable by anyone
+    pub fn poke(&mut self, pool_id: String) {
+        assert!(self.pools.get(&pool_id).is_some(), "Pool not found");
+        self.update_pool_rewards(&pool_id);
+    }
+
+    // Calculate rewards for a provider
+    // Rewards claimable by `provider`, the boost on top paid from the boost budget,
+    // and the accrual remainder to carry into their next claim
+    fn calculate_rewards(
+        &self,
+        pool: &LiquidityPool,
+        reward: &PoolReward,
+        provider: &LiquidityProvider,
+    ) -> (u128, u128, u128) {
+        if pool.total_shares.0 == 0 || provider.shares.0 == 0 {
+            return (0, 0, provider.reward_dust.0);
+        }
+        
+        let total_rewards = reward.total_rewards.0 - reward.distributed_rewards.0;
+        let pool_share = mul_div(total_rewards, provider.shares.0, pool.total_shares.0);
+        
+        let (base, dust) = if reward.reward_end.0 == 0 {
+            let mut reward = reward.clone();
+            allocate_rewards(&mut reward, pool.total_shares.0);
+            pending_accrual(&reward, provider)
+        } else {
+            let (accrued, dust) = pending_accrual(reward, provider);
+            if accrued > pool_share {
+                (pool_share, 0)
+            } else {
+                (accrued, dust)
+            }
+        };
+        
+        // Long-term holders earn a capped boost for every full week since joining
+        let weeks_held = env::block_timestamp().saturating_sub(provider.joined_at.0) / NANOS_PER_WEEK;
+        let boost_bps = (weeks_held as u128 * reward.boost_bps_per_week as u128).min(reward.max_boost_bps as u128);
+        let base = base.min(total_rewards);
+        (base, mul_div(base, boost_bps, 10000).min(reward.boost_rewards.0), dust)
+    }
+
+    // Liquidity and undistributed rewards held for pools of `token`
+    fn internal_token_obligations(&self, token: &AccountId) -> u128 {
+        self.token_obligations.get(token).unwrap_or(0)
+    }
+
+    // Store a pool, moving its liquidity into the token obligations
+    fn internal_save_pool(&mut self, pool_id: &str, pool: &LiquidityPool) {
+        let previous = self.pools.insert(&pool_id.to_string(), pool);
+        if let Some(previous) = previous {
+            self.internal_release_obligation(&previous.token, previous.total_liquidity.0);
+        }
+        self.internal_add_obligation(&pool.token, pool.total_liquidity.0);
+    }
+
+    // Store a pool's reward state, moving its unpaid balance into the token obligations
+    fn internal_save_reward(&mut self, pool_id: &str, reward: &PoolReward) {
+        let previous = self.rewards.insert(&pool_id.to_string(), reward);
+        if let Some(previous) = previous {
+            self.internal_release_obligation(&previous.reward_token, reward_obligation(&previous));
+        }
+        self.internal_add_obligation(&reward.reward_token, reward_obligation(reward));
+    }
+
+    fn internal_add_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.internal_token_obligations(token);
+            self.token_obligations.insert(token, &(obligations + amount));
+        }
+    }
+
+    fn internal_release_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.internal_token_obligations(token);
+            self.token_obligations.insert(token, &obligations.saturating_sub(amount));
+        }
+    }
+
+    // Add rewards to a pool (called by solver)
     pub fn add_rewards(&mut self, pool_id: String, amount: U128) -> bool {
+        let reward = self.rewards.get(&pool_id).expect("Reward not found");
         let solver = env::predecessor_account_id();
-        
+        self.internal_add_rewards(pool_id, solver, reward.reward_token, amount, RewardBucket::Claimable)
+    }
+
+    // Park rewards in the undistributed balance for keepers to release periodically (called by solver)
+    pub fn schedule_rewards(&mut self, pool_id: String, amount: U128) -> bool {
+        let reward = self.rewards.get(&pool_id).expect("Reward not found");
+        let solver = env::predecessor_account_id();
+        self.internal_add_rewards(pool_id, solver, reward.reward_token, amount, RewardBucket::Scheduled)
+    }
+
+    // Fund the budget long-term holder boosts are paid from (called by solver)
+    pub fn add_boost_rewards(&mut self, pool_id: String, amount: U128) -> bool {
+        let reward = self.rewards.get(&pool_id).expect("Reward not found");
+        let solver = env::predecessor_account_id();
+        self.internal_add_rewards(pool_id, solver, reward.reward_token, amount, RewardBucket::Boost)
+    }
+
+    fn internal_add_rewards(
+        &mut self,
+        pool_id: String,
+        solver: AccountId,
+        token: AccountId,
+        amount: U128,
+        bucket: RewardBucket,
+    ) -> bool {
         let pool = self.pools.get(&pool_id).expect("Pool not found");
         assert_eq!(pool.solver, solver, "Only pool solver can add rewards");
         
         let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
-        reward.total_rewards = U128(reward.total_rewards.0 + amount.0);
-        self.rewards.insert(&pool_id, &reward);
+        assert_eq!(reward.reward_token, token, "Reward token mismatch");
+        let balance = match bucket {
+            RewardBucket::Claimable => &mut reward.total_rewards,
+            RewardBucket::Scheduled => &mut reward.undistributed_rewards,
+            RewardBucket::Boost => &mut reward.boost_rewards,
+        };
+        *balance = U128(balance.0 + amount.0);
+        self.internal_save_reward(&pool_id, &reward);
         
         // Record transaction
         let tx_id = format!("tx_{}_{}", solver, env::block_timestamp());
         let transaction = PoolTransaction {
             id: tx_id.clone(),
             pool_id: pool_id.clone(),
-            user: solver,
+            user: solver.clone(),
             action: PoolAction::FeeCollection,
             amount,
             shares: U128(0),
@@ -432,15 +1312,70 @@ impl FusionPool {
             tx_hash: None,
         };
         self.transactions.insert(&tx_id, &transaction);
+        PoolEvent::AddRewards {
+            pool_id,
+            account: solver,
+            amount,
+            shares: U128(0),
+        }
+        .emit();
         
         true
     }
 
+    // Release one interval of `reward_rate` emission on the pool's liquidity from the undistributed balance
+    // into claimable rewards once `next_distribution` has passed; callable by anyone, e.g. keeper bots
+    pub fn distribute_due_rewards(&mut self, pool_id: String) -> U128 {
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        let reward = self.rewards.get(&pool_id).expect("Reward not found");
+        assert!(
+            env::block_timestamp() >= reward.next_distribution.0,
+            "Reward distribution not due until {}",
+            reward.next_distribution.0
+        );
+        
+        // Fold earlier rewards at the current shares before adding this batch
+        let mut reward = self.update_pool_rewards(&pool_id);
+        let interval = self.reward_distribution_interval.0 as u128;
+        let emission = (U256::from(pool.total_liquidity.0) * U256::from(reward.reward_rate) * U256::from(interval)
+            / U256::from(10000 * NANOS_PER_DAY))
+            .as_u128();
+        let amount = emission.min(reward.undistributed_rewards.0);
+        
+        reward.undistributed_rewards = U128(reward.undistributed_rewards.0 - amount);
+        reward.total_rewards = U128(reward.total_rewards.0 + amount);
+        reward.next_distribution = U64(reward.next_distribution.0 + self.reward_distribution_interval.0);
+        allocate_rewards(&mut reward, pool.total_shares.0);
+        self.internal_save_reward(&pool_id, &reward);
+        
+        U128(amount)
+    }
+
+    // Deterministic pool id for a solver, token and salt: hex(sha256("solver:token:salt"))
+    pub fn derive_pool_id(&self, solver: AccountId, token: AccountId, salt: String) -> String {
+        hex::encode(env::sha256(format!("{}:{}:{}", solver, token, salt).as_bytes()))
+    }
+
     // View methods
     pub fn get_pool(&self, pool_id: String) -> String {
         serde_json::to_string(&self.pools.get(&pool_id)).unwrap_or_default()
     }
 
+    // Same as `get_pool`, without the JSON string round trip
+    pub fn get_pool_typed(&self, pool_id: String) -> Option<LiquidityPool> {
+        self.pools.get(&pool_id)
+    }
+
+    // Fetch several pools at once, in input order
+    pub fn get_pools_by_ids(&self, ids: Vec<String>) -> Vec<Option<LiquidityPool>> {
+        assert!(
+            ids.len() <= MAX_POOLS_PER_BATCH,
+            "Cannot fetch more than {} pools at once",
+            MAX_POOLS_PER_BATCH
+        );
+        ids.iter().map(|id| self.pools.get(id)).collect()
+    }
+
     pub fn get_provider(&self, provider_key: String) -> String {
         serde_json::to_string(&self.providers.get(&provider_key)).unwrap_or_default()
     }
@@ -449,6 +1384,32 @@ impl FusionPool {
         serde_json::to_string(&self.rewards.get(&pool_id)).unwrap_or_default()
     }
 
+    // Emission so far and when the remaining budget runs out at the current rate, capped at the schedule end
+    pub fn get_reward_analytics(&self, pool_id: String) -> RewardAnalytics {
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        let reward = self.rewards.get(&pool_id).expect("Reward not found");
+        let remaining = reward.total_rewards.0 - reward.distributed_rewards.0;
+        
+        // Scheduled accrual runs on deposits, which sum to the pool's liquidity
+        let daily_emission = U256::from(pool.total_liquidity.0) * U256::from(reward.reward_rate);
+        let projected_end = if reward.reward_end.0 == 0 || daily_emission.is_zero() {
+            None
+        } else {
+            let start = env::block_timestamp().max(reward.reward_start.0);
+            let runout = U256::from(remaining) * U256::from(10000 * NANOS_PER_DAY) / daily_emission;
+            let runout = if runout > U256::from(u64::MAX) { u64::MAX } else { runout.as_u64() };
+            Some(U64(start.saturating_add(runout).min(reward.reward_end.0)))
+        };
+        
+        RewardAnalytics {
+            total: reward.total_rewards,
+            distributed: reward.distributed_rewards,
+            remaining: U128(remaining),
+            rate: reward.reward_rate,
+            projected_end,
+        }
+    }
+
     pub fn get_user_pools(&self, user: AccountId) -> Vec<String> {
         self.user_pools.get(&user).unwrap_or_default()
     }
@@ -457,17 +1418,158 @@ impl FusionPool {
         self.solver_pools.get(&solver).unwrap_or_default()
     }
 
-    pub fn get_pool_providers(&self, pool_id: String) -> String {
-        // This would need to be implemented with a more efficient data structure
-        // For now, returning empty vector as JSON
-        serde_json::to_string(&Vec::<LiquidityProvider>::new()).unwrap_or_default()
+    pub fn get_pool_providers(&self, pool_id: String) -> Vec<LiquidityProvider> {
+        self.get_pool_providers_paged(pool_id, 0, u64::MAX)
+    }
+
+    // Oldest first; only the most recent MAX_REWARD_CLAIM_HISTORY claims are kept
+    pub fn get_reward_claim_history(
+        &self,
+        account: AccountId,
+        pool_id: String,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<(U64, U128)> {
+        paginate(
+            self.reward_claims.get(&format!("{}_{}", account, pool_id)).unwrap_or_default(),
+            from_index,
+            limit,
+        )
+    }
+
+    // Paged variants of the listings above; a `from_index` past the end returns an empty page
+    pub fn get_user_pools_paged(&self, user: AccountId, from_index: u64, limit: u64) -> Vec<String> {
+        paginate(self.user_pools.get(&user).unwrap_or_default(), from_index, limit)
+    }
+
+    pub fn get_solver_pools_paged(&self, solver: AccountId, from_index: u64, limit: u64) -> Vec<String> {
+        paginate(self.solver_pools.get(&solver).unwrap_or_default(), from_index, limit)
+    }
+
+    pub fn get_pool_providers_paged(&self, pool_id: String, from_index: u64, limit: u64) -> Vec<LiquidityProvider> {
+        paginate(self.pool_providers.get(&pool_id).unwrap_or_default(), from_index, limit)
+            .iter()
+            .filter_map(|provider| self.providers.get(&format!("{}_{}", provider, pool_id)))
+            .collect()
+    }
+
+    // Check that provider positions add up to every pool's shares and liquidity
+    pub fn check_invariants(&self) -> bool {
+        let records = self.providers.len() + self.pending_withdrawals.len();
+        assert!(
+            records <= MAX_INVARIANT_RECORDS,
+            "Too many records, use check_invariants_paged"
+        );
+        self.check_invariants_paged(0, MAX_INVARIANT_RECORDS, None)
+            .holds
+            .expect("Invariant check incomplete")
+    }
+
+    // Scan providers, then queued withdrawals, from `from_index`; compares against pools on the last page
+    pub fn check_invariants_paged(
+        &self,
+        from_index: u64,
+        limit: u64,
+        totals: Option<InvariantTotals>,
+    ) -> InvariantPage {
+        assert!(limit <= MAX_INVARIANT_RECORDS, "Limit cannot exceed {}", MAX_INVARIANT_RECORDS);
+        let mut totals = totals.unwrap_or_default();
+        let providers = self.providers.values_as_vector();
+        let pending = self.pending_withdrawals.values_as_vector();
+        let records = providers.len() + pending.len();
+        let end = from_index.saturating_add(limit).min(records);
+        
+        for index in from_index..end {
+            if index < providers.len() {
+                let provider = providers.get(index).unwrap();
+                add_to_total(&mut totals.shares, &provider.pool_id, provider.shares.0);
+                add_to_total(&mut totals.deposited, &provider.pool_id, provider.deposited_amount.0);
+            } else {
+                let withdrawal = pending.get(index - providers.len()).unwrap();
+                add_to_total(&mut totals.shares, &withdrawal.pool_id, withdrawal.shares.0);
+            }
+        }
+        
+        if end < records {
+            return InvariantPage { totals, next_index: Some(end), holds: None };
+        }
+        
+        let total_of = |map: &HashMap<String, U128>, pool_id: &String| map.get(pool_id).map_or(0, |sum| sum.0);
+        let mut pool_liquidity = 0;
+        let mut holds = true;
+        for (pool_id, pool) in self.pools.iter() {
+            pool_liquidity += pool.total_liquidity.0;
+            holds &= total_of(&totals.shares, &pool_id) == pool.total_shares.0
+                && total_of(&totals.deposited, &pool_id) == pool.total_liquidity.0;
+        }
+        holds &= pool_liquidity == self.total_liquidity.0;
+        
+        InvariantPage { totals, next_index: None, holds: Some(holds) }
     }
 
     pub fn get_statistics(&self) -> (u64, u64, U128, U128) {
         (self.total_pools, self.total_providers, self.total_liquidity, self.total_rewards_distributed)
     }
 
+    // Fee bounds out of range or contract paused
+    pub fn self_test(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.min_pool_fee > self.max_pool_fee {
+            problems.push(format!(
+                "min_pool_fee {} exceeds max_pool_fee {}",
+                self.min_pool_fee, self.max_pool_fee
+            ));
+        }
+        if self.max_pool_fee > MAX_FEE_BPS {
+            problems.push(format!("max_pool_fee {} exceeds {} bps", self.max_pool_fee, MAX_FEE_BPS));
+        }
+        if self.paused {
+            problems.push("contract is paused".to_string());
+        }
+
+        problems
+    }
+
     // Admin methods
+    // Rescue tokens sent to the contract that no tracked obligation accounts for
+    pub fn recover_tokens(&mut self, token: AccountId, amount: U128, to: AccountId) -> Promise {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can recover tokens"
+        );
+
+        ext_ft::ext(token.clone())
+            .with_static_gas(GAS_FOR_FT_BALANCE)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RECOVER_CALLBACK)
+                    .on_recover_tokens_balance(token, amount, to),
+            )
+    }
+
+    #[private]
+    pub fn on_recover_tokens_balance(
+        &mut self,
+        token: AccountId,
+        amount: U128,
+        to: AccountId,
+        #[callback_unwrap] balance: U128,
+    ) -> Promise {
+        let obligations = self.internal_token_obligations(&token);
+        assert!(
+            balance.0.saturating_sub(obligations) >= amount.0,
+            "Recovery would dip into tracked obligations"
+        );
+
+        ext_ft::ext(token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(to, amount, Some("Recover tokens".to_string()))
+    }
+
     pub fn set_min_pool_fee(&mut self, min_fee: u32) {
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set min fee");
         self.min_pool_fee = min_fee;
@@ -475,6 +1577,7 @@ impl FusionPool {
 
     pub fn set_max_pool_fee(&mut self, max_fee: u32) {
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set max fee");
+        assert!(max_fee <= MAX_FEE_BPS, "Max fee cannot exceed 10%");
         self.max_pool_fee = max_fee;
     }
 
@@ -488,45 +1591,343 @@ impl FusionPool {
         self.reward_distribution_interval = interval;
     }
 
+    // Emergency switch across every pool; withdrawals deliberately keep working while paused
+    pub fn pause(&mut self) {
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can pause");
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can unpause");
+        self.paused = false;
+    }
+
+    pub fn set_max_total_liquidity(&mut self, pool_id: String, max_total_liquidity: U128) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set liquidity cap");
+        
+        pool.max_total_liquidity = max_total_liquidity;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn set_max_providers(&mut self, pool_id: String, max_providers: u32) {
+        let caller = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert!(
+            caller == self.owner || caller == pool.solver,
+            "Only owner or pool solver can set max providers"
+        );
+        
+        pool.max_providers = max_providers;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn set_token_decimals(&mut self, pool_id: String, token_decimals: u8) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set token decimals");
+        assert_eq!(pool.total_shares.0, 0, "Cannot change decimals after deposits");
+        assert!(token_decimals <= 36, "Token decimals too large");
+        
+        pool.token_decimals = token_decimals;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
     pub fn deactivate_pool(&mut self, pool_id: String) {
         let solver = env::predecessor_account_id();
         let mut pool = self.pools.get(&pool_id).expect("Pool not found");
         assert_eq!(pool.solver, solver, "Only pool solver can deactivate pool");
         
         pool.is_active = false;
-        self.pools.insert(&pool_id, &pool);
+        self.internal_save_pool(&pool_id, &pool);
     }
 
-    pub fn activate_pool(&mut self, pool_id: String) {
+    pub fn set_reward_token(&mut self, pool_id: String, reward_token: AccountId) {
         let solver = env::predecessor_account_id();
-        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
-        assert_eq!(pool.solver, solver, "Only pool solver can activate pool");
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set reward token");
         
-        pool.is_active = true;
-        self.pools.insert(&pool_id, &pool);
+        let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
+        assert_eq!(
+            reward.total_rewards.0,
+            reward.distributed_rewards.0,
+            "Cannot change reward token with undistributed rewards"
+        );
+        reward.reward_token = reward_token;
+        self.internal_save_reward(&pool_id, &reward);
     }
-}
 
-// Implement FungibleTokenReceiver for handling token transfers
-#[near_bindgen]
-impl FungibleTokenReceiver for FusionPool {
-    fn ft_on_transfer(
-        &mut self,
-        sender_id: AccountId,
-        amount: U128,
-        msg: String,
-    ) -> PromiseOrValue<U128> {
-        // Handle incoming token transfers for pool deposits
-        // This would parse the msg to determine the pool and action
-        PromiseOrValue::Value(U128(0))
+    pub fn set_boost_curve(&mut self, pool_id: String, boost_bps_per_week: u32, max_boost_bps: u32) {
+        let solver = env::predecessor_account_id();
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set boost curve");
+        assert!(max_boost_bps <= MAX_BOOST_BPS, "Max boost cannot exceed {} bps", MAX_BOOST_BPS);
+        
+        let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
+        reward.boost_bps_per_week = boost_bps_per_week;
+        reward.max_boost_bps = max_boost_bps;
+        self.internal_save_reward(&pool_id, &reward);
     }
-}
 
-#[cfg(test)]
+    pub fn set_reward_schedule(&mut self, pool_id: String, start: U64, end: U64, rate: u32) {
+        let solver = env::predecessor_account_id();
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set reward schedule");
+        assert!(start.0 < end.0, "Reward start must be before end");
+        
+        // Accrual so far is booked at the old rate; rewards not yet allocated fund the schedule
+        let mut reward = self.rewards.get(&pool_id).expect("Reward not found");
+        reward.accrued_rate_time = U128(accrued_rate_time(&reward));
+        reward.last_accrual = U64(env::block_timestamp());
+        reward.reward_start = start;
+        reward.reward_end = end;
+        reward.reward_rate = rate;
+        self.internal_save_reward(&pool_id, &reward);
+    }
+
+    pub fn set_withdrawal_queue(
+        &mut self,
+        pool_id: String,
+        large_withdrawal_threshold: U128,
+        withdrawal_delay_seconds: U64,
+    ) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set withdrawal queue");
+        
+        pool.large_withdrawal_threshold = large_withdrawal_threshold;
+        pool.withdrawal_delay_seconds = withdrawal_delay_seconds;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn set_dust_threshold(&mut self, pool_id: String, dust_threshold: U128) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set dust threshold");
+        
+        pool.dust_threshold = dust_threshold;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn get_pending_withdrawal(&self, id: String) -> Option<PendingWithdrawal> {
+        self.pending_withdrawals.get(&id)
+    }
+
+    pub fn set_claim_cooldown(&mut self, pool_id: String, claim_cooldown_seconds: U64) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can set claim cooldown");
+        
+        pool.claim_cooldown_seconds = claim_cooldown_seconds;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn pause_deposits(&mut self, pool_id: String) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can pause deposits");
+        
+        pool.deposits_paused = true;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn resume_deposits(&mut self, pool_id: String) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can resume deposits");
+        
+        pool.deposits_paused = false;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+
+    pub fn activate_pool(&mut self, pool_id: String) {
+        let solver = env::predecessor_account_id();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, solver, "Only pool solver can activate pool");
+        
+        pool.is_active = true;
+        self.internal_save_pool(&pool_id, &pool);
+    }
+}
+
+// Scale a token amount to the internal share precision
+fn to_share_precision(amount: u128, token_decimals: u8) -> u128 {
+    if token_decimals >= SHARE_DECIMALS {
+        amount / 10u128.pow((token_decimals - SHARE_DECIMALS) as u32)
+    } else {
+        amount
+            .checked_mul(10u128.pow((SHARE_DECIMALS - token_decimals) as u32))
+            .expect("Amount too large for share precision")
+    }
+}
+
+// Nanoseconds until `provider` may claim again
+fn claim_cooldown_remaining(pool: &LiquidityPool, provider: &LiquidityProvider) -> u64 {
+    let next_claim = provider.last_claim.0 + pool.claim_cooldown_seconds.0 * NANOS_PER_SECOND;
+    next_claim.saturating_sub(env::block_timestamp())
+}
+
+// Scheduled accumulator value at the current block, including time since `last_accrual`
+fn accrued_rate_time(reward: &PoolReward) -> u128 {
+    let from = reward.last_accrual.0.max(reward.reward_start.0);
+    let to = env::block_timestamp().min(reward.reward_end.0);
+    if to <= from {
+        return reward.accrued_rate_time.0;
+    }
+    reward.accrued_rate_time.0 + reward.reward_rate as u128 * (to - from) as u128
+}
+
+// Fold rewards added to an unscheduled pool into its per-share accumulator, split over the shares now held
+fn allocate_rewards(reward: &mut PoolReward, total_shares: u128) {
+    if reward.reward_end.0 != 0 || total_shares == 0 {
+        return;
+    }
+    let unallocated = reward.total_rewards.0 - reward.allocated_rewards.0;
+    reward.reward_per_share = U128(reward.reward_per_share.0 + mul_div(unallocated, REWARD_PER_SHARE_SCALE, total_shares));
+    reward.allocated_rewards = reward.total_rewards;
+}
+
+// Whole reward units owed to `provider` from the schedule and unscheduled allocations, and the remainder to carry
+fn pending_accrual(reward: &PoolReward, provider: &LiquidityProvider) -> (u128, u128) {
+    // The remainder left by integer division is carried instead of dropped
+    let denominator = U256::from(10000 * NANOS_PER_DAY);
+    let numerator = U256::from(provider.deposited_amount.0)
+        * U256::from(accrued_rate_time(reward) - provider.reward_checkpoint.0)
+        + U256::from(provider.reward_dust.0);
+    let allocated = mul_div(
+        provider.shares.0,
+        reward.reward_per_share.0 - provider.reward_per_share_paid.0,
+        REWARD_PER_SHARE_SCALE,
+    );
+    (
+        provider.accrued_rewards.0 + allocated + (numerator / denominator).as_u128(),
+        (numerator % denominator).as_u128(),
+    )
+}
+
+// Bank accrual on the current balance so a following balance change only affects later accrual
+fn settle_accrual(reward: &PoolReward, provider: &mut LiquidityProvider) {
+    let (accrued, dust) = pending_accrual(reward, provider);
+    provider.accrued_rewards = U128(accrued);
+    provider.reward_dust = U128(dust);
+    provider.reward_checkpoint = U128(accrued_rate_time(reward));
+    provider.reward_per_share_paid = reward.reward_per_share;
+}
+
+fn reward_transfer(reward_token: AccountId, provider: AccountId, amount: u128, boost: u128, pool_id: &str) -> Promise {
+    ext_ft::ext(reward_token)
+        .with_attached_deposit(NearToken::from_yoctonear(1))
+        .with_static_gas(GAS_FOR_FT_TRANSFER)
+        .ft_transfer(provider.clone(), U128(amount), Some(format!("Claim rewards from pool {}", pool_id)))
+        .then(
+            FusionPool::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                .on_reward_transfer_resolved(pool_id.to_string(), provider, U128(amount), U128(boost)),
+        )
+}
+
+fn withdrawal_transfer(token: AccountId, provider: AccountId, amount: u128, shares: U128, pool_id: &str) -> Promise {
+    ext_ft::ext(token)
+        .with_attached_deposit(NearToken::from_yoctonear(1))
+        .with_static_gas(GAS_FOR_FT_TRANSFER)
+        .ft_transfer(provider.clone(), U128(amount), Some(format!("Withdraw from pool {}", pool_id)))
+        .then(
+            FusionPool::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                .on_withdraw_transfer_resolved(pool_id.to_string(), provider, U128(amount), shares),
+        )
+}
+
+// An empty position whose reward accounting starts at the pool's current accrual
+fn new_liquidity_provider(provider: &AccountId, pool_id: &str, reward: &PoolReward) -> LiquidityProvider {
+    LiquidityProvider {
+        account_id: provider.clone(),
+        pool_id: pool_id.to_string(),
+        shares: U128(0),
+        deposited_amount: U128(0),
+        avg_entry_price: U128(0),
+        claimed_rewards: U128(0),
+        joined_at: U64(env::block_timestamp()),
+        last_claim: U64(env::block_timestamp()),
+        reward_dust: U128(0),
+        accrued_rewards: U128(0),
+        reward_checkpoint: reward.accrued_rate_time,
+        reward_per_share_paid: reward.reward_per_share,
+        claim_delegate: None,
+    }
+}
+
+// Rewards still owed to providers, including the undistributed and boost budgets
+fn reward_obligation(reward: &PoolReward) -> u128 {
+    reward.total_rewards.0 - reward.distributed_rewards.0 + reward.undistributed_rewards.0 + reward.boost_rewards.0
+}
+
+fn paginate<T>(items: Vec<T>, from_index: u64, limit: u64) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(from_index as usize)
+        .take(limit as usize)
+        .collect()
+}
+
+fn add_to_total(totals: &mut HashMap<String, U128>, pool_id: &str, amount: u128) {
+    let total = totals.entry(pool_id.to_string()).or_insert(U128(0));
+    total.0 += amount;
+}
+
+// Compute a * b / c without intermediate overflow
+fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    assert!(c > 0, "Division by zero");
+    (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+}
+
+// Compute a * b / c rounded up, without intermediate overflow
+fn mul_div_ceil(a: u128, b: u128, c: u128) -> u128 {
+    assert!(c > 0, "Division by zero");
+    let divisor = U256::from(c);
+    ((U256::from(a) * U256::from(b) + divisor - 1) / divisor).as_u128()
+}
+
+// Implement FungibleTokenReceiver for handling token transfers
+#[near_bindgen]
+impl FungibleTokenReceiver for FusionPool {
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // Handle incoming token transfers for pool deposits
+        // Solvers fund rewards with msg {"add_rewards":{"pool_id":"..."}},
+        // or {"schedule_rewards":{"pool_id":"..."}} to release them through `distribute_due_rewards`,
+        // and fund the boost budget with {"add_boost_rewards":{"pool_id":"..."}}
+        match serde_json::from_str(&msg) {
+            Ok(PoolTransferMessage::AddRewards { pool_id }) => {
+                let token = env::predecessor_account_id();
+                self.internal_add_rewards(pool_id, sender_id, token, amount, RewardBucket::Claimable);
+            }
+            Ok(PoolTransferMessage::ScheduleRewards { pool_id }) => {
+                let token = env::predecessor_account_id();
+                self.internal_add_rewards(pool_id, sender_id, token, amount, RewardBucket::Scheduled);
+            }
+            Ok(PoolTransferMessage::AddBoostRewards { pool_id }) => {
+                let token = env::predecessor_account_id();
+                self.internal_add_rewards(pool_id, sender_id, token, amount, RewardBucket::Boost);
+            }
+            Err(_) => {}
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, AccountId};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, get_logs};
+    use near_sdk::{testing_env, AccountId, PromiseResult};
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -544,19 +1945,21 @@ mod tests {
         
         let mut contract = FusionPool::new(accounts(0), accounts(2));
         
-        let success = contract.create_pool(
-            "pool1".to_string(),
-            "Test Pool".to_string(),
-            "A test liquidity pool".to_string(),
-            accounts(3),
-            100, // 1% fee
-            U128(1000),
-            U128(1000000),
-        );
+        let success = contract.create_pool(CreatePoolArgs {
+            pool_id: "pool1".to_string(),
+            name: "Test Pool".to_string(),
+            description: "A test liquidity pool".to_string(),
+            token: accounts(3),
+            fee_rate: 100,
+            min_deposit: // 1% fee
+            U128(NearToken::from_near(1).as_yoctonear()),
+            max_deposit: U128(NearToken::from_near(10).as_yoctonear()),
+            max_total_liquidity: None,
+        });
         
         assert!(success);
         
-        let pool = contract.get_pool("pool1".to_string());
+        let pool = contract.get_pool_typed("pool1".to_string());
         assert!(pool.is_some());
         assert_eq!(pool.unwrap().name, "Test Pool");
     }
@@ -572,4 +1975,1480 @@ mod tests {
         assert_eq!(stats.0, 0); // total_pools
         assert_eq!(stats.1, 0); // total_providers
     }
-} 
\ No newline at end of file
+
+    fn setup_capped_pool() -> FusionPool {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(2));
+        contract.create_pool(CreatePoolArgs {
+            pool_id: "pool1".to_string(),
+            name: "Test Pool".to_string(),
+            description: "A test liquidity pool".to_string(),
+            token: accounts(3),
+            fee_rate: 100,
+            min_deposit: U128(NearToken::from_near(1).as_yoctonear()),
+            max_deposit: U128(NearToken::from_near(10).as_yoctonear()),
+            max_total_liquidity: None,
+        });
+        contract.set_max_providers("pool1".to_string(), 1);
+        contract
+    }
+
+    fn deposit(contract: &mut FusionPool, provider: AccountId) {
+        deposit_to(contract, "pool1", provider, NearToken::from_near(1).as_yoctonear());
+    }
+
+    fn deposit_to(contract: &mut FusionPool, pool_id: &str, provider: AccountId, amount: u128) {
+        testing_env!(get_context(provider)
+            .attached_deposit(NearToken::from_yoctonear(amount))
+            .build());
+        drop(contract.deposit_liquidity(pool_id.to_string(), None));
+    }
+
+    #[test]
+    fn test_repeat_deposits_count_provider_once() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        deposit(&mut contract, accounts(4));
+        
+        assert_eq!(contract.get_statistics().1, 1);
+    }
+
+    #[test]
+    fn test_existing_provider_can_top_up_at_cap() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        deposit(&mut contract, accounts(4));
+        
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        assert_eq!(pool.provider_count, 1);
+        assert_eq!(pool.total_liquidity, U128(NearToken::from_near(2).as_yoctonear()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool provider limit reached")]
+    fn test_new_provider_rejected_at_cap() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        deposit(&mut contract, accounts(5));
+    }
+
+    #[test]
+    fn test_share_precision_across_token_decimals() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(2));
+        contract.set_min_deposit_amount(U128(1));
+        
+        // One pool for a 6-decimal token, one for a 24-decimal token
+        testing_env!(get_context(accounts(1)).build());
+        for (pool_id, decimals) in [("usdc", 6), ("wnear", 24)] {
+            contract.create_pool(CreatePoolArgs {
+                pool_id: pool_id.to_string(),
+                name: pool_id.to_string(),
+                description: "Test pool".to_string(),
+                token: accounts(3),
+                fee_rate: 100,
+                min_deposit: U128(1),
+                max_deposit: U128(u128::MAX),
+                max_total_liquidity: None,
+            });
+            contract.set_token_decimals(pool_id.to_string(), decimals);
+        }
+        
+        let one_usdc = 1_000_000;
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        deposit_to(&mut contract, "usdc", accounts(4), 5 * one_usdc);
+        deposit_to(&mut contract, "wnear", accounts(4), 5 * one_near);
+        deposit_to(&mut contract, "wnear", accounts(5), 5 * one_near);
+        
+        // Five whole tokens mint the same number of shares in both pools
+        let usdc_provider = contract.providers.get(&format!("{}_usdc", accounts(4))).unwrap();
+        let near_provider = contract.providers.get(&format!("{}_wnear", accounts(4))).unwrap();
+        assert_eq!(usdc_provider.shares, U128(5 * 10u128.pow(18)));
+        assert_eq!(near_provider.shares, usdc_provider.shares);
+        
+        // Withdrawing two of five tokens' worth of shares pays out two tokens
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("usdc".to_string(), U128(2 * 10u128.pow(18)), None));
+        drop(contract.withdraw_liquidity("wnear".to_string(), U128(2 * 10u128.pow(18)), None));
+        
+        assert_eq!(contract.pools.get(&"usdc".to_string()).unwrap().total_liquidity, U128(3 * one_usdc));
+        assert_eq!(contract.pools.get(&"wnear".to_string()).unwrap().total_liquidity, U128(8 * one_near));
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount too large for share precision")]
+    fn test_deposit_overflowing_share_precision_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(2));
+        contract.set_min_deposit_amount(U128(1));
+        testing_env!(get_context(accounts(1)).build());
+        contract.create_pool(CreatePoolArgs {
+            pool_id: "whole".to_string(),
+            name: "whole".to_string(),
+            description: "Test pool".to_string(),
+            token: accounts(3),
+            fee_rate: 100,
+            min_deposit: U128(1),
+            max_deposit: U128(u128::MAX),
+            max_total_liquidity: None,
+        });
+        contract.set_token_decimals("whole".to_string(), 0);
+        
+        // 10^21 whole tokens scaled by 10^18 exceed u128
+        deposit_to(&mut contract, "whole", accounts(4), 10u128.pow(21));
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_mul_div_rejects_zero_denominator() {
+        mul_div(1, 1, 0);
+    }
+
+    #[test]
+    fn test_create_pool_rejects_duplicate_id() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(5)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.create_pool(CreatePoolArgs {
+                pool_id: "pool1".to_string(),
+                name: "Hijacked Pool".to_string(),
+                description: "Overwrites pool1".to_string(),
+                token: accounts(5),
+                fee_rate: 100,
+                min_deposit: U128(NearToken::from_near(1).as_yoctonear()),
+                max_deposit: U128(NearToken::from_near(10).as_yoctonear()),
+                max_total_liquidity: None,
+            });
+        }));
+        assert!(result.is_err());
+        
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        assert_eq!(pool.name, "Test Pool");
+        assert_eq!(pool.solver, accounts(1));
+        assert_eq!(pool.total_liquidity, U128(NearToken::from_near(1).as_yoctonear()));
+        assert_eq!(contract.get_statistics().0, 1);
+    }
+
+    #[test]
+    fn test_avg_entry_price_across_deposits() {
+        let mut contract = setup_capped_pool();
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        let provider_key = format!("{}_pool1", accounts(4));
+        
+        // First deposit at 1 NEAR per share
+        deposit(&mut contract, accounts(4));
+        assert_eq!(contract.providers.get(&provider_key).unwrap().avg_entry_price, U128(one_near));
+        
+        // Simulate pool growth doubling the share price
+        let mut pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        pool.total_liquidity = U128(2 * one_near);
+        pool.available_liquidity = U128(2 * one_near);
+        contract.pools.insert(&"pool1".to_string(), &pool);
+        
+        // Second deposit of 2 NEAR at 2 NEAR per share
+        deposit_to(&mut contract, "pool1", accounts(4), 2 * one_near);
+        let position = contract.providers.get(&provider_key).unwrap();
+        assert_eq!(position.shares, U128(2 * 10u128.pow(18)));
+        assert_eq!(position.avg_entry_price, U128(3 * one_near / 2));
+        
+        // Withdrawals leave the cost basis untouched
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        let position = contract.providers.get(&provider_key).unwrap();
+        assert_eq!(position.shares, U128(10u128.pow(18)));
+        assert_eq!(position.avg_entry_price, U128(3 * one_near / 2));
+    }
+
+    #[test]
+    fn test_exit_pool_closes_position() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.exit_pool("pool1".to_string()));
+        
+        assert_eq!(
+            ft_transfers(),
+            vec![(accounts(4), U128(1000)), (accounts(4), U128(NearToken::from_near(1).as_yoctonear()))]
+        );
+        assert!(contract.providers.get(&format!("{}_pool1", accounts(4))).is_none());
+        assert!(contract.get_user_pools(accounts(4)).is_empty());
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        assert_eq!(pool.total_shares, U128(0));
+        assert_eq!(pool.provider_count, 0);
+        
+        // The freed slot is open to another provider
+        deposit(&mut contract, accounts(5));
+    }
+
+    #[test]
+    fn test_get_pool_providers() {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        deposit(&mut contract, accounts(3));
+        deposit(&mut contract, accounts(4));
+        deposit(&mut contract, accounts(4));
+        
+        let providers: Vec<AccountId> = contract
+            .get_pool_providers("pool1".to_string())
+            .into_iter()
+            .map(|provider| provider.account_id)
+            .collect();
+        assert_eq!(providers, vec![accounts(3), accounts(4)]);
+        
+        // Withdrawing every share drops the provider from the pool's list
+        testing_env!(get_context(accounts(3)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        let providers = contract.get_pool_providers("pool1".to_string());
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].account_id, accounts(4));
+    }
+
+    #[test]
+    fn test_withdrawal_transaction_is_recorded() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(1).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(5 * 10u128.pow(17)), None));
+        
+        let tx_id = format!("tx_{}_1", accounts(4));
+        let transaction = contract.transactions.get(&tx_id).expect("Withdrawal not recorded");
+        assert_eq!(transaction.id, tx_id);
+        assert!(matches!(transaction.action, PoolAction::Withdraw));
+        assert_eq!(transaction.shares, U128(5 * 10u128.pow(17)));
+        assert_eq!(transaction.amount, U128(NearToken::from_near(1).as_yoctonear() / 2));
+    }
+
+    // Receiver and amount of the most recent ft_transfer scheduled by the contract
+    fn ft_transfers() -> Vec<(AccountId, U128)> {
+        get_created_receipts()
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. }
+                    if method_name == b"ft_transfer" =>
+                {
+                    let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                    Some((
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        U128(args["amount"].as_str().unwrap().parse().unwrap()),
+                    ))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn last_ft_transfer() -> (AccountId, U128) {
+        ft_transfers().pop().expect("No ft_transfer call")
+    }
+
+    // Run the recovery callback as if ft_balance_of returned `balance`
+    fn recover_with_balance(contract: &mut FusionPool, token: AccountId, amount: u128, balance: u128) {
+        testing_env!(get_context(accounts(0)).build());
+        drop(contract.on_recover_tokens_balance(token, U128(amount), accounts(5), U128(balance)));
+    }
+
+    #[test]
+    fn test_recover_stray_tokens() {
+        // pool1 holds 1 NEAR of liquidity for token accounts(3)
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let pool_liquidity = NearToken::from_near(1).as_yoctonear();
+        
+        recover_with_balance(&mut contract, accounts(2), 500, 500);
+        assert_eq!(last_ft_transfer(), (accounts(5), U128(500)));
+        
+        recover_with_balance(&mut contract, accounts(3), 100, pool_liquidity + 100);
+        assert_eq!(last_ft_transfer(), (accounts(5), U128(100)));
+    }
+
+    #[test]
+    fn test_token_obligations_follow_liquidity_and_rewards() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let pool_liquidity = NearToken::from_near(1).as_yoctonear();
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        assert_eq!(contract.internal_token_obligations(&accounts(3)), pool_liquidity + 1000);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        assert_eq!(contract.internal_token_obligations(&accounts(3)), pool_liquidity);
+        
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        assert_eq!(contract.internal_token_obligations(&accounts(3)), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery would dip into tracked obligations")]
+    fn test_recover_tokens_rejects_obligated_balance() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let pool_liquidity = NearToken::from_near(1).as_yoctonear();
+        
+        recover_with_balance(&mut contract, accounts(3), 101, pool_liquidity + 100);
+    }
+
+    #[test]
+    fn test_withdraw_and_claim_while_deposits_paused() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        contract.pause_deposits("pool1".to_string());
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.claimed_rewards, U128(1000));
+        assert_eq!(position.shares, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool deposits are paused")]
+    fn test_deposit_rejected_while_deposits_paused() {
+        let mut contract = setup_capped_pool();
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.pause_deposits("pool1".to_string());
+        
+        deposit(&mut contract, accounts(4));
+    }
+
+    #[test]
+    fn test_get_pools_by_ids() {
+        let mut contract = setup_capped_pool();
+        for pool_id in ["pool2", "pool3"] {
+            contract.create_pool(CreatePoolArgs {
+                pool_id: pool_id.to_string(),
+                name: "Test Pool".to_string(),
+                description: "A test liquidity pool".to_string(),
+                token: accounts(3),
+                fee_rate: 100,
+                min_deposit: U128(NearToken::from_near(1).as_yoctonear()),
+                max_deposit: U128(NearToken::from_near(10).as_yoctonear()),
+                max_total_liquidity: None,
+            });
+        }
+        
+        let pools = contract.get_pools_by_ids(vec![
+            "pool3".to_string(),
+            "missing".to_string(),
+            "pool1".to_string(),
+            "pool2".to_string(),
+        ]);
+        
+        let ids: Vec<Option<String>> = pools.into_iter().map(|pool| pool.map(|pool| pool.id)).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("pool3".to_string()),
+                None,
+                Some("pool1".to_string()),
+                Some("pool2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot fetch more than 100 pools at once")]
+    fn test_get_pools_by_ids_is_bounded() {
+        let contract = setup_capped_pool();
+        contract.get_pools_by_ids(vec!["pool1".to_string(); 101]);
+    }
+
+    fn setup_reward_schedule() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        // 1% per day of the 1 NEAR deposit between day 1 and day 3
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(NearToken::from_near(1).as_yoctonear()));
+        contract.set_reward_schedule(
+            "pool1".to_string(),
+            U64(NANOS_PER_DAY as u64),
+            U64(3 * NANOS_PER_DAY as u64),
+            100,
+        );
+        contract
+    }
+
+    fn claim_at(contract: &mut FusionPool, timestamp: u128) -> u128 {
+        testing_env!(get_context(accounts(4)).block_timestamp(timestamp as u64).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        let (_, amount) = last_ft_transfer();
+        amount.0
+    }
+
+    #[test]
+    #[should_panic(expected = "No rewards to claim")]
+    fn test_no_rewards_before_schedule_start() {
+        let mut contract = setup_reward_schedule();
+        claim_at(&mut contract, NANOS_PER_DAY - 1);
+    }
+
+    #[test]
+    fn test_rewards_accrue_within_schedule() {
+        let mut contract = setup_reward_schedule();
+        let one_percent = NearToken::from_near(1).as_yoctonear() / 100;
+        
+        assert_eq!(claim_at(&mut contract, 3 * NANOS_PER_DAY / 2), one_percent / 2);
+        assert_eq!(claim_at(&mut contract, 2 * NANOS_PER_DAY), one_percent / 2);
+        
+        // Only the remaining day until the end accrues
+        assert_eq!(claim_at(&mut contract, 10 * NANOS_PER_DAY), one_percent);
+    }
+
+    #[test]
+    fn test_reward_dust_carries_over_between_claims() {
+        let mut contract = setup_reward_schedule();
+        
+        // Each nanosecond accrues 115740740.74... yocto, so every claim leaves a remainder
+        let mut claimed = 0;
+        for ns in 1..=7 {
+            claimed += claim_at(&mut contract, NANOS_PER_DAY + ns);
+        }
+        
+        let emitted = mul_div(NearToken::from_near(1).as_yoctonear(), 100 * 7, 10000 * NANOS_PER_DAY);
+        assert_eq!(claimed, emitted);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert!(position.reward_dust.0 < 10000 * NANOS_PER_DAY);
+    }
+
+    #[test]
+    fn test_poke_advances_accumulator_consistently_with_claim() {
+        let mut contract = setup_reward_schedule();
+        let one_percent = NearToken::from_near(1).as_yoctonear() / 100;
+        
+        // Anyone can refresh accrual halfway through the window
+        testing_env!(get_context(accounts(5)).block_timestamp(2 * NANOS_PER_DAY as u64).build());
+        contract.poke("pool1".to_string());
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        assert_eq!(reward.accrued_rate_time, U128(100 * NANOS_PER_DAY));
+        assert_eq!(reward.last_accrual, U64(2 * NANOS_PER_DAY as u64));
+        
+        // The poke changes nothing about what the whole window pays out
+        assert_eq!(claim_at(&mut contract, 3 * NANOS_PER_DAY), 2 * one_percent);
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        assert_eq!(reward.accrued_rate_time, U128(200 * NANOS_PER_DAY));
+    }
+
+    #[test]
+    fn test_deposit_mid_window_only_accrues_forward() {
+        let mut contract = setup_reward_schedule();
+        let one_percent = NearToken::from_near(1).as_yoctonear() / 100;
+        
+        // 1 NEAR earns for day 1 to 2, then 2 NEAR for day 2 to 3
+        deposit_at(&mut contract, accounts(4), 2 * NANOS_PER_DAY as u64);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.accrued_rewards, U128(one_percent));
+        
+        assert_eq!(claim_at(&mut contract, 3 * NANOS_PER_DAY), 3 * one_percent);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.accrued_rewards, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool not found")]
+    fn test_poke_unknown_pool() {
+        let mut contract = setup_reward_schedule();
+        contract.poke("missing".to_string());
+    }
+
+    #[test]
+    fn test_reward_analytics_runout_moves_with_budget() {
+        let mut contract = setup_reward_schedule();
+        contract.set_reward_schedule("pool1".to_string(), U64(NANOS_PER_DAY as u64), U64(1000 * NANOS_PER_DAY as u64), 100);
+        
+        // 1 NEAR budget at 1% per day of 1 NEAR lasts 100 days from the schedule start
+        let analytics = contract.get_reward_analytics("pool1".to_string());
+        assert_eq!(analytics.total, U128(NearToken::from_near(1).as_yoctonear()));
+        assert_eq!(analytics.distributed, U128(0));
+        assert_eq!(analytics.remaining, analytics.total);
+        assert_eq!(analytics.rate, 100);
+        assert_eq!(analytics.projected_end, Some(U64(101 * NANOS_PER_DAY as u64)));
+        
+        contract.add_rewards("pool1".to_string(), U128(NearToken::from_near(1).as_yoctonear()));
+        let analytics = contract.get_reward_analytics("pool1".to_string());
+        assert_eq!(analytics.projected_end, Some(U64(201 * NANOS_PER_DAY as u64)));
+    }
+
+    #[test]
+    fn test_reward_analytics_capped_at_schedule_end() {
+        let contract = setup_reward_schedule();
+        let analytics = contract.get_reward_analytics("pool1".to_string());
+        assert_eq!(analytics.projected_end, Some(U64(3 * NANOS_PER_DAY as u64)));
+    }
+
+    #[test]
+    #[should_panic(expected = "No rewards to claim")]
+    fn test_no_rewards_after_schedule_end() {
+        let mut contract = setup_reward_schedule();
+        claim_at(&mut contract, 3 * NANOS_PER_DAY);
+        claim_at(&mut contract, 4 * NANOS_PER_DAY);
+    }
+
+    #[test]
+    fn test_derive_pool_id() {
+        testing_env!(get_context(accounts(1)).build());
+        let contract = FusionPool::new(accounts(0), accounts(2));
+        
+        let pool_id = contract.derive_pool_id(accounts(1), accounts(3), "v1".to_string());
+        assert_eq!(pool_id.len(), 64);
+        assert_eq!(pool_id, contract.derive_pool_id(accounts(1), accounts(3), "v1".to_string()));
+        assert_ne!(pool_id, contract.derive_pool_id(accounts(1), accounts(3), "v2".to_string()));
+        assert_ne!(pool_id, contract.derive_pool_id(accounts(2), accounts(3), "v1".to_string()));
+    }
+
+    fn setup_claim_cooldown() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        contract.set_claim_cooldown("pool1".to_string(), U64(3600));
+        
+        claim_at(&mut contract, 3600 * NANOS_PER_SECOND as u128);
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim cooldown active, retry after 1 seconds")]
+    fn test_claim_rejected_within_cooldown() {
+        let mut contract = setup_claim_cooldown();
+        claim_at(&mut contract, 7199 * NANOS_PER_SECOND as u128);
+    }
+
+    #[test]
+    fn test_claim_allowed_after_cooldown() {
+        let mut contract = setup_claim_cooldown();
+        assert_eq!(claim_at(&mut contract, 7200 * NANOS_PER_SECOND as u128), 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim cooldown active, retry after 1 seconds")]
+    fn test_exit_rejected_within_cooldown() {
+        let mut contract = setup_claim_cooldown();
+        testing_env!(get_context(accounts(4)).block_timestamp(7199 * NANOS_PER_SECOND).build());
+        drop(contract.exit_pool("pool1".to_string()));
+    }
+
+    fn setup_claim_delegate() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        
+        testing_env!(get_context(accounts(4)).build());
+        contract.set_claim_delegate("pool1".to_string(), Some(accounts(5)));
+        contract
+    }
+
+    #[test]
+    fn test_delegate_claims_to_provider() {
+        let mut contract = setup_claim_delegate();
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.claim_rewards_for("pool1".to_string(), accounts(4)));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(1000)));
+        let provider = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(provider.claimed_rewards, U128(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the provider's claim delegate can claim")]
+    fn test_unauthorized_delegate_rejected() {
+        let mut contract = setup_claim_delegate();
+        
+        testing_env!(get_context(accounts(2)).build());
+        drop(contract.claim_rewards_for("pool1".to_string(), accounts(4)));
+    }
+
+    fn transfer_rewards(contract: &mut FusionPool, token: AccountId, amount: u128) {
+        testing_env!(get_context(token).build());
+        let msg = r#"{"add_rewards":{"pool_id":"pool1"}}"#.to_string();
+        drop(contract.ft_on_transfer(accounts(1), U128(amount), msg));
+    }
+
+    #[test]
+    fn test_add_rewards_via_ft_transfer_call() {
+        let mut contract = setup_capped_pool();
+        transfer_rewards(&mut contract, accounts(3), 1000);
+        
+        assert_eq!(contract.rewards.get(&"pool1".to_string()).unwrap().total_rewards, U128(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Reward token mismatch")]
+    fn test_add_rewards_rejects_wrong_token() {
+        let mut contract = setup_capped_pool();
+        transfer_rewards(&mut contract, accounts(2), 1000);
+    }
+
+    #[test]
+    fn test_rewards_paid_in_reward_token() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_reward_token("pool1".to_string(), accounts(5));
+        transfer_rewards(&mut contract, accounts(5), 1000);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        
+        let receipt = get_created_receipts().into_iter().next().unwrap();
+        assert_eq!(receipt.receiver_id, accounts(5));
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(1000)));
+    }
+
+    #[test]
+    fn test_max_pool_fee_defaults_to_shared_cap() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(2));
+        assert_eq!(contract.max_pool_fee, MAX_FEE_BPS);
+        
+        contract.set_max_pool_fee(500);
+        contract.set_max_pool_fee(MAX_FEE_BPS);
+        assert_eq!(contract.max_pool_fee, MAX_FEE_BPS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Max fee cannot exceed 10%")]
+    fn test_max_pool_fee_above_shared_cap_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(2));
+        contract.set_max_pool_fee(MAX_FEE_BPS + 1);
+    }
+
+    fn setup_withdrawal_queue() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit_to(&mut contract, "pool1", accounts(4), NearToken::from_near(2).as_yoctonear());
+        
+        // Withdrawals above 1 NEAR wait an hour
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_withdrawal_queue(
+            "pool1".to_string(),
+            U128(NearToken::from_near(1).as_yoctonear()),
+            U64(3600),
+        );
+        contract
+    }
+
+    fn queue_large_withdrawal(contract: &mut FusionPool) -> String {
+        testing_env!(get_context(accounts(4)).build());
+        match contract.withdraw_liquidity("pool1".to_string(), U128(2 * 10u128.pow(18)), None) {
+            PromiseOrValue::Value(id) => id,
+            PromiseOrValue::Promise(_) => panic!("Large withdrawal was not queued"),
+        }
+    }
+
+    #[test]
+    fn test_small_withdrawal_is_instant() {
+        let mut contract = setup_withdrawal_queue();
+        
+        testing_env!(get_context(accounts(4)).build());
+        let result = contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None);
+        assert!(matches!(result, PromiseOrValue::Promise(_)));
+        drop(result);
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_near(1).as_yoctonear())));
+    }
+
+    #[test]
+    fn test_large_withdrawal_executes_after_delay() {
+        let mut contract = setup_withdrawal_queue();
+        let id = queue_large_withdrawal(&mut contract);
+        
+        // Liquidity stays in the pool while the withdrawal is pending
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        assert_eq!(pool.total_liquidity, U128(NearToken::from_near(2).as_yoctonear()));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3600 * NANOS_PER_SECOND).build());
+        drop(contract.execute_pending_withdrawal(id.clone()));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_near(2).as_yoctonear())));
+        assert!(contract.get_pending_withdrawal(id).is_none());
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.shares, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal delay not over")]
+    fn test_large_withdrawal_blocked_during_delay() {
+        let mut contract = setup_withdrawal_queue();
+        let id = queue_large_withdrawal(&mut contract);
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3599 * NANOS_PER_SECOND).build());
+        drop(contract.execute_pending_withdrawal(id));
+    }
+
+    fn queue_partial_withdrawal(contract: &mut FusionPool) -> String {
+        testing_env!(get_context(accounts(4)).build());
+        match contract.withdraw_liquidity("pool1".to_string(), U128(3 * 10u128.pow(18) / 2), None) {
+            PromiseOrValue::Value(id) => id,
+            PromiseOrValue::Promise(_) => panic!("Large withdrawal was not queued"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Execute pending withdrawals before exiting")]
+    fn test_exit_rejected_while_withdrawal_queued() {
+        let mut contract = setup_withdrawal_queue();
+        queue_partial_withdrawal(&mut contract);
+        drop(contract.exit_pool("pool1".to_string()));
+    }
+
+    #[test]
+    fn test_exit_allowed_after_queued_withdrawal_executes() {
+        let mut contract = setup_withdrawal_queue();
+        let id = queue_partial_withdrawal(&mut contract);
+        
+        testing_env!(get_context(accounts(4)).block_timestamp(3600 * NANOS_PER_SECOND).build());
+        drop(contract.execute_pending_withdrawal(id));
+        drop(contract.exit_pool("pool1".to_string()));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_near(1).as_yoctonear() / 2)));
+        assert!(contract.providers.get(&format!("{}_pool1", accounts(4))).is_none());
+    }
+
+    #[test]
+    fn test_withdrawals_queued_in_same_block_kept_apart() {
+        let mut contract = setup_withdrawal_queue();
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_withdrawal_queue(
+            "pool1".to_string(),
+            U128(NearToken::from_near(1).as_yoctonear() / 2),
+            U64(3600),
+        );
+        
+        testing_env!(get_context(accounts(4)).build());
+        let ids: Vec<String> = (0..2)
+            .map(|_| match contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None) {
+                PromiseOrValue::Value(id) => id,
+                PromiseOrValue::Promise(_) => panic!("Large withdrawal was not queued"),
+            })
+            .collect();
+        
+        assert_ne!(ids[0], ids[1]);
+        for id in ids {
+            assert_eq!(contract.get_pending_withdrawal(id).unwrap().shares, U128(10u128.pow(18)));
+        }
+    }
+
+    // 1 NEAR position in pool1 with 1000 rewards waiting
+    fn setup_rewarded_position() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        contract
+    }
+
+    #[test]
+    fn test_withdraw_auto_claims_rewards() {
+        let mut contract = setup_rewarded_position();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        
+        // Rewards are paid before the principal
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        assert_eq!(ft_transfers(), vec![(accounts(4), U128(1000)), (accounts(4), U128(one_near))]);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.claimed_rewards, U128(1000));
+    }
+
+    #[test]
+    fn test_failed_auto_claim_keeps_rewards_claimable() {
+        let mut contract = setup_rewarded_position();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18) / 2), None));
+        assert_eq!(rewards_of(&contract, accounts(4)), 0);
+        
+        // The principal still goes out while the reward payout is rolled back
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_reward_transfer_resolved("pool1".to_string(), accounts(4), U128(1000), U128(0)));
+        assert_eq!(rewards_of(&contract, accounts(4)), 1000);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.claimed_rewards, U128(0));
+        assert_eq!(position.shares, U128(10u128.pow(18) / 2));
+    }
+
+    #[test]
+    fn test_withdraw_without_auto_claim_pays_principal_only() {
+        let mut contract = setup_rewarded_position();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), Some(false)));
+        
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        assert_eq!(ft_transfers(), vec![(accounts(4), U128(one_near))]);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.claimed_rewards, U128(0));
+    }
+
+    fn setup_dust_threshold() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_dust_threshold("pool1".to_string(), U128(NearToken::from_millinear(100).as_yoctonear()));
+        contract
+    }
+
+    #[test]
+    fn test_withdrawal_leaving_dust_becomes_full_exit() {
+        let mut contract = setup_dust_threshold();
+        
+        // Leaving 0.05 NEAR behind is below the 0.1 NEAR threshold
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(95 * 10u128.pow(16)), None));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_near(1).as_yoctonear())));
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.shares, U128(0));
+    }
+
+    #[test]
+    fn test_withdrawal_above_dust_threshold_is_partial() {
+        let mut contract = setup_dust_threshold();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(5 * 10u128.pow(17)), None));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_millinear(500).as_yoctonear())));
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.shares, U128(5 * 10u128.pow(17)));
+    }
+
+    // One instant and one queued withdrawal out of a 2 NEAR position
+    fn setup_mixed_withdrawals() -> FusionPool {
+        let mut contract = setup_withdrawal_queue();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(5 * 10u128.pow(17)), None));
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(15 * 10u128.pow(17)), None));
+        contract
+    }
+
+    #[test]
+    fn test_invariants_hold_after_deposits_and_withdrawals() {
+        let contract = setup_mixed_withdrawals();
+        assert!(contract.check_invariants());
+        
+        // Same result one record per page
+        let mut page = contract.check_invariants_paged(0, 1, None);
+        while let Some(next_index) = page.next_index {
+            page = contract.check_invariants_paged(next_index, 1, Some(page.totals));
+        }
+        assert_eq!(page.holds, Some(true));
+    }
+
+    #[test]
+    fn test_invariants_flag_corrupted_provider() {
+        let mut contract = setup_withdrawal_queue();
+        let provider_key = format!("{}_pool1", accounts(4));
+        let mut position = contract.providers.get(&provider_key).unwrap();
+        position.shares = U128(position.shares.0 + 1);
+        contract.providers.insert(&provider_key, &position);
+        
+        assert!(!contract.check_invariants());
+    }
+
+    fn deposit_at(contract: &mut FusionPool, provider: AccountId, timestamp: u64) {
+        testing_env!(get_context(provider)
+            .block_timestamp(timestamp)
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        drop(contract.deposit_liquidity("pool1".to_string(), None));
+    }
+
+    fn claim_as(contract: &mut FusionPool, provider: AccountId, timestamp: u64) -> u128 {
+        testing_env!(get_context(provider).block_timestamp(timestamp).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        let (_, amount) = last_ft_transfer();
+        amount.0
+    }
+
+    #[test]
+    fn test_long_held_position_earns_boost() {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        contract.add_rewards("pool1".to_string(), U128(NearToken::from_near(1).as_yoctonear()));
+        contract.set_reward_schedule("pool1".to_string(), U64(4 * NANOS_PER_WEEK), U64(5 * NANOS_PER_WEEK), 100);
+        contract.set_boost_curve("pool1".to_string(), 100, 300);
+        contract.add_boost_rewards("pool1".to_string(), U128(NearToken::from_near(1).as_yoctonear()));
+        
+        // Identical 1 NEAR positions, both accruing only during week 5
+        deposit_at(&mut contract, accounts(4), 0);
+        deposit_at(&mut contract, accounts(5), 4 * NANOS_PER_WEEK);
+        
+        let long_held = claim_as(&mut contract, accounts(4), 5 * NANOS_PER_WEEK);
+        let fresh = claim_as(&mut contract, accounts(5), 5 * NANOS_PER_WEEK);
+        
+        // 1% per day for a week; one week held earns 1%, five weeks hit the 3% cap
+        let base = NearToken::from_near(1).as_yoctonear() * 7 / 100;
+        assert_eq!(fresh, base * 10100 / 10000);
+        assert_eq!(long_held, base * 10300 / 10000);
+        
+        // Both boosts came out of the boost budget
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        assert_eq!(reward.distributed_rewards, U128(2 * base));
+        assert_eq!(reward.boost_rewards, U128(NearToken::from_near(1).as_yoctonear() - base * 400 / 10000));
+    }
+
+    // accounts(3) and accounts(4) share 1000 rewards 1:3 under a 3% boost cap
+    fn setup_boosted_split(boost_budget: u128) -> FusionPool {
+        let mut contract = setup_reward_split(&[(accounts(3), 1), (accounts(4), 3)], 1000);
+        contract.set_boost_curve("pool1".to_string(), 100, 300);
+        contract.add_boost_rewards("pool1".to_string(), U128(boost_budget));
+        contract
+    }
+
+    #[test]
+    fn test_boost_does_not_dilute_other_providers() {
+        let mut contract = setup_boosted_split(1000);
+        
+        // 3% on top of a 250 share
+        assert_eq!(claim_as(&mut contract, accounts(3), 5 * NANOS_PER_WEEK), 257);
+        
+        // accounts(4) keeps its whole 750 share, boosted from the budget as well
+        assert_eq!(rewards_of(&contract, accounts(4)), 772);
+    }
+
+    #[test]
+    fn test_boost_is_capped_by_its_budget() {
+        let mut contract = setup_boosted_split(5);
+        
+        assert_eq!(claim_as(&mut contract, accounts(3), 5 * NANOS_PER_WEEK), 255);
+        assert_eq!(contract.rewards.get(&"pool1".to_string()).unwrap().boost_rewards, U128(0));
+        // With the budget spent, accounts(4) gets its plain share
+        assert_eq!(rewards_of(&contract, accounts(4)), 750);
+    }
+
+    #[test]
+    fn test_failed_boosted_claim_returns_boost_to_budget() {
+        let mut contract = setup_boosted_split(1000);
+        claim_as(&mut contract, accounts(3), 5 * NANOS_PER_WEEK);
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_reward_transfer_resolved("pool1".to_string(), accounts(3), U128(257), U128(7)));
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        assert_eq!(reward.boost_rewards, U128(1000));
+        assert_eq!(reward.distributed_rewards, U128(0));
+        assert_eq!(claim_as(&mut contract, accounts(3), 5 * NANOS_PER_WEEK), 257);
+    }
+
+    #[test]
+    #[should_panic(expected = "Max boost cannot exceed 10000 bps")]
+    fn test_max_boost_is_capped() {
+        let mut contract = setup_capped_pool();
+        contract.set_boost_curve("pool1".to_string(), 100, MAX_BOOST_BPS + 1);
+    }
+
+    // Providers deposit `deposits` NEAR each into pool1, which then receives `rewards`
+    fn setup_reward_split(deposits: &[(AccountId, u128)], rewards: u128) -> FusionPool {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        for (provider, near) in deposits {
+            deposit_to(&mut contract, "pool1", provider.clone(), NearToken::from_near(*near).as_yoctonear());
+        }
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(rewards));
+        contract
+    }
+
+    fn rewards_of(contract: &FusionPool, provider: AccountId) -> u128 {
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        let liquidity_provider = contract.providers.get(&format!("{}_pool1", provider)).unwrap();
+        let (base, boost, _) = contract.calculate_rewards(&pool, &reward, &liquidity_provider);
+        base + boost
+    }
+
+    #[test]
+    fn test_reward_split_is_proportional_to_shares() {
+        let contract = setup_reward_split(&[(accounts(3), 1), (accounts(4), 3)], 1000);
+        assert_eq!(rewards_of(&contract, accounts(3)), 250);
+        assert_eq!(rewards_of(&contract, accounts(4)), 750);
+    }
+
+    #[test]
+    fn test_uneven_reward_split_rounds_down() {
+        let contract = setup_reward_split(&[(accounts(3), 1), (accounts(4), 2)], 1000);
+        assert_eq!(rewards_of(&contract, accounts(3)), 333);
+        assert_eq!(rewards_of(&contract, accounts(4)), 666);
+    }
+
+    #[test]
+    fn test_reward_split_is_exact_beyond_f64_precision() {
+        // 10^24 + 1 has more significant digits than an f64 can hold
+        let contract = setup_reward_split(&[(accounts(3), 1), (accounts(4), 2)], 10u128.pow(24) + 1);
+        assert_eq!(rewards_of(&contract, accounts(3)), 333_333_333_333_333_333_333_333);
+        assert_eq!(rewards_of(&contract, accounts(4)), 666_666_666_666_666_666_666_667);
+    }
+
+    #[test]
+    fn test_late_provider_only_shares_later_rewards() {
+        let mut contract = setup_reward_split(&[(accounts(3), 1)], 1000);
+        deposit(&mut contract, accounts(4));
+        assert_eq!(rewards_of(&contract, accounts(3)), 1000);
+        assert_eq!(rewards_of(&contract, accounts(4)), 0);
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        assert_eq!(rewards_of(&contract, accounts(3)), 1500);
+        assert_eq!(rewards_of(&contract, accounts(4)), 500);
+    }
+
+    #[test]
+    fn test_scheduled_rewards_scale_with_time_held() {
+        let mut contract = setup_reward_schedule();
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_max_providers("pool1".to_string(), 0);
+        
+        // accounts(4) holds from day 1, accounts(3) deposits the same amount on day 2
+        testing_env!(get_context(accounts(3))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(2 * NANOS_PER_DAY as u64)
+            .build());
+        drop(contract.deposit_liquidity("pool1".to_string(), None));
+        
+        testing_env!(get_context(accounts(0)).block_timestamp(3 * NANOS_PER_DAY as u64).build());
+        let one_day = NearToken::from_near(1).as_yoctonear() / 100;
+        assert_eq!(rewards_of(&contract, accounts(4)), 2 * one_day);
+        assert_eq!(rewards_of(&contract, accounts(3)), one_day);
+    }
+
+    #[test]
+    fn test_withdraw_exact_amount() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity_amount("pool1".to_string(), U128(4 * one_near / 10)));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(4 * one_near / 10)));
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.deposited_amount, U128(6 * one_near / 10));
+        assert_eq!(position.shares, U128(6 * 10u128.pow(17)));
+    }
+
+    #[test]
+    fn test_withdraw_amount_rounds_shares_up() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let one_near = NearToken::from_near(1).as_yoctonear();
+        
+        // Half a NEAR is a sixth of the pool, which does not divide the 10^18 shares evenly
+        let mut pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        pool.total_liquidity = U128(3 * one_near);
+        pool.available_liquidity = U128(3 * one_near);
+        contract.pools.insert(&"pool1".to_string(), &pool);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity_amount("pool1".to_string(), U128(one_near / 2)));
+        
+        let (_, paid) = last_ft_transfer();
+        assert!(paid.0 >= one_near / 2);
+        let position = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap();
+        assert_eq!(position.shares, U128(10u128.pow(18) - 166_666_666_666_666_667));
+    }
+
+    #[test]
+    fn test_self_test_passes_on_fresh_contract() {
+        testing_env!(get_context(accounts(0)).build());
+        let contract = FusionPool::new(accounts(0), accounts(1));
+        assert!(contract.self_test().is_empty());
+    }
+
+    #[test]
+    fn test_self_test_reports_misconfiguration() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionPool::new(accounts(0), accounts(1));
+        contract.min_pool_fee = 500;
+        contract.max_pool_fee = 200;
+        
+        let problems = contract.self_test();
+        assert_eq!(problems, vec!["min_pool_fee 500 exceeds max_pool_fee 200".to_string()]);
+    }
+
+    // Resolve the transfer of the current receipt with `result`
+    fn resolve_transfer_with(result: PromiseResult) {
+        testing_env!(
+            get_context(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_failed_deposit_transfer_burns_shares() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let shares = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap().shares;
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        let one_near = U128(NearToken::from_near(1).as_yoctonear());
+        assert!(!contract.on_deposit_transfer_resolved("pool1".to_string(), accounts(4), one_near, shares));
+        
+        let pool = contract.get_pool_typed("pool1".to_string()).unwrap();
+        assert_eq!(pool.total_shares, U128(0));
+        assert_eq!(pool.total_liquidity, U128(0));
+        assert_eq!(contract.total_liquidity, U128(0));
+        assert!(contract.get_pool_providers("pool1".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_failed_deposit_transfer_burns_only_held_shares() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let shares = contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap().shares;
+        
+        // Half the position is withdrawn before the deposit transfer resolves
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(shares.0 / 2), None));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        let one_near = U128(NearToken::from_near(1).as_yoctonear());
+        assert!(!contract.on_deposit_transfer_resolved("pool1".to_string(), accounts(4), one_near, shares));
+        
+        let pool = contract.get_pool_typed("pool1".to_string()).unwrap();
+        assert_eq!(pool.total_shares, U128(0));
+        assert_eq!(pool.total_liquidity, U128(0));
+        assert_eq!(contract.total_liquidity, U128(0));
+        assert_eq!(contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap().shares, U128(0));
+    }
+
+    #[test]
+    fn test_failed_withdraw_transfer_restores_shares() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        let provider_key = format!("{}_pool1", accounts(4));
+        let shares = contract.providers.get(&provider_key).unwrap().shares;
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.exit_pool("pool1".to_string()));
+        assert!(contract.providers.get(&provider_key).is_none());
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        let one_near = U128(NearToken::from_near(1).as_yoctonear());
+        assert!(!contract.on_withdraw_transfer_resolved("pool1".to_string(), accounts(4), one_near, shares));
+        
+        let liquidity_provider = contract.providers.get(&provider_key).unwrap();
+        assert_eq!(liquidity_provider.shares, shares);
+        assert_eq!(liquidity_provider.deposited_amount, one_near);
+        let pool = contract.get_pool_typed("pool1".to_string()).unwrap();
+        assert_eq!(pool.total_shares, shares);
+        assert_eq!(pool.total_liquidity, one_near);
+        assert_eq!(pool.provider_count, 1);
+        assert_eq!(contract.get_user_pools(accounts(4)), vec!["pool1".to_string()]);
+    }
+
+    #[test]
+    fn test_failed_reward_transfer_keeps_rewards_claimable() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        assert_eq!(contract.total_rewards_distributed, U128(1000));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_reward_transfer_resolved("pool1".to_string(), accounts(4), U128(1000), U128(0)));
+        assert_eq!(contract.total_rewards_distributed, U128(0));
+        assert_eq!(rewards_of(&contract, accounts(4)), 1000);
+        
+        resolve_transfer_with(PromiseResult::Successful(vec![]));
+        assert!(contract.on_reward_transfer_resolved("pool1".to_string(), accounts(4), U128(1000), U128(0)));
+    }
+
+    #[test]
+    fn test_emergency_withdraw_returns_principal_without_rewards() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.emergency_withdraw("pool1".to_string()));
+        
+        assert_eq!(ft_transfers(), vec![(accounts(4), U128(NearToken::from_near(1).as_yoctonear()))]);
+        assert_eq!(contract.total_rewards_distributed, U128(0));
+        assert!(contract.providers.get(&format!("{}_pool1", accounts(4))).is_none());
+        let pool = contract.get_pool_typed("pool1".to_string()).unwrap();
+        assert_eq!(pool.total_shares, U128(0));
+        assert_eq!(pool.provider_count, 0);
+    }
+
+    #[test]
+    fn test_emergency_withdraw_from_deactivated_pool() {
+        let mut contract = setup_reward_split(&[(accounts(4), 1), (accounts(5), 1)], 1000);
+        contract.deactivate_pool("pool1".to_string());
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.emergency_withdraw("pool1".to_string()));
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_near(1).as_yoctonear())));
+        
+        // The forfeited share is left for the providers who stay
+        assert_eq!(rewards_of(&contract, accounts(5)), 1000);
+    }
+
+    fn setup_paused_pool() -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.pause();
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_deposit_rejected_while_paused() {
+        let mut contract = setup_paused_pool();
+        deposit(&mut contract, accounts(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_claim_rejected_while_paused() {
+        let mut contract = setup_paused_pool();
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+    }
+
+    #[test]
+    fn test_withdraw_while_paused_pays_principal_only() {
+        let mut contract = setup_paused_pool();
+        assert_eq!(contract.self_test(), vec!["contract is paused".to_string()]);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), None));
+        assert_eq!(ft_transfers(), vec![(accounts(4), U128(NearToken::from_near(1).as_yoctonear()))]);
+        assert_eq!(contract.total_rewards_distributed, U128(0));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.unpause();
+        deposit(&mut contract, accounts(4));
+        assert_eq!(contract.get_pool_typed("pool1".to_string()).unwrap().total_shares, U128(10u128.pow(18)));
+    }
+
+    fn pool_event(event: &str, account: &str, amount: &str, shares: &str) -> String {
+        format!(
+            r#"EVENT_JSON:{{"standard":"fusion-pool","version":"1.0.0","event":"{}","data":{{"pool_id":"pool1","account":"{}","amount":"{}","shares":"{}"}}}}"#,
+            event, account, amount, shares
+        )
+    }
+
+    #[test]
+    fn test_pool_operation_events() {
+        let one_near = NearToken::from_near(1).as_yoctonear().to_string();
+        let shares = 10u128.pow(18).to_string();
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        assert_eq!(get_logs(), vec![pool_event("deposit", "eugene", &one_near, &shares)]);
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.add_rewards("pool1".to_string(), U128(1000));
+        assert_eq!(get_logs(), vec![pool_event("add_rewards", "bob", "1000", "0")]);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_rewards("pool1".to_string()));
+        assert_eq!(get_logs(), vec![pool_event("claim_rewards", "eugene", "1000", &shares)]);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.withdraw_liquidity("pool1".to_string(), U128(10u128.pow(18)), Some(false)));
+        assert_eq!(get_logs(), vec![pool_event("withdraw", "eugene", &one_near, &shares)]);
+    }
+
+    #[test]
+    fn test_pool_listings_paged() {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        for provider in [accounts(2), accounts(3), accounts(4)] {
+            deposit(&mut contract, provider);
+        }
+        
+        let page_of = |from_index, limit| -> Vec<AccountId> {
+            contract
+                .get_pool_providers_paged("pool1".to_string(), from_index, limit)
+                .into_iter()
+                .map(|provider| provider.account_id)
+                .collect()
+        };
+        assert_eq!(page_of(0, 2), vec![accounts(2), accounts(3)]);
+        assert_eq!(page_of(1, 1), vec![accounts(3)]);
+        assert_eq!(page_of(2, 5), vec![accounts(4)]);
+        assert!(page_of(3, 2).is_empty());
+        assert!(page_of(u64::MAX, 2).is_empty());
+        
+        assert_eq!(contract.get_user_pools_paged(accounts(4), 0, 1), vec!["pool1".to_string()]);
+        assert!(contract.get_user_pools_paged(accounts(4), 1, 1).is_empty());
+        assert_eq!(contract.get_solver_pools_paged(accounts(1), 0, 10), vec!["pool1".to_string()]);
+        assert!(contract.get_solver_pools_paged(accounts(1), 10, 10).is_empty());
+    }
+
+    fn setup_share_approval(shares: u128) -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(4)).build());
+        contract.approve_shares("pool1".to_string(), accounts(5), U128(shares));
+        contract
+    }
+
+    #[test]
+    fn test_approved_spender_withdraws_to_owner() {
+        let half = 10u128.pow(18) / 2;
+        let mut contract = setup_share_approval(3 * half / 2);
+        assert_eq!(contract.get_share_approval(accounts(4), accounts(5), "pool1".to_string()), U128(3 * half / 2));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.withdraw_liquidity_from(accounts(4), "pool1".to_string(), U128(half)));
+        
+        assert_eq!(last_ft_transfer(), (accounts(4), U128(NearToken::from_millinear(500).as_yoctonear())));
+        assert_eq!(contract.get_share_approval(accounts(4), accounts(5), "pool1".to_string()), U128(half / 2));
+        assert_eq!(contract.providers.get(&format!("{}_pool1", accounts(4))).unwrap().shares, U128(half));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient share approval")]
+    fn test_spender_cannot_exceed_approval() {
+        let half = 10u128.pow(18) / 2;
+        let mut contract = setup_share_approval(half);
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.withdraw_liquidity_from(accounts(4), "pool1".to_string(), U128(half + 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient share approval")]
+    fn test_revoked_spender_cannot_withdraw() {
+        let mut contract = setup_share_approval(10u128.pow(18));
+        contract.revoke_shares("pool1".to_string(), accounts(5));
+        
+        testing_env!(get_context(accounts(5)).build());
+        drop(contract.withdraw_liquidity_from(accounts(4), "pool1".to_string(), U128(1)));
+    }
+
+    #[test]
+    fn test_deposits_fill_pool_up_to_liquidity_cap() {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        contract.set_max_total_liquidity("pool1".to_string(), U128(NearToken::from_near(3).as_yoctonear()));
+        
+        deposit_to(&mut contract, "pool1", accounts(4), NearToken::from_near(2).as_yoctonear());
+        deposit_to(&mut contract, "pool1", accounts(5), NearToken::from_near(1).as_yoctonear());
+        assert_eq!(
+            contract.get_pool_typed("pool1".to_string()).unwrap().total_liquidity,
+            U128(NearToken::from_near(3).as_yoctonear())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Deposit would exceed the pool liquidity cap")]
+    fn test_deposit_past_liquidity_cap_rejected() {
+        let mut contract = setup_capped_pool();
+        contract.set_max_providers("pool1".to_string(), 0);
+        contract.set_max_total_liquidity("pool1".to_string(), U128(NearToken::from_near(3).as_yoctonear()));
+        
+        deposit_to(&mut contract, "pool1", accounts(4), NearToken::from_near(2).as_yoctonear());
+        deposit_to(&mut contract, "pool1", accounts(5), NearToken::from_near(2).as_yoctonear());
+    }
+
+    #[test]
+    #[should_panic(expected = "below the minimum of")]
+    fn test_deposit_below_min_shares_out_reverts() {
+        let mut contract = setup_capped_pool();
+        testing_env!(get_context(accounts(4))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        drop(contract.deposit_liquidity("pool1".to_string(), Some(U128(u128::MAX))));
+    }
+
+    #[test]
+    fn test_reward_claim_history() {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        for (timestamp, amount) in [(10, 1000), (20, 400)] {
+            testing_env!(get_context(accounts(1)).block_timestamp(timestamp).build());
+            contract.add_rewards("pool1".to_string(), U128(amount));
+            testing_env!(get_context(accounts(4)).block_timestamp(timestamp).build());
+            drop(contract.claim_rewards("pool1".to_string()));
+        }
+        
+        assert_eq!(
+            contract.get_reward_claim_history(accounts(4), "pool1".to_string(), 0, 10),
+            vec![(U64(10), U128(1000)), (U64(20), U128(400))]
+        );
+        assert_eq!(
+            contract.get_reward_claim_history(accounts(4), "pool1".to_string(), 1, 10),
+            vec![(U64(20), U128(400))]
+        );
+        
+        // A claim whose transfer failed is dropped again
+        resolve_transfer_with(PromiseResult::Failed);
+        contract.on_reward_transfer_resolved("pool1".to_string(), accounts(4), U128(400), U128(0));
+        assert_eq!(
+            contract.get_reward_claim_history(accounts(4), "pool1".to_string(), 0, 10),
+            vec![(U64(10), U128(1000))]
+        );
+    }
+
+    fn setup_scheduled_distribution(undistributed: u128) -> FusionPool {
+        let mut contract = setup_capped_pool();
+        deposit(&mut contract, accounts(4));
+        testing_env!(get_context(accounts(1)).build());
+        contract.schedule_rewards("pool1".to_string(), U128(undistributed));
+        contract
+    }
+
+    #[test]
+    fn test_distribute_due_rewards_releases_interval_emission() {
+        // 1% a day of 1 NEAR per daily distribution, the second capped by what is left
+        let daily = NearToken::from_near(1).as_yoctonear() / 100;
+        let mut contract = setup_scheduled_distribution(daily * 3 / 2);
+        assert_eq!(rewards_of(&contract, accounts(4)), 0);
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(NANOS_PER_DAY as u64).build());
+        assert_eq!(contract.distribute_due_rewards("pool1".to_string()), U128(daily));
+        assert_eq!(rewards_of(&contract, accounts(4)), daily);
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(2 * NANOS_PER_DAY as u64).build());
+        assert_eq!(contract.distribute_due_rewards("pool1".to_string()), U128(daily / 2));
+        let reward = contract.rewards.get(&"pool1".to_string()).unwrap();
+        assert_eq!(reward.undistributed_rewards, U128(0));
+        assert_eq!(reward.next_distribution, U64(3 * NANOS_PER_DAY as u64));
+    }
+
+    #[test]
+    #[should_panic(expected = "Reward distribution not due")]
+    fn test_premature_distribution_rejected() {
+        let mut contract = setup_scheduled_distribution(1000);
+        testing_env!(get_context(accounts(5)).block_timestamp(NANOS_PER_DAY as u64).build());
+        contract.distribute_due_rewards("pool1".to_string());
+        contract.distribute_due_rewards("pool1".to_string());
+    }
+}
@@ -1,19 +1,39 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LookupMap, UnorderedMap};
-use near_sdk::json_types::{U128, U64};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, ext_contract, near_bindgen, AccountId, Gas, PanicOnDefault, Promise, PromiseOrValue,
-    NearToken,
+    env, ext_contract, near_bindgen, AccountId, CurveType, Gas, PanicOnDefault, Promise,
+    PromiseOrValue, NearToken, PublicKey,
 };
 use near_contract_standards::fungible_token::Balance;
 use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use fusion_common::MAX_FEE_BPS;
+
+mod u256 {
+    #![allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+use u256::U256;
 
 // Gas constants
 const GAS_FOR_SOLVE: Gas = Gas::from_tgas(50);
 const GAS_FOR_QUOTE: Gas = Gas::from_tgas(20);
 const GAS_FOR_VERIFY: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas::from_tgas(50); // Escrow's ft_on_transfer plus the token's resolve
+const GAS_FOR_RESOLVE_ESCROW: Gas = Gas::from_tgas(10);
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_BALANCE: Gas = Gas::from_tgas(5);
+const GAS_FOR_RECOVER_CALLBACK: Gas = Gas::from_tgas(20);
+
+// Provider fees per staked yoctoNEAR are tracked with 30 extra decimals
+const FEE_PER_STAKE_PRECISION: u128 = 1_000_000_000_000_000_000_000_000_000_000;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -30,6 +50,8 @@ pub struct Solver {
     pub total_fees: U128,
     pub registered_at: U64,
     pub last_active: U64,
+    pub slashed_until: U64,
+    pub consecutive_failures: u32,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -43,10 +65,24 @@ pub struct SolverPool {
     pub fee_rate: u32, // Basis points
     pub min_order_size: U128,
     pub max_order_size: U128,
+    pub max_pending_orders: u32, // 0 = unlimited
+    pub pending_order_count: u32,
+    pub fee_token: Option<AccountId>, // Token provider fees are paid in and claimed in
+    pub fee_per_stake: U128, // Fees received per staked yoctoNEAR, scaled by FEE_PER_STAKE_PRECISION
+    pub provider_fee_share_bps: u32, // Share of each executed order's fee credited to providers
     pub is_active: bool,
     pub created_at: U64,
 }
 
+// A liquidity provider's position in a solver pool
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProviderStake {
+    pub stake: U128,
+    pub unclaimed_rewards: U128, // Pool fee token earned, settled up to `fee_debt`
+    pub fee_debt: U128, // stake * fee_per_stake at the last settlement
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FusionOrder {
@@ -57,12 +93,29 @@ pub struct FusionOrder {
     pub from_amount: U128,
     pub to_amount: U128,
     pub min_to_amount: U128,
+    pub filled_amount: U128,
     pub deadline: U64,
+    pub auto_extend_on_near_expiry: bool,
+    pub deadline_extended: bool,
+    pub expiry_notified: bool, // OrderNearExpiry already emitted for the current deadline
     pub solver: Option<AccountId>,
+    pub pool_id: Option<String>, // Pool of the quote the order was created from
+    pub fee: U128, // Quoted fee, 0 for orders without a known quote
     pub status: OrderStatus,
     pub created_at: U64,
     pub filled_at: Option<U64>,
     pub tx_hash: Option<String>,
+    pub settlement_proof: Option<SettlementProof>,
+    pub escrow_order_id: Option<String>, // Funded fusion-escrow order opened by `accept_and_escrow`
+    pub escrow_pending: bool, // Tokens on their way to fusion-escrow; cleared by `on_order_escrowed`
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettlementProof {
+    pub chain_id: String,
+    pub block_height: U64,
+    pub tx_hash: String,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
@@ -93,6 +146,7 @@ pub struct QuoteRequest {
     pub amount: U128,
     pub user: AccountId,
     pub deadline: U64,
+    pub nonce: String, // Must be echoed back by the quoting solver
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -108,22 +162,209 @@ pub struct QuoteResponse {
     pub solver: AccountId,
     pub pool_id: String,
     pub fee: U128,
+    pub fee_rate: u32, // Pool fee rate in basis points when quoted
     pub valid_until: U64,
 }
 
-// External contract interface for escrow contract
-#[ext_contract(ext_escrow)]
-pub trait ExtEscrow {
-    fn create_order(
-        &mut self,
+// Page of solver state for off-chain order-book reconstruction
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StateSnapshot {
+    pub orders: Vec<FusionOrder>,
+    pub pools: Vec<SolverPool>,
+    pub solvers: Vec<Solver>,
+    pub next_index: Option<u64>,
+}
+
+// Arguments of `provide_quote`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProvideQuoteArgs {
+    pub quote_id: String,
+    pub nonce: String, // Must echo the quote request's nonce
+    pub to_amount: U128,
+    pub price: String,
+    pub gas_estimate: U128,
+    pub pool_id: String,
+    pub fee: U128,
+    pub valid_until: U64,
+}
+
+// Arguments of `create_order`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateOrderArgs {
+    pub quote_id: String,
+    pub from_token: AccountId,
+    pub to_token: AccountId,
+    pub from_amount: U128,
+    pub to_amount: U128,
+    pub min_to_amount: U128,
+    pub deadline: U64,
+    pub solver: AccountId,
+}
+
+// Order intent signed off-chain by the user and submitted by a relayer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderIntent {
+    pub user: AccountId,
+    pub quote_id: String,
+    pub from_token: AccountId,
+    pub to_token: AccountId,
+    pub from_amount: U128,
+    pub to_amount: U128,
+    pub min_to_amount: U128,
+    pub deadline: U64,
+    pub solver: AccountId,
+    pub nonce: u64,
+}
+
+// Terms of an `accept_and_escrow` transfer message
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AcceptAndEscrowArgs {
+    pub quote_id: String,
+    pub min_to_amount: U128,
+    pub deadline: U64,
+    pub hashlock: String,
+    pub secret_length: u32,
+    pub timelock: U64, // Seconds
+}
+
+// `ft_on_transfer` messages accepted from fungible token contracts
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SolverTransferMessage {
+    // Accept a quote and lock the transferred tokens in fusion-escrow in one flow
+    AcceptAndEscrow(AcceptAndEscrowArgs),
+    // Pay liquidity providers of a pool their fees, in the pool fee token
+    PayProviderFees {
+        pool_id: String,
+    },
+}
+
+// Message opening a funded order in fusion-escrow, mirroring its `EscrowTransferMessage`
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+enum EscrowTransferMessage {
+    CreateOrder {
+        order_id: String,
+        maker: AccountId,
         taker: AccountId,
-        from_token: AccountId,
         to_token: AccountId,
-        from_amount: U128,
         to_amount: U128,
         hashlock: String,
+        secret_length: u32,
         timelock: U64,
-    ) -> String;
+    },
+}
+
+// Arguments of fusion-escrow's `create_order`, mirroring its `CreateOrderArgs`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowOrderArgs {
+    pub taker: AccountId,
+    pub from_token: AccountId,
+    pub to_token: AccountId,
+    pub from_amount: U128,
+    pub to_amount: U128,
+    pub hashlock: String,
+    pub secret_length: u32,
+    pub timelock: U64,
+    #[serde(default)]
+    pub refund_bounty_opt_in: bool,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+// NEP-297 events emitted for off-chain solvers
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum SolverEvent {
+    QuoteRequested {
+        quote_id: String,
+        user: AccountId,
+        from_token: AccountId,
+        to_token: AccountId,
+        amount: U128,
+        deadline: U64,
+        nonce: String,
+    },
+    OrderCreated {
+        order_id: String,
+        user: AccountId,
+        solver: AccountId,
+        from_token: AccountId,
+        to_token: AccountId,
+        from_amount: U128,
+        to_amount: U128,
+        min_to_amount: U128,
+        deadline: U64,
+    },
+    OrderExecuted {
+        order_id: String,
+        user: AccountId,
+        solver: AccountId,
+        from_token: AccountId,
+        to_token: AccountId,
+        from_amount: U128,
+        to_amount: U128,
+        tx_hash: String,
+    },
+    OrderCancelled {
+        order_id: String,
+        user: AccountId,
+        from_token: AccountId,
+        to_token: AccountId,
+    },
+    OrderNearExpiry {
+        order_id: String,
+        user: AccountId,
+        deadline: U64,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a SolverEvent,
+}
+
+impl SolverEvent {
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: "fusion-solver",
+            version: "1.0.0",
+            event: self,
+        };
+        env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()));
+    }
+}
+
+// External contract interface for fungible tokens
+#[ext_contract(ext_ft)]
+pub trait ExtFungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) -> Promise;
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> Promise;
+}
+
+// External contract interface for escrow contract
+#[ext_contract(ext_escrow)]
+pub trait ExtEscrow {
+    fn create_order(&mut self, args: EscrowOrderArgs) -> String;
     
     fn fund_order(&mut self, order_id: String) -> Promise;
 }
@@ -140,16 +381,27 @@ pub struct FusionSolver {
     
     // Pools
     pub pools: UnorderedMap<String, SolverPool>,
+    pub provider_stakes: LookupMap<String, ProviderStake>,
     pub solver_pools: LookupMap<AccountId, Vec<String>>,
+    pub token_obligations: LookupMap<AccountId, u128>, // Unclaimed provider fees and tokens in flight to escrow
     
     // Orders
     pub orders: UnorderedMap<String, FusionOrder>,
     pub user_orders: LookupMap<AccountId, Vec<String>>,
     pub pending_orders: UnorderedMap<String, QuoteRequest>,
     
+    // Quotes
+    pub quotes: LookupMap<String, QuoteResponse>,
+    pub solver_quotes: LookupMap<AccountId, Vec<QuoteResponse>>, // Every quote a solver provided, as provided
+    pub quote_timestamps: LookupMap<AccountId, Vec<u64>>, // Each solver's quotes within the rate limit window
+    
     // Chain signatures
     pub signatures: UnorderedMap<String, ChainSignature>,
     
+    // Gasless order intents
+    pub intent_keys: LookupMap<AccountId, PublicKey>,
+    pub intent_nonces: LookupMap<AccountId, u64>,
+    
     // Statistics
     pub total_orders: u64,
     pub total_volume: U128,
@@ -159,6 +411,16 @@ pub struct FusionSolver {
     pub min_solver_stake: U128,
     pub max_solver_fee: u32, // Basis points
     pub quote_timeout: U64,
+    pub deadline_extension_window: U64,
+    pub expiry_warning_window: U64, // Nanoseconds before a deadline that `check_expiring_orders` reports an order
+    pub max_deadline_extension: U64,
+    pub slash_cooldown: U64,
+    pub max_consecutive_failures: u32, // 0 = never auto-deactivate
+    pub min_fill_bps: u32, // Smallest partial fill as a share of the remaining amount, 0 = any size
+    pub min_quote_improvement_bps: u32, // Margin a competing quote must beat the best `to_amount` by
+    pub max_quotes_per_window: u32, // Quotes a solver may provide per `quote_window`, 0 = unlimited
+    pub quote_window: U64, // Nanoseconds
+    pub read_only: bool, // Rejects every mutation except `set_read_only`
 }
 
 #[near_bindgen]
@@ -171,17 +433,34 @@ impl FusionSolver {
             solvers: UnorderedMap::new(b"s"),
             active_solvers: UnorderedMap::new(b"a"),
             pools: UnorderedMap::new(b"p"),
+            provider_stakes: LookupMap::new(b"l"),
             solver_pools: LookupMap::new(b"s"),
+            token_obligations: LookupMap::new(b"f"),
             orders: UnorderedMap::new(b"o"),
             user_orders: LookupMap::new(b"u"),
             pending_orders: UnorderedMap::new(b"q"),
+            quotes: LookupMap::new(b"r"),
+            solver_quotes: LookupMap::new(b"h"),
+            quote_timestamps: LookupMap::new(b"t"),
             signatures: UnorderedMap::new(b"i"),
+            intent_keys: LookupMap::new(b"k"),
+            intent_nonces: LookupMap::new(b"n"),
             total_orders: 0,
             total_volume: U128(0),
             total_fees: U128(0),
             min_solver_stake: U128(100_000_000_000_000_000_000_000), // 100 NEAR
             max_solver_fee: 500, // 5%
             quote_timeout: U64(300_000_000_000), // 5 minutes in nanoseconds
+            deadline_extension_window: U64(60_000_000_000), // 1 minute in nanoseconds
+            expiry_warning_window: U64(300_000_000_000), // 5 minutes in nanoseconds
+            max_deadline_extension: U64(300_000_000_000), // 5 minutes in nanoseconds
+            slash_cooldown: U64(86_400_000_000_000), // 1 day in nanoseconds
+            max_consecutive_failures: 3,
+            min_fill_bps: 0,
+            min_quote_improvement_bps: 0,
+            max_quotes_per_window: 0,
+            quote_window: U64(60_000_000_000), // 1 minute in nanoseconds
+            read_only: false,
         }
     }
 
@@ -193,6 +472,7 @@ impl FusionSolver {
         version: String,
         code_hash: String,
     ) -> bool {
+        self.assert_not_read_only();
         let solver_id = env::predecessor_account_id();
         
         // Check if solver already exists
@@ -213,6 +493,8 @@ impl FusionSolver {
             total_fees: U128(0),
             registered_at: U64(env::block_timestamp()),
             last_active: U64(env::block_timestamp()),
+            slashed_until: U64(0),
+            consecutive_failures: 0,
         };
 
         self.solvers.insert(&solver_id, &solver);
@@ -229,12 +511,14 @@ impl FusionSolver {
         min_order_size: U128,
         max_order_size: U128,
     ) -> bool {
+        self.assert_not_read_only();
         let solver_id = env::predecessor_account_id();
         
         // Verify solver is registered and active
         let solver = self.solvers.get(&solver_id).expect("Solver not found");
         assert!(solver.is_active, "Solver is not active");
         assert!(fee_rate <= self.max_solver_fee, "Fee rate too high");
+        assert!(self.pools.get(&pool_id).is_none(), "Pool already exists");
 
         let pool = SolverPool {
             id: pool_id.clone(),
@@ -245,6 +529,11 @@ impl FusionSolver {
             fee_rate,
             min_order_size,
             max_order_size,
+            max_pending_orders: 0,
+            pending_order_count: 0,
+            fee_token: None,
+            fee_per_stake: U128(0),
+            provider_fee_share_bps: 0,
             is_active: true,
             created_at: U64(env::block_timestamp()),
         };
@@ -261,6 +550,7 @@ impl FusionSolver {
 
     // Add liquidity to a pool
     pub fn add_liquidity(&mut self, pool_id: String) -> Promise {
+        self.assert_not_read_only();
         let provider = env::predecessor_account_id();
         let attached_deposit = env::attached_deposit();
         
@@ -279,6 +569,18 @@ impl FusionSolver {
         
         self.pools.insert(&pool_id, &pool);
         
+        let key = stake_key(&pool_id, &provider);
+        let mut stake = self.provider_stakes.get(&key).unwrap_or(ProviderStake {
+            stake: U128(0),
+            unclaimed_rewards: U128(0),
+            fee_debt: U128(0),
+        });
+        // Fees paid before this deposit only accrue to the stake held at the time
+        settle_provider_fees(&pool, &mut stake);
+        stake.stake = U128(stake.stake.0 + attached_deposit.as_yoctonear());
+        stake.fee_debt = U128(mul_div(stake.stake.0, pool.fee_per_stake.0, FEE_PER_STAKE_PRECISION));
+        self.provider_stakes.insert(&key, &stake);
+        
         // Return success promise
         Promise::new(env::current_account_id()).transfer(NearToken::from_yoctonear(0))
     }
@@ -291,6 +593,7 @@ impl FusionSolver {
         amount: U128,
         deadline: U64,
     ) -> String {
+        self.assert_not_read_only();
         let user = env::predecessor_account_id();
         let quote_id = format!("quote_{}_{}", user, env::block_timestamp());
         
@@ -300,28 +603,75 @@ impl FusionSolver {
             amount,
             user,
             deadline,
+            nonce: quote_nonce(&quote_id),
+        };
+        
+        self.internal_add_quote_request(quote_id.clone(), &request);
+        quote_id
+    }
+
+    // Request a quote for the unfilled remainder of a partially filled order
+    pub fn request_quote_for_remaining(&mut self, order_id: String) -> String {
+        self.assert_not_read_only();
+        let user = env::predecessor_account_id();
+        let order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.user, user, "Only order owner can request a quote");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        
+        let remaining = order.from_amount.0 - order.filled_amount.0;
+        assert!(remaining > 0, "Order has no remaining amount");
+        
+        let quote_id = format!("quote_{}_{}", user, env::block_timestamp());
+        let request = QuoteRequest {
+            from_token: order.from_token,
+            to_token: order.to_token,
+            amount: U128(remaining),
+            user,
+            deadline: order.deadline,
+            nonce: quote_nonce(&quote_id),
         };
         
-        self.pending_orders.insert(&quote_id, &request);
+        self.internal_add_quote_request(quote_id.clone(), &request);
         quote_id
     }
 
+    fn internal_add_quote_request(&mut self, quote_id: String, request: &QuoteRequest) {
+        self.pending_orders.insert(&quote_id, request);
+        // Quotes compete per request, so a reused id starts without a best quote
+        self.quotes.remove(&quote_id);
+        
+        SolverEvent::QuoteRequested {
+            quote_id,
+            user: request.user.clone(),
+            from_token: request.from_token.clone(),
+            to_token: request.to_token.clone(),
+            amount: request.amount,
+            deadline: request.deadline,
+            nonce: request.nonce.clone(),
+        }
+        .emit();
+    }
+
     // Provide a quote (called by solvers)
-    pub fn provide_quote(
-        &mut self,
-        quote_id: String,
-        to_amount: U128,
-        price: String,
-        gas_estimate: U128,
-        pool_id: String,
-        fee: U128,
-        valid_until: U64,
-    ) -> String {
+    pub fn provide_quote(&mut self, args: ProvideQuoteArgs) -> String {
+        self.assert_not_read_only();
+        let ProvideQuoteArgs {
+            quote_id,
+            nonce,
+            to_amount,
+            price,
+            gas_estimate,
+            pool_id,
+            fee,
+            valid_until,
+        } = args;
         let solver_id = env::predecessor_account_id();
         
         // Verify solver is active
         let solver = self.solvers.get(&solver_id).expect("Solver not found");
         assert!(solver.is_active, "Solver is not active");
+        assert!(env::block_timestamp() >= solver.slashed_until.0, "Solver is in slashing cooldown");
+        self.consume_quote_budget(&solver_id);
         
         // Verify pool exists and belongs to solver
         let pool = self.pools.get(&pool_id).expect("Pool not found");
@@ -331,6 +681,19 @@ impl FusionSolver {
         // Verify quote request exists
         let request = self.pending_orders.get(&quote_id).expect("Quote request not found");
         assert!(env::block_timestamp() <= request.deadline.0, "Quote request expired");
+        assert_eq!(nonce, request.nonce, "Quote nonce mismatch");
+        assert!(request.amount.0 >= pool.min_order_size.0, "Amount below pool minimum order size");
+        assert!(request.amount.0 <= pool.max_order_size.0, "Amount above pool maximum order size");
+        
+        // A competing quote replaces the best one only if it beats it by the configured margin
+        if let Some(best) = self.quotes.get(&quote_id) {
+            let required = mul_div(best.to_amount.0, 10000 + self.min_quote_improvement_bps as u128, 10000);
+            assert!(
+                to_amount.0 > best.to_amount.0 && to_amount.0 >= required,
+                "Quote must improve on the best to_amount by at least {} bps",
+                self.min_quote_improvement_bps
+            );
+        }
         
         let response = QuoteResponse {
             quote_id: quote_id.clone(),
@@ -340,31 +703,236 @@ impl FusionSolver {
             to_amount,
             price,
             gas_estimate,
-            solver: solver_id,
+            solver: solver_id.clone(),
             pool_id,
             fee,
+            fee_rate: pool.fee_rate,
             valid_until,
         };
         
-        // Remove pending order
-        self.pending_orders.remove(&quote_id);
+        // The request stays open to competing quotes until an order consumes it
+        // Record quote in solver's history
+        self.quotes.insert(&quote_id, &response);
+        let mut solver_quotes = self.solver_quotes.get(&solver_id).unwrap_or_default();
+        solver_quotes.push(response.clone());
+        self.solver_quotes.insert(&solver_id, &solver_quotes);
         
         serde_json::to_string(&response).unwrap_or_default()
     }
 
     // Create and execute a Fusion order
-    pub fn create_order(
+    pub fn create_order(&mut self, args: CreateOrderArgs) -> String {
+        self.assert_not_read_only();
+        let user = env::predecessor_account_id();
+        let quote = self.quotes.get(&args.quote_id).expect("Quote not found");
+        self.internal_create_order(user, quote, args)
+    }
+
+    // Create the order for `quote_id` and forward the user's `amount` of `from_token` to fusion-escrow,
+    // which opens a funded order under the same id; `on_order_escrowed` rolls back if escrow rejects it
+    fn internal_accept_and_escrow(
         &mut self,
-        quote_id: String,
+        user: AccountId,
         from_token: AccountId,
-        to_token: AccountId,
-        from_amount: U128,
-        to_amount: U128,
-        min_to_amount: U128,
-        deadline: U64,
-        solver: AccountId,
-    ) -> String {
+        amount: U128,
+        args: AcceptAndEscrowArgs,
+    ) -> Promise {
+        self.assert_not_read_only();
+        let AcceptAndEscrowArgs {
+            quote_id,
+            min_to_amount,
+            deadline,
+            hashlock,
+            secret_length,
+            timelock,
+        } = args;
+        let request = self.pending_orders.get(&quote_id).expect("Quote request not found");
+        assert_eq!(request.user, user, "Quote requested by another account");
+        let quote = self.quotes.get(&quote_id).expect("Quote not found");
+        assert!(env::block_timestamp() <= quote.valid_until.0, "Quote expired");
+        assert_eq!(quote.from_token, from_token, "Token does not match quote");
+        assert_eq!(quote.from_amount, amount, "Amount does not match quote");
+        
+        let to_token = quote.to_token.clone();
+        let to_amount = quote.to_amount;
+        let solver = quote.solver.clone();
+        let order_id = self.internal_create_order(
+            user.clone(),
+            quote,
+            CreateOrderArgs {
+                quote_id,
+                from_token: from_token.clone(),
+                to_token: to_token.clone(),
+                from_amount: amount,
+                to_amount,
+                min_to_amount,
+                deadline,
+                solver: solver.clone(),
+            },
+        );
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        order.escrow_pending = true;
+        self.orders.insert(&order_id, &order);
+        self.internal_add_obligation(&from_token, amount.0);
+        
+        let msg = EscrowTransferMessage::CreateOrder {
+            order_id: order_id.clone(),
+            maker: user,
+            taker: solver,
+            to_token,
+            to_amount,
+            hashlock,
+            secret_length,
+            timelock,
+        };
+        ext_ft::ext(from_token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER_CALL)
+            .ft_transfer_call(
+                self.escrow_contract.clone(),
+                amount,
+                Some(format!("Escrow order {}", order_id)),
+                serde_json::to_string(&msg).unwrap(),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_ESCROW)
+                    .on_order_escrowed(order_id, amount),
+            )
+    }
+
+    // Link the escrow order, or fail the solver order when escrow kept nothing.
+    // Returns the amount the token contract should refund to the user.
+    #[private]
+    pub fn on_order_escrowed(&mut self, order_id: String, amount: U128) -> U128 {
+        // ft_transfer_call resolves to the amount the receiver kept
+        let used = env::promise_result_checked(0, 64)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<U128>(&bytes).ok())
+            .map_or(0, |used| used.0);
+        
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        order.escrow_pending = false;
+        // Escrow now holds what it kept and the token contract refunds the rest to the user
+        self.internal_release_obligation(&order.from_token, amount.0);
+        if used == amount.0 {
+            order.escrow_order_id = Some(order_id.clone());
+            self.orders.insert(&order_id, &order);
+            return U128(0);
+        }
+        
+        // Only a still-pending order is rolled back; its slot may already have been released otherwise
+        if order.status == OrderStatus::Pending {
+            order.status = OrderStatus::Failed;
+            self.release_pool_slot(&order);
+        }
+        self.orders.insert(&order_id, &order);
+        U128(amount.0 - used)
+    }
+
+    // Register the ed25519 key used to sign gasless order intents
+    pub fn register_intent_key(&mut self, public_key: PublicKey) {
+        self.assert_not_read_only();
+        assert!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Intent key must be an ed25519 key"
+        );
         let user = env::predecessor_account_id();
+        self.intent_keys.insert(&user, &public_key);
+    }
+
+    // Digest a wallet must sign for `create_order_with_signature` to accept `intent`
+    pub fn order_intent_hash(&self, intent: OrderIntent) -> String {
+        hex::encode(intent_digest(&intent))
+    }
+
+    // Create an order from an intent signed by the user (called by a relayer)
+    pub fn create_order_with_signature(
+        &mut self,
+        intent: OrderIntent,
+        signature: Base64VecU8,
+    ) -> String {
+        self.assert_not_read_only();
+        let public_key = self.intent_keys.get(&intent.user).expect("Intent key not registered");
+        let signature: [u8; 64] = signature.0.try_into().expect("Invalid signature length");
+        let key_bytes: [u8; 32] = public_key.as_bytes()[1..].try_into().expect("Invalid intent key");
+        
+        let digest = intent_digest(&intent);
+        assert!(
+            env::ed25519_verify(&signature, &digest, &key_bytes),
+            "Invalid intent signature"
+        );
+        
+        // Reject replayed intents
+        let expected_nonce = self.intent_nonces.get(&intent.user).unwrap_or(0);
+        assert_eq!(intent.nonce, expected_nonce, "Invalid intent nonce");
+        self.intent_nonces.insert(&intent.user, &(expected_nonce + 1));
+        
+        assert!(env::block_timestamp() <= intent.deadline.0, "Intent expired");
+        
+        // The signed quote must still be open to this user and match the intent
+        let request = self.pending_orders.get(&intent.quote_id).expect("Quote request not found");
+        assert_eq!(request.user, intent.user, "Quote requested by another account");
+        let quote = self.quotes.get(&intent.quote_id).expect("Quote not found");
+        assert!(env::block_timestamp() <= quote.valid_until.0, "Quote expired");
+        assert!(
+            quote.from_token == intent.from_token
+                && quote.to_token == intent.to_token
+                && quote.from_amount == intent.from_amount,
+            "Intent does not match quote"
+        );
+        self.internal_create_order(
+            intent.user,
+            quote,
+            CreateOrderArgs {
+                quote_id: intent.quote_id,
+                from_token: intent.from_token,
+                to_token: intent.to_token,
+                from_amount: intent.from_amount,
+                to_amount: intent.to_amount,
+                min_to_amount: intent.min_to_amount,
+                deadline: intent.deadline,
+                solver: intent.solver,
+            },
+        )
+    }
+
+    // Record a new order on behalf of `user`
+    fn internal_create_order(&mut self, user: AccountId, quote: QuoteResponse, args: CreateOrderArgs) -> String {
+        let CreateOrderArgs {
+            from_token,
+            to_token,
+            from_amount,
+            to_amount,
+            min_to_amount,
+            deadline,
+            solver,
+            ..
+        } = args;
+        assert!(from_amount.0 > 0, "From amount must be positive");
+        assert!(to_amount.0 > 0, "To amount must be positive");
+        assert_ne!(from_token, to_token, "Cannot swap a token for itself");
+        assert_eq!(quote.solver, solver, "Quote was provided by another solver");
+        
+        if let Some(assigned) = self.solvers.get(&solver) {
+            assert!(
+                env::block_timestamp() >= assigned.slashed_until.0,
+                "Solver is in slashing cooldown"
+            );
+            assert!(assigned.is_active, "Solver is not active");
+        }
+        
+        self.pending_orders.remove(&quote.quote_id);
+        
+        // Reserve one of the pool's pending order slots
+        let mut pool = self.pools.get(&quote.pool_id).expect("Pool not found");
+        assert!(
+            pool.max_pending_orders == 0 || pool.pending_order_count < pool.max_pending_orders,
+            "Pool has too many pending orders"
+        );
+        pool.pending_order_count += 1;
+        self.pools.insert(&quote.pool_id, &pool);
+        
         let order_id = format!("order_{}_{}", user, env::block_timestamp());
         
         let order = FusionOrder {
@@ -375,12 +943,21 @@ impl FusionSolver {
             from_amount,
             to_amount,
             min_to_amount,
+            filled_amount: U128(0),
             deadline,
+            auto_extend_on_near_expiry: false,
+            deadline_extended: false,
+            expiry_notified: false,
             solver: Some(solver),
+            pool_id: Some(quote.pool_id),
+            fee: quote.fee,
             status: OrderStatus::Pending,
             created_at: U64(env::block_timestamp()),
             filled_at: None,
             tx_hash: None,
+            settlement_proof: None,
+            escrow_order_id: None,
+            escrow_pending: false,
         };
         
         self.orders.insert(&order_id, &order);
@@ -392,22 +969,40 @@ impl FusionSolver {
         
         self.total_orders += 1;
         
+        SolverEvent::OrderCreated {
+            order_id: order_id.clone(),
+            user,
+            solver: order.solver.clone().unwrap(),
+            from_token: order.from_token,
+            to_token: order.to_token,
+            from_amount: order.from_amount,
+            to_amount: order.to_amount,
+            min_to_amount: order.min_to_amount,
+            deadline: order.deadline,
+        }
+        .emit();
+        
         order_id
     }
 
     // Execute order (called by solver)
-    pub fn execute_order(&mut self, order_id: String, tx_hash: String) -> bool {
+    pub fn execute_order(&mut self, order_id: String, proof: SettlementProof) -> bool {
+        self.assert_not_read_only();
         let solver_id = env::predecessor_account_id();
         
         let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_ne!(order.status, OrderStatus::Expired, "Order status is Expired");
         assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
         assert_eq!(order.solver, Some(solver_id.clone()), "Order not assigned to solver");
         assert!(env::block_timestamp() <= order.deadline.0, "Order expired");
         
         // Update order status
         order.status = OrderStatus::Filled;
+        order.filled_amount = order.from_amount;
+        self.release_pool_slot(&order);
         order.filled_at = Some(U64(env::block_timestamp()));
-        order.tx_hash = Some(tx_hash);
+        order.tx_hash = Some(proof.tx_hash.clone());
+        order.settlement_proof = Some(proof);
         
         self.orders.insert(&order_id, &order);
         
@@ -416,6 +1011,7 @@ impl FusionSolver {
         solver.total_solves += 1;
         solver.total_volume = U128(solver.total_volume.0 + order.from_amount.0);
         solver.last_active = U64(env::block_timestamp());
+        solver.consecutive_failures = 0;
         
         // Calculate success rate (simplified)
         if solver.total_solves > 0 {
@@ -426,83 +1022,663 @@ impl FusionSolver {
         
         // Update global statistics
         self.total_volume = U128(self.total_volume.0 + order.from_amount.0);
+        self.internal_collect_order_fee(&order);
         
-        true
-    }
-
-    // Verify chain signature
-    pub fn verify_signature(
-        &mut self,
-        signature: String,
-        public_key: String,
-        message: String,
-        solver: AccountId,
-    ) -> bool {
-        // In a real implementation, this would verify the chain signature
-        // using NEAR's Chain Signatures infrastructure
-        
-        let sig_id = format!("sig_{}_{}", solver, env::block_timestamp());
-        let chain_sig = ChainSignature {
-            signature,
-            public_key,
-            message,
-            timestamp: U64(env::block_timestamp()),
-            solver,
-        };
-        
-        self.signatures.insert(&sig_id, &chain_sig);
+        Self::emit_order_executed(&order);
         
-        // For now, return true (mock verification)
         true
     }
 
-    // View methods
-    pub fn get_solver(&self, solver_id: AccountId) -> String {
-        serde_json::to_string(&self.solvers.get(&solver_id)).unwrap_or_default()
+    pub fn get_settlement_proof(&self, order_id: String) -> Option<SettlementProof> {
+        self.orders.get(&order_id).and_then(|order| order.settlement_proof)
     }
 
-    pub fn get_pool(&self, pool_id: String) -> String {
-        serde_json::to_string(&self.pools.get(&pool_id)).unwrap_or_default()
+    // Free the pool slot held by an order that is no longer pending
+    fn release_pool_slot(&mut self, order: &FusionOrder) {
+        if let Some(pool_id) = &order.pool_id {
+            if let Some(mut pool) = self.pools.get(pool_id) {
+                pool.pending_order_count = pool.pending_order_count.saturating_sub(1);
+                self.pools.insert(pool_id, &pool);
+            }
+        }
     }
 
-    pub fn get_order(&self, order_id: String) -> String {
-        serde_json::to_string(&self.orders.get(&order_id)).unwrap_or_default()
+    // Count a filled order's fee and credit the pool's provider share to its providers, pro-rata to stake.
+    // The share is paid in the pool fee token, so pools without one or without stake credit nothing.
+    fn internal_collect_order_fee(&mut self, order: &FusionOrder) {
+        self.total_fees = U128(self.total_fees.0 + order.fee.0);
+        if let Some(solver_id) = &order.solver {
+            if let Some(mut solver) = self.solvers.get(solver_id) {
+                solver.total_fees = U128(solver.total_fees.0 + order.fee.0);
+                self.solvers.insert(solver_id, &solver);
+            }
+        }
+        
+        let Some(pool_id) = &order.pool_id else { return };
+        let Some(mut pool) = self.pools.get(pool_id) else { return };
+        if pool.fee_token.is_none() || pool.total_liquidity.0 == 0 {
+            return;
+        }
+        let provider_fees = mul_div(order.fee.0, pool.provider_fee_share_bps as u128, 10000);
+        pool.fee_per_stake = U128(
+            pool.fee_per_stake.0 + mul_div(provider_fees, FEE_PER_STAKE_PRECISION, pool.total_liquidity.0),
+        );
+        self.pools.insert(pool_id, &pool);
+        self.internal_add_obligation(&pool.fee_token.unwrap(), provider_fees);
     }
 
-    pub fn get_user_orders(&self, user: AccountId) -> Vec<String> {
-        self.user_orders.get(&user).unwrap_or_default()
+    // Spread fee tokens paid into a pool over its providers, pro-rata to stake.
+    // Returns the unused amount: everything when the pool has no stake to credit.
+    fn internal_pay_provider_fees(&mut self, pool_id: String, token: AccountId, amount: U128) -> U128 {
+        self.assert_not_read_only();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.fee_token, Some(token), "Token is not the pool fee token");
+        if pool.total_liquidity.0 == 0 {
+            return amount;
+        }
+        
+        // Rounding dust stays with the contract
+        let increase = mul_div(amount.0, FEE_PER_STAKE_PRECISION, pool.total_liquidity.0);
+        pool.fee_per_stake = U128(pool.fee_per_stake.0 + increase);
+        self.pools.insert(&pool_id, &pool);
+        self.internal_add_obligation(&pool.fee_token.unwrap(), amount.0);
+        U128(0)
     }
 
-    pub fn get_solver_pools(&self, solver_id: AccountId) -> Vec<String> {
+    // Claim fee rewards earned as a liquidity provider of a pool, paid in the pool fee token
+    pub fn claim_solver_pool_rewards(&mut self, pool_id: String) -> Promise {
+        self.assert_not_read_only();
+        let provider = env::predecessor_account_id();
+        let pool = self.pools.get(&pool_id).expect("Pool not found");
+        let fee_token = pool.fee_token.clone().expect("Pool has no fee token");
+        let key = stake_key(&pool_id, &provider);
+        let mut stake = self.provider_stakes.get(&key).expect("Not a provider of this pool");
+        settle_provider_fees(&pool, &mut stake);
+        let amount = stake.unclaimed_rewards;
+        assert!(amount.0 > 0, "No rewards to claim");
+        
+        stake.unclaimed_rewards = U128(0);
+        self.provider_stakes.insert(&key, &stake);
+        self.internal_release_obligation(&fee_token, amount.0);
+        ext_ft::ext(fee_token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(provider.clone(), amount, Some(format!("Provider fees from {}", pool_id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .on_provider_fees_claimed(pool_id, provider, amount),
+            )
+    }
+
+    // Give the provider back the claimed fees if the payout failed
+    #[private]
+    pub fn on_provider_fees_claimed(&mut self, pool_id: String, provider: AccountId, amount: U128) -> bool {
+        let success = env::promise_result_checked(0, 0).is_ok();
+        if !success {
+            let key = stake_key(&pool_id, &provider);
+            if let Some(mut stake) = self.provider_stakes.get(&key) {
+                stake.unclaimed_rewards = U128(stake.unclaimed_rewards.0 + amount.0);
+                self.provider_stakes.insert(&key, &stake);
+            }
+            if let Some(fee_token) = self.pools.get(&pool_id).and_then(|pool| pool.fee_token) {
+                self.internal_add_obligation(&fee_token, amount.0);
+            }
+        }
+        success
+    }
+
+    // Provider fees not yet claimed and user tokens on their way to escrow, held in `token`
+    fn internal_token_obligations(&self, token: &AccountId) -> u128 {
+        self.token_obligations.get(token).unwrap_or(0)
+    }
+
+    fn internal_add_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.internal_token_obligations(token);
+            self.token_obligations.insert(token, &(obligations + amount));
+        }
+    }
+
+    fn internal_release_obligation(&mut self, token: &AccountId, amount: u128) {
+        if amount > 0 {
+            let obligations = self.internal_token_obligations(token);
+            self.token_obligations.insert(token, &obligations.saturating_sub(amount));
+        }
+    }
+
+    fn emit_order_executed(order: &FusionOrder) {
+        SolverEvent::OrderExecuted {
+            order_id: order.id.clone(),
+            user: order.user.clone(),
+            solver: order.solver.clone().unwrap(),
+            from_token: order.from_token.clone(),
+            to_token: order.to_token.clone(),
+            from_amount: order.from_amount,
+            to_amount: order.to_amount,
+            tx_hash: order.tx_hash.clone().unwrap_or_default(),
+        }
+        .emit();
+    }
+
+    // Opt an order in or out of a one-time deadline extension
+    pub fn set_order_auto_extend(&mut self, order_id: String, enabled: bool) {
+        self.assert_not_read_only();
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(env::predecessor_account_id(), order.user, "Only order owner can set auto extend");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        
+        order.auto_extend_on_near_expiry = enabled;
+        self.orders.insert(&order_id, &order);
+    }
+
+    // Push an order's deadline once when it is about to expire (called by solver)
+    pub fn extend_if_near_deadline(&mut self, order_id: String) -> U64 {
+        self.assert_not_read_only();
+        let solver_id = env::predecessor_account_id();
+        
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        assert_eq!(order.solver, Some(solver_id), "Order not assigned to solver");
+        assert!(order.auto_extend_on_near_expiry, "Order does not allow deadline extension");
+        assert!(!order.deadline_extended, "Order deadline already extended");
+        
+        let now = env::block_timestamp();
+        assert!(now <= order.deadline.0, "Order expired");
+        assert!(
+            order.deadline.0 - now <= self.deadline_extension_window.0,
+            "Order not near deadline"
+        );
+        
+        order.deadline = U64(order.deadline.0 + self.max_deadline_extension.0);
+        order.deadline_extended = true;
+        order.expiry_notified = false;
+        self.orders.insert(&order_id, &order);
+        
+        order.deadline
+    }
+
+    // Emit OrderNearExpiry for pending orders inside the warning window among `limit` orders from
+    // `from_index`; anyone may poke. Each order is reported once per deadline. Returns the number of events emitted.
+    pub fn check_expiring_orders(&mut self, from_index: u64, limit: u64) -> u64 {
+        self.assert_not_read_only();
+        let now = env::block_timestamp();
+        let expiring: Vec<FusionOrder> = self.orders
+            .values_as_vector()
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter(|order| {
+                order.status == OrderStatus::Pending
+                    && !order.expiry_notified
+                    && now <= order.deadline.0
+                    && order.deadline.0 - now <= self.expiry_warning_window.0
+            })
+            .collect();
+        
+        let count = expiring.len() as u64;
+        for mut order in expiring {
+            order.expiry_notified = true;
+            self.orders.insert(&order.id, &order);
+            SolverEvent::OrderNearExpiry {
+                order_id: order.id,
+                user: order.user,
+                deadline: order.deadline,
+            }
+            .emit();
+        }
+        count
+    }
+
+    // Mark a pending order past its deadline as expired and free its pool slot; anyone may call
+    pub fn expire_order(&mut self, order_id: String) {
+        self.assert_not_read_only();
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        assert!(!order.escrow_pending, "Order escrow funding in progress");
+        assert!(env::block_timestamp() > order.deadline.0, "Order deadline has not passed");
+        
+        order.status = OrderStatus::Expired;
+        self.orders.insert(&order_id, &order);
+        self.release_pool_slot(&order);
+    }
+
+    // Cancel a pending order before a solver fills it and free its pool slot (called by the order's user).
+    // Tokens locked through `accept_and_escrow` stay in fusion-escrow; the user recovers them there with
+    // `refund_order` once the escrow timelock expires.
+    pub fn cancel_order(&mut self, order_id: String) {
+        self.assert_not_read_only();
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(env::predecessor_account_id(), order.user, "Only order owner can cancel order");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        assert!(!order.escrow_pending, "Order escrow funding in progress");
+        
+        order.status = OrderStatus::Cancelled;
+        self.orders.insert(&order_id, &order);
+        self.release_pool_slot(&order);
+        
+        SolverEvent::OrderCancelled {
+            order_id,
+            user: order.user,
+            from_token: order.from_token,
+            to_token: order.to_token,
+        }
+        .emit();
+    }
+
+    // Drop up to `limit` quote requests past their deadline, with any quotes they received.
+    // Anyone may call; returns the number of requests removed.
+    pub fn sweep_expired_quote_requests(&mut self, limit: u64) -> u64 {
+        self.assert_not_read_only();
+        let now = env::block_timestamp();
+        let expired: Vec<String> = self.pending_orders
+            .iter()
+            .filter(|(_, request)| now > request.deadline.0)
+            .map(|(quote_id, _)| quote_id)
+            .take(limit as usize)
+            .collect();
+        
+        for quote_id in &expired {
+            self.pending_orders.remove(quote_id);
+            self.quotes.remove(quote_id);
+        }
+        expired.len() as u64
+    }
+
+    // Partially fill an order (called by solver)
+    pub fn partial_fill_order(&mut self, order_id: String, fill_amount: U128, tx_hash: String) -> bool {
+        self.assert_not_read_only();
+        let solver_id = env::predecessor_account_id();
+        
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        assert_eq!(order.solver, Some(solver_id.clone()), "Order not assigned to solver");
+        assert!(env::block_timestamp() <= order.deadline.0, "Order expired");
+        
+        let remaining = order.from_amount.0 - order.filled_amount.0;
+        assert!(fill_amount.0 > 0, "Fill amount must be positive");
+        assert!(fill_amount.0 <= remaining, "Fill amount exceeds remaining amount");
+        
+        // Only the fill completing the order may be smaller than the minimum ratio
+        if fill_amount.0 < remaining {
+            assert!(
+                U256::from(fill_amount.0) * U256::from(10000) >= U256::from(remaining) * U256::from(self.min_fill_bps),
+                "Fill below minimum of {} bps of remaining amount",
+                self.min_fill_bps
+            );
+        }
+        
+        order.filled_amount = U128(order.filled_amount.0 + fill_amount.0);
+        order.tx_hash = Some(tx_hash);
+        if order.filled_amount == order.from_amount {
+            order.status = OrderStatus::Filled;
+            order.filled_at = Some(U64(env::block_timestamp()));
+            self.release_pool_slot(&order);
+        }
+        
+        self.orders.insert(&order_id, &order);
+        
+        // Update solver statistics
+        let mut solver = self.solvers.get(&solver_id).expect("Solver not found");
+        solver.total_volume = U128(solver.total_volume.0 + fill_amount.0);
+        solver.last_active = U64(env::block_timestamp());
+        if order.status == OrderStatus::Filled {
+            solver.total_solves += 1;
+            solver.consecutive_failures = 0;
+        }
+        self.solvers.insert(&solver_id, &solver);
+        
+        // Update global statistics
+        self.total_volume = U128(self.total_volume.0 + fill_amount.0);
+        
+        if order.status == OrderStatus::Filled {
+            self.internal_collect_order_fee(&order);
+            Self::emit_order_executed(&order);
+        }
+        
+        true
+    }
+
+    // Mark an order as failed and deactivate its solver after too many failures in a row
+    pub fn fail_order(&mut self, order_id: String) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can fail orders");
+        
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.status, OrderStatus::Pending, "Order not pending");
+        assert!(!order.escrow_pending, "Order escrow funding in progress");
+        order.status = OrderStatus::Failed;
+        self.orders.insert(&order_id, &order);
+        self.release_pool_slot(&order);
+        
+        let solver_id = order.solver.expect("Order has no solver");
+        if let Some(mut solver) = self.solvers.get(&solver_id) {
+            solver.consecutive_failures += 1;
+            if self.max_consecutive_failures > 0 && solver.consecutive_failures > self.max_consecutive_failures {
+                solver.is_active = false;
+                self.active_solvers.remove(&solver_id);
+            }
+            self.solvers.insert(&solver_id, &solver);
+        }
+    }
+
+    // Verify chain signature
+    pub fn verify_signature(
+        &mut self,
+        signature: String,
+        public_key: String,
+        message: String,
+        solver: AccountId,
+    ) -> bool {
+        self.assert_not_read_only();
+        // In a real implementation, this would verify the chain signature
+        // using NEAR's Chain Signatures infrastructure
+        
+        let sig_id = format!("sig_{}_{}", solver, env::block_timestamp());
+        let chain_sig = ChainSignature {
+            signature,
+            public_key,
+            message,
+            timestamp: U64(env::block_timestamp()),
+            solver,
+        };
+        
+        self.signatures.insert(&sig_id, &chain_sig);
+        
+        // For now, return true (mock verification)
+        true
+    }
+
+    // View methods
+    // The string views return JSON-encoded strings and are kept for existing clients;
+    // the `_typed` variants below are the recommended API
+    pub fn get_solver(&self, solver_id: AccountId) -> String {
+        serde_json::to_string(&self.solvers.get(&solver_id)).unwrap_or_default()
+    }
+
+    pub fn get_pool(&self, pool_id: String) -> String {
+        serde_json::to_string(&self.pools.get(&pool_id)).unwrap_or_default()
+    }
+
+    pub fn get_order(&self, order_id: String) -> String {
+        serde_json::to_string(&self.orders.get(&order_id)).unwrap_or_default()
+    }
+
+    pub fn get_solver_typed(&self, solver_id: AccountId) -> Option<Solver> {
+        self.solvers.get(&solver_id)
+    }
+
+    pub fn get_pool_typed(&self, pool_id: String) -> Option<SolverPool> {
+        self.pools.get(&pool_id)
+    }
+
+    pub fn get_order_typed(&self, order_id: String) -> Option<FusionOrder> {
+        self.orders.get(&order_id)
+    }
+
+    pub fn get_user_orders(&self, user: AccountId) -> Vec<String> {
+        self.user_orders.get(&user).unwrap_or_default()
+    }
+
+    // Provider position with fees paid since its last settlement included in `unclaimed_rewards`
+    pub fn get_provider_stake(&self, pool_id: String, provider: AccountId) -> Option<ProviderStake> {
+        let pool = self.pools.get(&pool_id)?;
+        let mut stake = self.provider_stakes.get(&stake_key(&pool_id, &provider))?;
+        settle_provider_fees(&pool, &mut stake);
+        Some(stake)
+    }
+
+    pub fn get_solver_pools(&self, solver_id: AccountId) -> Vec<String> {
         self.solver_pools.get(&solver_id).unwrap_or_default()
     }
 
+    pub fn get_quote_request(&self, quote_id: String) -> Option<QuoteRequest> {
+        self.pending_orders.get(&quote_id)
+    }
+
+    // Quotes `solver_id` provided, oldest first, as they were when provided
+    pub fn get_solver_quotes(
+        &self,
+        solver_id: AccountId,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<QuoteResponse> {
+        self.solver_quotes
+            .get(&solver_id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
     pub fn get_statistics(&self) -> (u64, U128, U128) {
         (self.total_orders, self.total_volume, self.total_fees)
     }
 
+    // Read-only mode and out-of-range fee, fill and quote settings
+    pub fn self_test(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.read_only {
+            problems.push("contract is in read-only mode".to_string());
+        }
+        if self.max_solver_fee > MAX_FEE_BPS {
+            problems.push(format!("max_solver_fee {} exceeds {} bps", self.max_solver_fee, MAX_FEE_BPS));
+        }
+        if self.min_fill_bps > 10000 {
+            problems.push(format!("min_fill_bps {} exceeds 10000 bps", self.min_fill_bps));
+        }
+        if self.min_quote_improvement_bps > 10000 {
+            problems.push(format!(
+                "min_quote_improvement_bps {} exceeds 10000 bps",
+                self.min_quote_improvement_bps
+            ));
+        }
+        if self.max_quotes_per_window > 0 && self.quote_window.0 == 0 {
+            problems.push("quote rate limit has an empty quote_window".to_string());
+        }
+
+        problems
+    }
+
+    // Page through pending orders, active pools and solvers by storage index
+    pub fn export_state(&self, from_index: u64, limit: u64) -> StateSnapshot {
+        let orders = self.orders.values_as_vector();
+        let pools = self.pools.values_as_vector();
+        let solvers = self.solvers.values_as_vector();
+        
+        let end = from_index.saturating_add(limit);
+        let has_more = [orders.len(), pools.len(), solvers.len()].iter().any(|len| *len > end);
+        
+        StateSnapshot {
+            orders: orders
+                .iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .filter(|order| order.status == OrderStatus::Pending)
+                .collect(),
+            pools: pools
+                .iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .filter(|pool| pool.is_active)
+                .collect(),
+            solvers: solvers.iter().skip(from_index as usize).take(limit as usize).collect(),
+            next_index: if has_more { Some(end) } else { None },
+        }
+    }
+
     pub fn get_active_solvers(&self) -> Vec<AccountId> {
         self.active_solvers.values_as_vector().to_vec()
     }
 
+    // Active solvers sorted by account id, so pages stay stable as other solvers come and go
+    pub fn get_active_solvers_paged(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        let mut solvers = self.active_solvers.keys_as_vector().to_vec();
+        solvers.sort();
+        solvers
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
     // Admin methods
+    // Rescue fungible tokens sent to the contract, leaving provider fees and escrow transfers covered
+    pub fn recover_tokens(&mut self, token: AccountId, amount: U128, to: AccountId) -> Promise {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can recover tokens");
+        
+        ext_ft::ext(token.clone())
+            .with_static_gas(GAS_FOR_FT_BALANCE)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RECOVER_CALLBACK)
+                    .on_recover_tokens_balance(token, amount, to),
+            )
+    }
+
+    #[private]
+    pub fn on_recover_tokens_balance(
+        &mut self,
+        token: AccountId,
+        amount: U128,
+        to: AccountId,
+        #[callback_unwrap] balance: U128,
+    ) -> Promise {
+        let obligations = self.internal_token_obligations(&token);
+        assert!(
+            balance.0.saturating_sub(obligations) >= amount.0,
+            "Recovery would dip into tracked obligations"
+        );
+        
+        ext_ft::ext(token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(to, amount, Some("Recover tokens".to_string()))
+    }
+
+    // Update a pool's fee rate; quotes already provided keep the rate they were quoted at
+    pub fn set_pool_fee_rate(&mut self, pool_id: String, new_rate: u32) {
+        self.assert_not_read_only();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, env::predecessor_account_id(), "Only pool solver can set fee rate");
+        assert!(new_rate <= self.max_solver_fee, "Fee rate too high");
+        
+        pool.fee_rate = new_rate;
+        self.pools.insert(&pool_id, &pool);
+    }
+
+    pub fn set_pool_max_pending_orders(&mut self, pool_id: String, max_pending_orders: u32) {
+        self.assert_not_read_only();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, env::predecessor_account_id(), "Only pool solver can set max pending orders");
+        
+        pool.max_pending_orders = max_pending_orders;
+        self.pools.insert(&pool_id, &pool);
+    }
+
+    // Set the share of each executed order's fee credited to the pool's providers
+    pub fn set_pool_provider_fee_share(&mut self, pool_id: String, share_bps: u32) {
+        self.assert_not_read_only();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, env::predecessor_account_id(), "Only pool solver can set provider fee share");
+        assert!(share_bps <= 10000, "Provider fee share cannot exceed 100%");
+        
+        pool.provider_fee_share_bps = share_bps;
+        self.pools.insert(&pool_id, &pool);
+    }
+
+    // Choose the token provider fees are paid in; fixed once any fees have been paid
+    pub fn set_pool_fee_token(&mut self, pool_id: String, fee_token: AccountId) {
+        self.assert_not_read_only();
+        let mut pool = self.pools.get(&pool_id).expect("Pool not found");
+        assert_eq!(pool.solver, env::predecessor_account_id(), "Only pool solver can set fee token");
+        assert_eq!(pool.fee_per_stake.0, 0, "Fee token cannot change once fees are paid");
+        pool.fee_token = Some(fee_token);
+        self.pools.insert(&pool_id, &pool);
+    }
+
     pub fn set_min_solver_stake(&mut self, min_stake: U128) {
+        self.assert_not_read_only();
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set min stake");
         self.min_solver_stake = min_stake;
     }
 
     pub fn set_max_solver_fee(&mut self, max_fee: u32) {
+        self.assert_not_read_only();
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set max fee");
-        assert!(max_fee <= 1000, "Max fee cannot exceed 10%");
+        assert!(max_fee <= MAX_FEE_BPS, "Max fee cannot exceed 10%");
         self.max_solver_fee = max_fee;
     }
 
     pub fn set_quote_timeout(&mut self, timeout: U64) {
+        self.assert_not_read_only();
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set timeout");
         self.quote_timeout = timeout;
     }
 
+    pub fn set_deadline_extension(&mut self, window: U64, max_extension: U64) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set deadline extension");
+        self.deadline_extension_window = window;
+        self.max_deadline_extension = max_extension;
+    }
+
+    pub fn set_expiry_warning_window(&mut self, window: U64) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set expiry warning window");
+        self.expiry_warning_window = window;
+    }
+
+    pub fn set_quote_rate_limit(&mut self, max_quotes_per_window: u32, quote_window: U64) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set quote rate limit");
+        assert!(quote_window.0 > 0, "Quote window must be positive");
+        self.max_quotes_per_window = max_quotes_per_window;
+        self.quote_window = quote_window;
+    }
+
+    pub fn set_slash_cooldown(&mut self, cooldown: U64) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set slash cooldown");
+        self.slash_cooldown = cooldown;
+    }
+
+    pub fn set_max_consecutive_failures(&mut self, max_failures: u32) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set max consecutive failures");
+        self.max_consecutive_failures = max_failures;
+    }
+
+    pub fn set_min_fill_bps(&mut self, min_fill_bps: u32) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set min fill ratio");
+        assert!(min_fill_bps <= 10000, "Min fill ratio cannot exceed 100%");
+        self.min_fill_bps = min_fill_bps;
+    }
+
+    pub fn set_min_quote_improvement(&mut self, min_quote_improvement_bps: u32) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set min quote improvement");
+        assert!(min_quote_improvement_bps <= 10000, "Min quote improvement cannot exceed 100%");
+        self.min_quote_improvement_bps = min_quote_improvement_bps;
+    }
+
+    // Bar a solver from quoting and taking orders for the slash cooldown
+    pub fn slash_solver(&mut self, solver_id: AccountId) {
+        self.assert_not_read_only();
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can slash solver");
+        
+        let mut solver = self.solvers.get(&solver_id).expect("Solver not found");
+        solver.slashed_until = U64(env::block_timestamp() + self.slash_cooldown.0);
+        self.solvers.insert(&solver_id, &solver);
+    }
+
     pub fn deactivate_solver(&mut self, solver_id: AccountId) {
+        self.assert_not_read_only();
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can deactivate solver");
         
         if let Some(mut solver) = self.solvers.get(&solver_id) {
@@ -513,6 +1689,7 @@ impl FusionSolver {
     }
 
     pub fn activate_solver(&mut self, solver_id: AccountId) {
+        self.assert_not_read_only();
         assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can activate solver");
         
         if let Some(mut solver) = self.solvers.get(&solver_id) {
@@ -521,13 +1698,96 @@ impl FusionSolver {
             self.active_solvers.insert(&solver_id, &solver_id);
         }
     }
+
+    // Serve views only, e.g. during upgrades or incidents
+    pub fn set_read_only(&mut self, read_only: bool) {
+        assert_eq!(env::predecessor_account_id(), self.owner, "Only owner can set read-only mode");
+        self.read_only = read_only;
+    }
+
+    fn assert_not_read_only(&self) {
+        assert!(!self.read_only, "Contract in read-only mode");
+    }
+
+    // Count a quote against the solver's sliding window budget
+    fn consume_quote_budget(&mut self, solver_id: &AccountId) {
+        if self.max_quotes_per_window == 0 {
+            return;
+        }
+        
+        let now = env::block_timestamp();
+        let mut timestamps = self.quote_timestamps.get(solver_id).unwrap_or_default();
+        timestamps.retain(|quoted_at| now < quoted_at + self.quote_window.0);
+        if timestamps.len() >= self.max_quotes_per_window as usize {
+            let retry_after = timestamps[0] + self.quote_window.0 - now;
+            env::panic_str(&format!(
+                "Quote rate limit reached, retry after {} seconds",
+                retry_after.div_ceil(NANOS_PER_SECOND)
+            ));
+        }
+        timestamps.push(now);
+        self.quote_timestamps.insert(solver_id, &timestamps);
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for FusionSolver {
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // Users accept a quote and escrow its tokens with msg {"accept_and_escrow":{...}}, and solvers
+        // pay provider fees with {"pay_provider_fees":{"pool_id":"..."}}; anything else is refunded
+        match serde_json::from_str(&msg) {
+            Ok(SolverTransferMessage::AcceptAndEscrow(args)) => PromiseOrValue::Promise(
+                self.internal_accept_and_escrow(sender_id, env::predecessor_account_id(), amount, args),
+            ),
+            Ok(SolverTransferMessage::PayProviderFees { pool_id }) => {
+                PromiseOrValue::Value(self.internal_pay_provider_fees(pool_id, env::predecessor_account_id(), amount))
+            }
+            Err(_) => PromiseOrValue::Value(amount),
+        }
+    }
+}
+
+// SHA-256 of the borsh-serialized (contract id, intent); this is what users sign.
+// Bound to this contract so an intent cannot be replayed against another deployment
+fn intent_digest(intent: &OrderIntent) -> Vec<u8> {
+    env::sha256(borsh::to_vec(&(env::current_account_id(), intent)).expect("Failed to serialize intent"))
+}
+
+fn stake_key(pool_id: &str, provider: &AccountId) -> String {
+    format!("{}_{}", provider, pool_id)
+}
+
+// Move fees accrued since the last settlement into `unclaimed_rewards`
+fn settle_provider_fees(pool: &SolverPool, stake: &mut ProviderStake) {
+    let accrued = mul_div(stake.stake.0, pool.fee_per_stake.0, FEE_PER_STAKE_PRECISION);
+    stake.unclaimed_rewards = U128(stake.unclaimed_rewards.0 + accrued.saturating_sub(stake.fee_debt.0));
+    stake.fee_debt = U128(accrued);
+}
+
+// Compute a * b / c without intermediate overflow
+fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+}
+
+// Unpredictable per-request nonce, so solvers cannot prepare responses before a request exists
+fn quote_nonce(quote_id: &str) -> String {
+    let mut seed = env::random_seed();
+    seed.extend_from_slice(quote_id.as_bytes());
+    hex::encode(env::sha256(&seed))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, AccountId};
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId, PromiseResult};
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -538,28 +1798,70 @@ mod tests {
         builder
     }
 
-    #[test]
-    fn test_register_solver() {
-        let context = get_context(accounts(1));
-        testing_env!(context.build());
-        
-        let mut contract = FusionSolver::new(accounts(0), accounts(2));
-        
-        let success = contract.register_solver(
+    // Register `solver` with a pool of its own named `pool_id`
+    fn register_pool_solver(contract: &mut FusionSolver, solver: AccountId, pool_id: &str) {
+        testing_env!(get_context(solver).block_timestamp(env::block_timestamp()).build());
+        contract.register_solver(
             "Test Solver".to_string(),
             "A test solver".to_string(),
             "1.0.0".to_string(),
             "abc123".to_string(),
         );
-        
-        assert!(success);
-        
-        let solver = contract.get_solver(accounts(1));
-        assert!(solver.is_some());
-        assert_eq!(solver.unwrap().name, "Test Solver");
+        contract.create_pool(pool_id.to_string(), 100, U128(1000), U128(1000000));
     }
 
-    #[test]
+    // Quote from `solver`'s pool for a 1000 accounts(3) -> accounts(4) request by `user`,
+    // leaving `user` as the caller so the order can be created next
+    fn quote_via(
+        contract: &mut FusionSolver,
+        user: AccountId,
+        solver: AccountId,
+        pool_id: &str,
+        deadline: U64,
+    ) -> String {
+        let timestamp = env::block_timestamp();
+        testing_env!(get_context(user.clone()).block_timestamp(timestamp).build());
+        let quote_id = contract.request_quote(accounts(3), accounts(4), U128(1000), deadline);
+        
+        testing_env!(get_context(solver).block_timestamp(timestamp).build());
+        let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id: quote_id.clone(),
+            nonce,
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: pool_id.to_string(),
+            fee: U128(10),
+            valid_until: deadline,
+        });
+        
+        testing_env!(get_context(user).block_timestamp(timestamp).build());
+        quote_id
+    }
+
+    #[test]
+    fn test_register_solver() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        
+        let success = contract.register_solver(
+            "Test Solver".to_string(),
+            "A test solver".to_string(),
+            "1.0.0".to_string(),
+            "abc123".to_string(),
+        );
+        
+        assert!(success);
+        
+        let solver = contract.get_solver_typed(accounts(1));
+        assert!(solver.is_some());
+        assert_eq!(solver.unwrap().name, "Test Solver");
+    }
+
+    #[test]
     fn test_create_pool() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
@@ -583,7 +1885,7 @@ mod tests {
         
         assert!(success);
         
-        let pool = contract.get_pool("pool1".to_string());
+        let pool = contract.get_pool_typed("pool1".to_string());
         assert!(pool.is_some());
         assert_eq!(pool.unwrap().solver, accounts(1));
     }
@@ -593,7 +1895,7 @@ mod tests {
         let context = get_context(accounts(1));
         testing_env!(context.build());
         
-        let contract = FusionSolver::new(accounts(0), accounts(2));
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
         
         let quote_id = contract.request_quote(
             accounts(3),
@@ -604,4 +1906,1596 @@ mod tests {
         
         assert!(!quote_id.is_empty());
     }
-} 
\ No newline at end of file
+
+    fn signed_intent(signing_key: &SigningKey, quote_id: &str, from_amount: U128) -> (OrderIntent, Base64VecU8) {
+        let intent = OrderIntent {
+            user: accounts(1),
+            quote_id: quote_id.to_string(),
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount,
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(2),
+            nonce: 0,
+        };
+        let signature = signing_key.sign(&intent_digest(&intent)).to_bytes().to_vec();
+        (intent, Base64VecU8(signature))
+    }
+
+    // Register accounts(1)'s intent key and return a quote from solver accounts(2) for it to sign
+    fn register_key(contract: &mut FusionSolver, signing_key: &SigningKey) -> String {
+        register_pool_solver(contract, accounts(2), "pool2");
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        let quote_id = quote_via(contract, accounts(1), accounts(2), "pool2", deadline);
+        
+        let public_key = PublicKey::from_parts(
+            CurveType::ED25519,
+            signing_key.verifying_key().to_bytes().to_vec(),
+        )
+        .unwrap();
+        contract.register_intent_key(public_key);
+        quote_id
+    }
+
+    #[test]
+    fn test_create_order_with_signature() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        // Relayer submits the user's signed intent
+        testing_env!(get_context(accounts(5)).build());
+        let (intent, signature) = signed_intent(&signing_key, &quote_id, U128(1000));
+        let order_id = contract.create_order_with_signature(intent, signature);
+        
+        assert_eq!(contract.get_user_orders(accounts(1)), vec![order_id.clone()]);
+        assert!(contract.get_user_orders(accounts(5)).is_empty());
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.user, accounts(1));
+        assert_eq!(order.from_amount, U128(1000));
+    }
+
+    #[test]
+    fn test_order_intent_hash_is_signed_digest() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        // A wallet signs the digest returned by the view
+        testing_env!(get_context(accounts(5)).build());
+        let (intent, _) = signed_intent(&signing_key, &quote_id, U128(1000));
+        let hash = contract.order_intent_hash(intent.clone());
+        let signed = borsh::to_vec(&(accounts(0), &intent)).unwrap();
+        assert_eq!(hash, hex::encode(env::sha256(&signed)));
+        
+        let signature = signing_key.sign(&hex::decode(hash).unwrap()).to_bytes().to_vec();
+        let order_id = contract.create_order_with_signature(intent, Base64VecU8(signature));
+        assert_eq!(contract.orders.get(&order_id).unwrap().user, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid intent signature")]
+    fn test_create_order_with_signature_intent_mismatch() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        testing_env!(get_context(accounts(5)).build());
+        let (mut intent, signature) = signed_intent(&signing_key, &quote_id, U128(1000));
+        intent.from_amount = U128(1_000_000);
+        contract.create_order_with_signature(intent, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid intent signature")]
+    fn test_intent_signed_for_other_contract_rejected() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        // Signed while another deployment was the current account
+        testing_env!(get_context(accounts(5)).current_account_id(accounts(3)).build());
+        let (intent, signature) = signed_intent(&signing_key, &quote_id, U128(1000));
+        
+        testing_env!(get_context(accounts(5)).build());
+        contract.create_order_with_signature(intent, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent does not match quote")]
+    fn test_intent_for_other_amount_than_quote_rejected() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        testing_env!(get_context(accounts(5)).build());
+        let (intent, signature) = signed_intent(&signing_key, &quote_id, U128(5000));
+        contract.create_order_with_signature(intent, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote request not found")]
+    fn test_intent_quote_cannot_be_reused() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let quote_id = register_key(&mut contract, &signing_key);
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.create_order(CreateOrderArgs {
+            quote_id: quote_id.clone(),
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(2),
+        });
+        
+        testing_env!(get_context(accounts(5)).build());
+        let (intent, signature) = signed_intent(&signing_key, &quote_id, U128(1000));
+        contract.create_order_with_signature(intent, signature);
+    }
+
+    #[test]
+    fn test_get_solver_quotes() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.register_solver(
+            "Test Solver".to_string(),
+            "A test solver".to_string(),
+            "1.0.0".to_string(),
+            "abc123".to_string(),
+        );
+        contract.create_pool("pool1".to_string(), 100, U128(1000), U128(1000000));
+        
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        testing_env!(get_context(accounts(3)).build());
+        let first = contract.request_quote(accounts(3), accounts(4), U128(1000), deadline);
+        testing_env!(get_context(accounts(4)).build());
+        let second = contract.request_quote(accounts(4), accounts(3), U128(2000), deadline);
+        
+        testing_env!(get_context(accounts(1)).build());
+        for quote_id in [&first, &second] {
+            let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+            contract.provide_quote(ProvideQuoteArgs {
+                quote_id: quote_id.clone(),
+                nonce,
+                to_amount: U128(990),
+                price: "0.99".to_string(),
+                gas_estimate: U128(30_000_000_000_000),
+                pool_id: "pool1".to_string(),
+                fee: U128(10),
+                valid_until: deadline,
+            });
+        }
+        
+        let history = contract.get_solver_quotes(accounts(1), 0, 10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].quote_id, first);
+        assert_eq!(history[0].from_amount, U128(1000));
+        assert_eq!(history[1].quote_id, second);
+        assert_eq!(history[1].from_amount, U128(2000));
+        
+        let page = contract.get_solver_quotes(accounts(1), 1, 10);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].quote_id, second);
+    }
+
+    #[test]
+    fn test_solver_quote_history_keeps_replaced_and_swept_quotes() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        register_pool_solver(&mut contract, accounts(1), "pool1");
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        
+        // accounts(1) improves its own quote, then accounts(2) beats it
+        let deadline = U64(1_000);
+        let quote_id = quote_via(&mut contract, accounts(3), accounts(1), "pool1", deadline);
+        for (solver, pool_id, to_amount) in [(accounts(1), "pool1", 995), (accounts(2), "pool2", 999)] {
+            testing_env!(get_context(solver).build());
+            let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+            contract.provide_quote(ProvideQuoteArgs {
+                quote_id: quote_id.clone(),
+                nonce,
+                to_amount: U128(to_amount),
+                price: "0.99".to_string(),
+                gas_estimate: U128(30_000_000_000_000),
+                pool_id: pool_id.to_string(),
+                fee: U128(10),
+                valid_until: deadline,
+            });
+        }
+        testing_env!(get_context(accounts(5)).block_timestamp(1_001).build());
+        assert_eq!(contract.sweep_expired_quote_requests(10), 1);
+        
+        let history = contract.get_solver_quotes(accounts(1), 0, 10);
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|quote| quote.quote_id == quote_id && quote.solver == accounts(1)));
+        assert_eq!(history[0].to_amount, U128(990));
+        assert_eq!(history[1].to_amount, U128(995));
+        assert_eq!(contract.get_solver_quotes(accounts(1), 1, 1)[0].to_amount, U128(995));
+        assert_eq!(contract.get_solver_quotes(accounts(2), 0, 10)[0].to_amount, U128(999));
+    }
+
+    #[test]
+    fn test_get_active_solvers_paged_is_stable() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        for solver in [accounts(3), accounts(1), accounts(4), accounts(2)] {
+            testing_env!(get_context(solver).build());
+            contract.register_solver(
+                "Test Solver".to_string(),
+                "A test solver".to_string(),
+                "1.0.0".to_string(),
+                "abc123".to_string(),
+            );
+        }
+        assert_eq!(
+            contract.get_active_solvers_paged(0, 10),
+            vec![accounts(1), accounts(2), accounts(3), accounts(4)]
+        );
+        let page = contract.get_active_solvers_paged(1, 2);
+        assert_eq!(page, vec![accounts(2), accounts(3)]);
+        
+        // Removing and re-adding an unrelated solver reshuffles storage but not the pages
+        testing_env!(get_context(accounts(0)).build());
+        contract.deactivate_solver(accounts(1));
+        contract.activate_solver(accounts(1));
+        assert_eq!(contract.get_active_solvers_paged(1, 2), page);
+        assert_eq!(contract.get_active_solvers_paged(3, 10), vec![accounts(4)]);
+    }
+
+    #[test]
+    fn test_request_quote_for_remaining_after_partial_fill() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        let quote_id = quote_via(&mut contract, accounts(1), accounts(2), "pool2", deadline);
+        let order_id = contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline,
+            solver: accounts(2),
+        });
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.partial_fill_order(order_id.clone(), U128(400), "tx1".to_string());
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let quote_id = contract.request_quote_for_remaining(order_id.clone());
+        let request = contract.pending_orders.get(&quote_id).unwrap();
+        assert_eq!(request.amount, U128(600));
+        assert_eq!(request.from_token, accounts(3));
+        assert_eq!(request.to_token, accounts(4));
+        assert_eq!(request.user, accounts(1));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Pending);
+    }
+
+    // Order of 1000 against pool1 where partial fills must cover half the remaining amount
+    fn setup_min_fill_order() -> (FusionSolver, String) {
+        let mut contract = setup_quoting_solver();
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_min_fill_bps(5000);
+        let order_id = order_against_pool1(&mut contract, accounts(3));
+        testing_env!(get_context(accounts(1)).build());
+        (contract, order_id)
+    }
+
+    #[test]
+    #[should_panic(expected = "Fill below minimum of 5000 bps of remaining amount")]
+    fn test_partial_fill_below_min_ratio_rejected() {
+        let (mut contract, order_id) = setup_min_fill_order();
+        contract.partial_fill_order(order_id, U128(499), "tx1".to_string());
+    }
+
+    #[test]
+    fn test_min_fill_ratio_on_large_order() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_min_fill_bps(5000);
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        let quote_id = quote_via(&mut contract, accounts(1), accounts(2), "pool2", deadline);
+        let amount = u128::MAX / 2;
+        let order_id = contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(amount),
+            to_amount: U128(amount),
+            min_to_amount: U128(amount),
+            deadline,
+            solver: accounts(2),
+        });
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.partial_fill_order(order_id.clone(), U128(amount / 2 + 1), "tx1".to_string());
+        assert_eq!(contract.orders.get(&order_id).unwrap().filled_amount, U128(amount / 2 + 1));
+    }
+
+    #[test]
+    fn test_completing_fill_below_min_ratio_accepted() {
+        let (mut contract, order_id) = setup_min_fill_order();
+        contract.partial_fill_order(order_id.clone(), U128(900), "tx1".to_string());
+        
+        // 100 is well under half the original amount, but it completes the order
+        contract.partial_fill_order(order_id.clone(), U128(100), "tx2".to_string());
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Filled);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order not pending")]
+    fn test_request_quote_for_remaining_rejects_filled_order() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        let quote_id = quote_via(&mut contract, accounts(1), accounts(2), "pool2", deadline);
+        let order_id = contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline,
+            solver: accounts(2),
+        });
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.partial_fill_order(order_id.clone(), U128(1000), "tx1".to_string());
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.request_quote_for_remaining(order_id);
+    }
+
+    fn create_auto_extend_order(contract: &mut FusionSolver) -> String {
+        register_pool_solver(contract, accounts(2), "pool2");
+        let quote_id = quote_via(contract, accounts(1), accounts(2), "pool2", U64(100_000_000_000));
+        let order_id = contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(100_000_000_000),
+            solver: accounts(2),
+        });
+        contract.set_order_auto_extend(order_id.clone(), true);
+        order_id
+    }
+
+    #[test]
+    fn test_extend_if_near_deadline() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let order_id = create_auto_extend_order(&mut contract);
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(90_000_000_000).build());
+        let deadline = contract.extend_if_near_deadline(order_id.clone());
+        
+        assert_eq!(deadline, U64(400_000_000_000));
+        assert_eq!(contract.orders.get(&order_id).unwrap().deadline, deadline);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order deadline already extended")]
+    fn test_extend_if_near_deadline_only_once() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        let order_id = create_auto_extend_order(&mut contract);
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(90_000_000_000).build());
+        contract.extend_if_near_deadline(order_id.clone());
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(390_000_000_000).build());
+        contract.extend_if_near_deadline(order_id);
+    }
+
+    fn settlement_proof(tx_hash: &str) -> SettlementProof {
+        SettlementProof {
+            chain_id: "ethereum".to_string(),
+            block_height: U64(19_000_000),
+            tx_hash: tx_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_order_lifecycle_events() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let quote_id = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(500));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"fusion-solver","version":"1.0.0","event":"quote_requested","data":{{"quote_id":"{}","user":"bob","from_token":"danny","to_token":"eugene","amount":"1000","deadline":"500","nonce":"{}"}}}}"#,
+                quote_id,
+                contract.get_quote_request(quote_id.clone()).unwrap().nonce
+            )]
+        );
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(1).build());
+        let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id: quote_id.clone(),
+            nonce,
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool2".to_string(),
+            fee: U128(10),
+            valid_until: U64(500),
+        });
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let order_id = contract.create_order(CreateOrderArgs {
+            quote_id: quote_id.clone(),
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(500),
+            solver: accounts(2),
+        });
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"fusion-solver","version":"1.0.0","event":"order_created","data":{{"order_id":"{}","user":"bob","solver":"charlie","from_token":"danny","to_token":"eugene","from_amount":"1000","to_amount":"990","min_to_amount":"980","deadline":"500"}}}}"#,
+                order_id
+            )]
+        );
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(2).build());
+        contract.execute_order(order_id.clone(), settlement_proof("tx1"));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"fusion-solver","version":"1.0.0","event":"order_executed","data":{{"order_id":"{}","user":"bob","solver":"charlie","from_token":"danny","to_token":"eugene","from_amount":"1000","to_amount":"990","tx_hash":"tx1"}}}}"#,
+                order_id
+            )]
+        );
+        assert_eq!(contract.get_settlement_proof(order_id), Some(settlement_proof("tx1")));
+    }
+
+    // Registered solver accounts(1) with pool1 and a quote request from accounts(3)
+    fn setup_slashed_solver() -> FusionSolver {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.register_solver(
+            "Test Solver".to_string(),
+            "A test solver".to_string(),
+            "1.0.0".to_string(),
+            "abc123".to_string(),
+        );
+        contract.create_pool("pool1".to_string(), 100, U128(1000), U128(1000000));
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_slash_cooldown(U64(1_000));
+        contract.slash_solver(accounts(1));
+        contract
+    }
+
+    fn quote_at(contract: &mut FusionSolver, timestamp: u64) {
+        testing_env!(get_context(accounts(3)).block_timestamp(timestamp).build());
+        let quote_id = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(10_000));
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(timestamp).build());
+        let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id,
+            nonce,
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool1".to_string(),
+            fee: U128(10),
+            valid_until: U64(10_000),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Solver is in slashing cooldown")]
+    fn test_slashed_solver_cannot_quote_during_cooldown() {
+        let mut contract = setup_slashed_solver();
+        quote_at(&mut contract, 999);
+    }
+
+    #[test]
+    fn test_slashed_solver_can_quote_after_cooldown() {
+        let mut contract = setup_slashed_solver();
+        quote_at(&mut contract, 1_000);
+        
+        assert_eq!(contract.get_solver_quotes(accounts(1), 0, 10).len(), 1);
+    }
+
+    // Registered solver accounts(2) that is deactivated after more than two failures in a row
+    fn setup_failing_solver() -> FusionSolver {
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_max_consecutive_failures(2);
+        contract
+    }
+
+    fn assign_order(contract: &mut FusionSolver) -> String {
+        let quote_id = quote_via(contract, accounts(1), accounts(2), "pool2", U64(500));
+        contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(500),
+            solver: accounts(2),
+        })
+    }
+
+    fn fail_orders(contract: &mut FusionSolver, count: usize) {
+        for _ in 0..count {
+            let order_id = assign_order(contract);
+            testing_env!(get_context(accounts(0)).build());
+            contract.fail_order(order_id);
+        }
+    }
+
+    #[test]
+    fn test_consecutive_failures_deactivate_solver() {
+        let mut contract = setup_failing_solver();
+        
+        fail_orders(&mut contract, 2);
+        assert!(contract.solvers.get(&accounts(2)).unwrap().is_active);
+        
+        fail_orders(&mut contract, 1);
+        let solver = contract.solvers.get(&accounts(2)).unwrap();
+        assert_eq!(solver.consecutive_failures, 3);
+        assert!(!solver.is_active);
+    }
+
+    #[test]
+    fn test_success_resets_consecutive_failures() {
+        let mut contract = setup_failing_solver();
+        fail_orders(&mut contract, 2);
+        
+        let order_id = assign_order(&mut contract);
+        testing_env!(get_context(accounts(2)).build());
+        contract.execute_order(order_id, settlement_proof("tx1"));
+        assert_eq!(contract.solvers.get(&accounts(2)).unwrap().consecutive_failures, 0);
+        
+        fail_orders(&mut contract, 2);
+        let solver = contract.solvers.get(&accounts(2)).unwrap();
+        assert_eq!(solver.consecutive_failures, 2);
+        assert!(solver.is_active);
+    }
+
+    #[test]
+    #[should_panic(expected = "Solver is not active")]
+    fn test_deactivated_solver_cannot_take_orders() {
+        let mut contract = setup_failing_solver();
+        fail_orders(&mut contract, 3);
+        assign_order(&mut contract);
+    }
+
+    #[test]
+    fn test_create_pool_rejects_duplicate_id() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.register_solver(
+            "Test Solver".to_string(),
+            "A test solver".to_string(),
+            "1.0.0".to_string(),
+            "abc123".to_string(),
+        );
+        contract.create_pool("pool1".to_string(), 100, U128(1000), U128(1000000));
+        
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.create_pool("pool1".to_string(), 200, U128(1), U128(10));
+        }));
+        assert!(result.is_err());
+        
+        let pool = contract.pools.get(&"pool1".to_string()).unwrap();
+        assert_eq!(pool.fee_rate, 100);
+        assert_eq!(pool.min_order_size, U128(1000));
+        assert_eq!(contract.get_solver_pools(accounts(1)), vec!["pool1".to_string()]);
+    }
+
+    #[test]
+    fn test_recover_tokens_checks_balance() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        
+        drop(contract.recover_tokens(accounts(3), U128(500), accounts(5)));
+        
+        let receipt = &get_created_receipts()[0];
+        assert_eq!(receipt.receiver_id, accounts(3));
+        let MockAction::FunctionCallWeight { method_name, .. } = &receipt.actions[0] else {
+            panic!("Expected ft_balance_of call");
+        };
+        assert_eq!(method_name, b"ft_balance_of");
+    }
+
+    // Run the recovery callback as if ft_balance_of returned `balance`, returning the recovered transfer
+    fn recover_with_balance(
+        contract: &mut FusionSolver,
+        token: AccountId,
+        amount: u128,
+        balance: u128,
+    ) -> (AccountId, String) {
+        testing_env!(get_context(accounts(0)).build());
+        drop(contract.on_recover_tokens_balance(token.clone(), U128(amount), accounts(5), U128(balance)));
+        
+        let receipt = get_created_receipts().pop().unwrap();
+        assert_eq!(receipt.receiver_id, token);
+        let MockAction::FunctionCallWeight { method_name, args, .. } = &receipt.actions[0] else {
+            panic!("Expected ft_transfer call");
+        };
+        assert_eq!(method_name, b"ft_transfer");
+        let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+        (args["receiver_id"].as_str().unwrap().parse().unwrap(), args["amount"].as_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_recover_stray_tokens_keeps_provider_fees() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        
+        assert_eq!(recover_with_balance(&mut contract, accounts(2), 500, 500), (accounts(5), "500".to_string()));
+        // 5 of the fee token's balance of 15 arrived outside provider fees
+        assert_eq!(recover_with_balance(&mut contract, fee_token(), 5, 15), (accounts(5), "5".to_string()));
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery would dip into tracked obligations")]
+    fn test_recover_tokens_rejects_unclaimed_provider_fees() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        
+        recover_with_balance(&mut contract, fee_token(), 6, 15);
+    }
+
+    #[test]
+    fn test_claimed_provider_fees_release_obligation() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_solver_pool_rewards("pool1".to_string()));
+        assert_eq!(contract.internal_token_obligations(&fee_token()), 2);
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        contract.on_provider_fees_claimed("pool1".to_string(), accounts(4), U128(8));
+        assert_eq!(contract.internal_token_obligations(&fee_token()), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery would dip into tracked obligations")]
+    fn test_recover_tokens_rejects_tokens_in_flight_to_escrow() {
+        let mut contract = setup_quoting_solver();
+        accept_and_escrow(&mut contract);
+        
+        recover_with_balance(&mut contract, accounts(3), 1, 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can recover tokens")]
+    fn test_recover_tokens_owner_only() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        
+        drop(contract.recover_tokens(accounts(3), U128(500), accounts(1)));
+    }
+
+    #[test]
+    fn test_export_state_pages() {
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        for (i, solver) in [accounts(1), accounts(2)].into_iter().enumerate() {
+            testing_env!(get_context(solver).build());
+            contract.register_solver(
+                "Test Solver".to_string(),
+                "A test solver".to_string(),
+                "1.0.0".to_string(),
+                "abc123".to_string(),
+            );
+            contract.create_pool(format!("pool{}", i), 100, U128(1000), U128(1000000));
+        }
+        
+        for timestamp in 0..3 {
+            testing_env!(get_context(accounts(3)).block_timestamp(timestamp).build());
+            let quote_id = quote_via(&mut contract, accounts(3), accounts(1), "pool0", U64(1_000));
+            contract.create_order(CreateOrderArgs {
+                quote_id,
+                from_token: accounts(3),
+                to_token: accounts(4),
+                from_amount: U128(1000),
+                to_amount: U128(990),
+                min_to_amount: U128(980),
+                deadline: U64(1_000),
+                solver: accounts(1),
+            });
+        }
+        
+        let first = contract.export_state(0, 2);
+        assert_eq!(first.orders.len(), 2);
+        assert_eq!(first.pools.len(), 2);
+        assert_eq!(first.solvers.len(), 2);
+        assert_eq!(first.next_index, Some(2));
+        
+        let second = contract.export_state(first.next_index.unwrap(), 2);
+        assert_eq!(second.orders.len(), 1);
+        assert!(second.pools.is_empty());
+        assert!(second.solvers.is_empty());
+        assert_eq!(second.next_index, None);
+    }
+
+    fn setup_quoting_solver() -> FusionSolver {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.register_solver(
+            "Test Solver".to_string(),
+            "A test solver".to_string(),
+            "1.0.0".to_string(),
+            "abc123".to_string(),
+        );
+        contract.create_pool("pool1".to_string(), 100, U128(1000), U128(1000000));
+        contract
+    }
+
+    fn quote_from_pool1(contract: &mut FusionSolver, user: AccountId) -> String {
+        quote_amount_from_pool1(contract, user, 1000)
+    }
+
+    fn quote_amount_from_pool1(contract: &mut FusionSolver, user: AccountId, amount: u128) -> String {
+        let deadline = U64(env::block_timestamp() + 300_000_000_000);
+        testing_env!(get_context(user.clone()).build());
+        let quote_id = contract.request_quote(user, accounts(5), U128(amount), deadline);
+        
+        testing_env!(get_context(accounts(1)).build());
+        let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id: quote_id.clone(),
+            nonce,
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool1".to_string(),
+            fee: U128(10),
+            valid_until: deadline,
+        });
+        quote_id
+    }
+
+    // Pool1 accepts two pending orders at a time
+    fn setup_limited_pool() -> FusionSolver {
+        let mut contract = setup_quoting_solver();
+        contract.set_pool_max_pending_orders("pool1".to_string(), 2);
+        contract
+    }
+
+    fn order_against_pool1(contract: &mut FusionSolver, user: AccountId) -> String {
+        let quote_id = quote_from_pool1(contract, user.clone());
+        testing_env!(get_context(user).build());
+        contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(1),
+        })
+    }
+
+    fn stake_in_pool1(contract: &mut FusionSolver, provider: AccountId, near: u128) {
+        testing_env!(get_context(provider)
+            .attached_deposit(NearToken::from_near(near))
+            .build());
+        drop(contract.add_liquidity("pool1".to_string()));
+    }
+
+    fn fee_token() -> AccountId {
+        "fees.near".parse().unwrap()
+    }
+
+    // Providers stake 100 and 400 NEAR in pool1, whose fees are paid in `fee_token()`
+    fn setup_shared_fee_pool() -> FusionSolver {
+        let mut contract = setup_quoting_solver();
+        contract.set_pool_fee_token("pool1".to_string(), fee_token());
+        stake_in_pool1(&mut contract, accounts(3), 100);
+        stake_in_pool1(&mut contract, accounts(4), 400);
+        contract
+    }
+
+    fn pay_fees(contract: &mut FusionSolver, token: AccountId, amount: u128) -> U128 {
+        testing_env!(get_context(token).build());
+        let msg = r#"{"pay_provider_fees":{"pool_id":"pool1"}}"#.to_string();
+        match contract.ft_on_transfer(accounts(1), U128(amount), msg) {
+            PromiseOrValue::Value(unused) => unused,
+            PromiseOrValue::Promise(_) => panic!("Expected a value"),
+        }
+    }
+
+    fn unclaimed_rewards(contract: &FusionSolver, provider: AccountId) -> u128 {
+        contract.get_provider_stake("pool1".to_string(), provider).unwrap().unclaimed_rewards.0
+    }
+
+    #[test]
+    fn test_paid_fees_reward_providers_by_stake() {
+        let mut contract = setup_shared_fee_pool();
+        for _ in 0..2 {
+            assert_eq!(pay_fees(&mut contract, fee_token(), 10), U128(0));
+        }
+        
+        assert_eq!(unclaimed_rewards(&contract, accounts(3)), 4);
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 16);
+    }
+
+    #[test]
+    fn test_executed_orders_reward_providers_by_stake() {
+        let mut contract = setup_shared_fee_pool();
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_pool_provider_fee_share("pool1".to_string(), 5000);
+        
+        // Half of each order's fee of 10 goes to providers staking 100 and 400 NEAR
+        for user in [accounts(2), accounts(5)] {
+            let order_id = order_against_pool1(&mut contract, user);
+            testing_env!(get_context(accounts(1)).build());
+            contract.execute_order(order_id, settlement_proof("tx1"));
+        }
+        
+        assert_eq!(unclaimed_rewards(&contract, accounts(3)), 2);
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 8);
+        assert_eq!(contract.get_statistics().2, U128(20));
+        assert_eq!(contract.get_solver_typed(accounts(1)).unwrap().total_fees, U128(20));
+    }
+
+    #[test]
+    fn test_executed_orders_without_provider_share_credit_nothing() {
+        let mut contract = setup_shared_fee_pool();
+        let order_id = order_against_pool1(&mut contract, accounts(5));
+        testing_env!(get_context(accounts(1)).build());
+        contract.execute_order(order_id, settlement_proof("tx1"));
+        
+        assert_eq!(unclaimed_rewards(&contract, accounts(3)), 0);
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 0);
+        assert_eq!(contract.get_statistics().2, U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Provider fee share cannot exceed 100%")]
+    fn test_provider_fee_share_capped() {
+        let mut contract = setup_shared_fee_pool();
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_pool_provider_fee_share("pool1".to_string(), 10001);
+    }
+
+    #[test]
+    fn test_pending_orders_accrue_no_provider_rewards() {
+        let mut contract = setup_shared_fee_pool();
+        order_against_pool1(&mut contract, accounts(5));
+        
+        assert_eq!(unclaimed_rewards(&contract, accounts(3)), 0);
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 0);
+    }
+
+    #[test]
+    fn test_late_provider_earns_only_later_fees() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        stake_in_pool1(&mut contract, accounts(5), 500);
+        pay_fees(&mut contract, fee_token(), 10);
+        
+        assert_eq!(unclaimed_rewards(&contract, accounts(3)), 3);
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 12);
+        assert_eq!(unclaimed_rewards(&contract, accounts(5)), 5);
+    }
+
+    #[test]
+    fn test_fees_without_stake_are_refunded() {
+        let mut contract = setup_quoting_solver();
+        contract.set_pool_fee_token("pool1".to_string(), fee_token());
+        assert_eq!(pay_fees(&mut contract, fee_token(), 10), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not the pool fee token")]
+    fn test_fees_in_other_token_rejected() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, accounts(2), 10);
+    }
+
+    #[test]
+    fn test_claim_solver_pool_rewards() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_solver_pool_rewards("pool1".to_string()));
+        let receipt = &get_created_receipts()[0];
+        assert_eq!(receipt.receiver_id, fee_token());
+        let MockAction::FunctionCallWeight { method_name, args, .. } = &receipt.actions[0] else {
+            panic!("Expected ft_transfer call");
+        };
+        assert_eq!(method_name, b"ft_transfer");
+        let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+        assert_eq!(args["receiver_id"], accounts(4).as_str());
+        assert_eq!(args["amount"], "8");
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 0);
+    }
+
+    #[test]
+    fn test_failed_provider_fee_claim_restores_rewards() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        testing_env!(get_context(accounts(4)).build());
+        drop(contract.claim_solver_pool_rewards("pool1".to_string()));
+        
+        resolve_transfer_with(PromiseResult::Failed);
+        assert!(!contract.on_provider_fees_claimed("pool1".to_string(), accounts(4), U128(8)));
+        assert_eq!(unclaimed_rewards(&contract, accounts(4)), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee token cannot change once fees are paid")]
+    fn test_fee_token_fixed_once_fees_paid() {
+        let mut contract = setup_shared_fee_pool();
+        pay_fees(&mut contract, fee_token(), 10);
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_pool_fee_token("pool1".to_string(), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool has too many pending orders")]
+    fn test_full_pool_rejects_pending_order() {
+        let mut contract = setup_limited_pool();
+        order_against_pool1(&mut contract, accounts(3));
+        order_against_pool1(&mut contract, accounts(4));
+        order_against_pool1(&mut contract, accounts(5));
+    }
+
+    #[test]
+    fn test_filled_order_frees_pool_slot() {
+        let mut contract = setup_limited_pool();
+        let first = order_against_pool1(&mut contract, accounts(3));
+        order_against_pool1(&mut contract, accounts(4));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.execute_order(first, settlement_proof("tx1"));
+        
+        let order_id = order_against_pool1(&mut contract, accounts(5));
+        assert_eq!(contract.orders.get(&order_id).unwrap().pool_id, Some("pool1".to_string()));
+        assert_eq!(contract.pools.get(&"pool1".to_string()).unwrap().pending_order_count, 2);
+    }
+
+    #[test]
+    fn test_provide_quote_within_pool_order_size() {
+        let mut contract = setup_quoting_solver();
+        let quote_id = quote_amount_from_pool1(&mut contract, accounts(3), 1_000_000);
+        assert!(contract.quotes.get(&quote_id).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount below pool minimum order size")]
+    fn test_provide_quote_below_pool_min_order_size() {
+        let mut contract = setup_quoting_solver();
+        quote_amount_from_pool1(&mut contract, accounts(3), 999);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount above pool maximum order size")]
+    fn test_provide_quote_above_pool_max_order_size() {
+        let mut contract = setup_quoting_solver();
+        quote_amount_from_pool1(&mut contract, accounts(3), 1_000_001);
+    }
+
+    #[test]
+    fn test_set_pool_fee_rate_applies_to_new_quotes() {
+        let mut contract = setup_quoting_solver();
+        let outstanding = quote_from_pool1(&mut contract, accounts(3));
+        
+        contract.set_pool_fee_rate("pool1".to_string(), 250);
+        let fresh = quote_from_pool1(&mut contract, accounts(4));
+        
+        assert_eq!(contract.pools.get(&"pool1".to_string()).unwrap().fee_rate, 250);
+        assert_eq!(contract.quotes.get(&outstanding).unwrap().fee_rate, 100);
+        assert_eq!(contract.quotes.get(&fresh).unwrap().fee_rate, 250);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee rate too high")]
+    fn test_set_pool_fee_rate_capped_by_max_solver_fee() {
+        let mut contract = setup_quoting_solver();
+        contract.set_pool_fee_rate("pool1".to_string(), 501);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only pool solver can set fee rate")]
+    fn test_set_pool_fee_rate_solver_only() {
+        let mut contract = setup_quoting_solver();
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.set_pool_fee_rate("pool1".to_string(), 200);
+    }
+
+    fn setup_read_only_solver() -> FusionSolver {
+        let mut contract = setup_quoting_solver();
+        quote_from_pool1(&mut contract, accounts(3));
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_read_only(true);
+        contract
+    }
+
+    #[test]
+    fn test_read_only_mode_serves_views() {
+        let contract = setup_read_only_solver();
+        
+        assert!(contract.pools.get(&"pool1".to_string()).is_some());
+        assert_eq!(contract.get_solver_pools(accounts(1)), vec!["pool1".to_string()]);
+        assert_eq!(contract.get_active_solvers(), vec![accounts(1)]);
+        assert_eq!(contract.get_solver_quotes(accounts(1), 0, 10).len(), 1);
+        assert_eq!(contract.export_state(0, 10).pools.len(), 1);
+    }
+
+    #[test]
+    fn test_read_only_mode_rejects_mutations() {
+        let mut contract = setup_read_only_solver();
+        
+        testing_env!(get_context(accounts(1)).build());
+        let mutations: [fn(&mut FusionSolver); 4] = [
+            |c| { c.create_pool("pool2".to_string(), 100, U128(1000), U128(1000000)); },
+            |c| c.set_pool_fee_rate("pool1".to_string(), 200),
+            |c| { c.request_quote(accounts(1), accounts(5), U128(1000), U64(1)); },
+            |c| c.fail_order("order".to_string()),
+        ];
+        for mutate in mutations {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mutate(&mut contract)));
+            let payload = result.unwrap_err();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap();
+            assert_eq!(message, "Contract in read-only mode");
+        }
+        
+        // Leaving read-only mode restores mutations
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_read_only(false);
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_pool_fee_rate("pool1".to_string(), 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can set read-only mode")]
+    fn test_set_read_only_owner_only() {
+        let mut contract = setup_quoting_solver();
+        contract.set_read_only(true);
+    }
+
+    fn create_test_order(
+        from_token: AccountId,
+        to_token: AccountId,
+        from_amount: u128,
+        to_amount: u128,
+    ) -> String {
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        let quote_id = quote_via(
+            &mut contract,
+            accounts(1),
+            accounts(2),
+            "pool2",
+            U64(env::block_timestamp() + 300_000_000_000),
+        );
+        contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token,
+            to_token,
+            from_amount: U128(from_amount),
+            to_amount: U128(to_amount),
+            min_to_amount: U128(to_amount),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(2),
+        })
+    }
+
+    #[test]
+    fn test_create_order_valid() {
+        assert!(!create_test_order(accounts(3), accounts(4), 1000, 990).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote not found")]
+    fn test_create_order_unknown_quote_rejected() {
+        let mut contract = setup_quoting_solver();
+        testing_env!(get_context(accounts(3)).build());
+        contract.create_order(CreateOrderArgs {
+            quote_id: "made_up_quote".to_string(),
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(1),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote was provided by another solver")]
+    fn test_create_order_quote_from_other_solver_rejected() {
+        let mut contract = setup_quoting_solver();
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        let quote_id = quote_from_pool1(&mut contract, accounts(3));
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.create_order(CreateOrderArgs {
+            quote_id,
+            from_token: accounts(3),
+            to_token: accounts(4),
+            from_amount: U128(1000),
+            to_amount: U128(990),
+            min_to_amount: U128(980),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(2),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "From amount must be positive")]
+    fn test_create_order_rejects_zero_from_amount() {
+        create_test_order(accounts(3), accounts(4), 0, 990);
+    }
+
+    #[test]
+    #[should_panic(expected = "To amount must be positive")]
+    fn test_create_order_rejects_zero_to_amount() {
+        create_test_order(accounts(3), accounts(4), 1000, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot swap a token for itself")]
+    fn test_create_order_rejects_self_swap() {
+        create_test_order(accounts(3), accounts(3), 1000, 990);
+    }
+
+    #[test]
+    fn test_max_solver_fee_accepts_shared_cap() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.set_max_solver_fee(MAX_FEE_BPS);
+        assert_eq!(contract.max_solver_fee, MAX_FEE_BPS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Max fee cannot exceed 10%")]
+    fn test_max_solver_fee_above_shared_cap_rejected() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.set_max_solver_fee(MAX_FEE_BPS + 1);
+    }
+
+    #[test]
+    fn test_provide_quote_with_request_nonce() {
+        let mut contract = setup_quoting_solver();
+        let quote_id = quote_from_pool1(&mut contract, accounts(3));
+        
+        // Other solvers may still compete for the request
+        assert!(contract.get_quote_request(quote_id.clone()).is_some());
+        assert!(contract.quotes.get(&quote_id).is_some());
+    }
+
+    // Quote `to_amount` for `quote_id` from a second solver, accounts(4), through pool2
+    fn compete_for_quote(contract: &mut FusionSolver, quote_id: &str, to_amount: u128) {
+        testing_env!(get_context(accounts(4)).build());
+        if contract.solvers.get(&accounts(4)).is_none() {
+            contract.register_solver(
+                "Rival Solver".to_string(),
+                "A competing solver".to_string(),
+                "1.0.0".to_string(),
+                "def456".to_string(),
+            );
+            contract.create_pool("pool2".to_string(), 100, U128(1000), U128(1000000));
+        }
+        let nonce = contract.get_quote_request(quote_id.to_string()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id: quote_id.to_string(),
+            nonce,
+            to_amount: U128(to_amount),
+            price: "1.00".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool2".to_string(),
+            fee: U128(10),
+            valid_until: U64(env::block_timestamp() + 300_000_000_000),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote must improve on the best to_amount by at least 0 bps")]
+    fn test_equal_competing_quote_rejected() {
+        let mut contract = setup_quoting_solver();
+        let quote_id = quote_from_pool1(&mut contract, accounts(3));
+        compete_for_quote(&mut contract, &quote_id, 990);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote must improve on the best to_amount by at least 1000 bps")]
+    fn test_marginally_better_quote_rejected() {
+        let mut contract = setup_quoting_solver();
+        let quote_id = quote_from_pool1(&mut contract, accounts(3));
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_min_quote_improvement(1000);
+        
+        // 10% over 990 is 1089
+        compete_for_quote(&mut contract, &quote_id, 1088);
+    }
+
+    #[test]
+    fn test_sufficiently_better_quote_replaces_best() {
+        let mut contract = setup_quoting_solver();
+        let quote_id = quote_from_pool1(&mut contract, accounts(3));
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_min_quote_improvement(1000);
+        
+        compete_for_quote(&mut contract, &quote_id, 1089);
+        let best = contract.quotes.get(&quote_id).unwrap();
+        assert_eq!(best.solver, accounts(4));
+        assert_eq!(best.to_amount, U128(1089));
+        
+        // Creating the order closes the request to further quotes
+        testing_env!(get_context(accounts(3)).build());
+        contract.create_order(CreateOrderArgs {
+            quote_id: quote_id.clone(),
+            from_token: accounts(3),
+            to_token: accounts(5),
+            from_amount: U128(1000),
+            to_amount: U128(1089),
+            min_to_amount: U128(1000),
+            deadline: U64(env::block_timestamp() + 300_000_000_000),
+            solver: accounts(4),
+        });
+        assert!(contract.get_quote_request(quote_id).is_none());
+    }
+
+    #[test]
+    fn test_quote_nonces_differ_per_request() {
+        let mut contract = setup_quoting_solver();
+        
+        testing_env!(get_context(accounts(3)).build());
+        let first = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(10_000));
+        testing_env!(get_context(accounts(4)).build());
+        let second = contract.request_quote(accounts(4), accounts(3), U128(1000), U64(10_000));
+        
+        let first_nonce = contract.get_quote_request(first).unwrap().nonce;
+        assert_eq!(first_nonce.len(), 64);
+        assert_ne!(first_nonce, contract.get_quote_request(second).unwrap().nonce);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote nonce mismatch")]
+    fn test_provide_quote_rejects_wrong_nonce() {
+        let mut contract = setup_quoting_solver();
+        
+        testing_env!(get_context(accounts(3)).build());
+        let quote_id = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(10_000));
+        
+        testing_env!(get_context(accounts(1)).build());
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id,
+            nonce: "00".repeat(32),
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool1".to_string(),
+            fee: U128(10),
+            valid_until: U64(10_000),
+        });
+    }
+
+    // Request and provide a pool1 quote for `user` at `timestamp`
+    fn quote_for_at(contract: &mut FusionSolver, user: AccountId, timestamp: u64) -> String {
+        let deadline = U64(timestamp + 300_000_000_000);
+        testing_env!(get_context(user.clone()).block_timestamp(timestamp).build());
+        let quote_id = contract.request_quote(user, accounts(5), U128(1000), deadline);
+        
+        testing_env!(get_context(accounts(1)).block_timestamp(timestamp).build());
+        let nonce = contract.get_quote_request(quote_id.clone()).unwrap().nonce;
+        contract.provide_quote(ProvideQuoteArgs {
+            quote_id: quote_id.clone(),
+            nonce,
+            to_amount: U128(990),
+            price: "0.99".to_string(),
+            gas_estimate: U128(30_000_000_000_000),
+            pool_id: "pool1".to_string(),
+            fee: U128(10),
+            valid_until: deadline,
+        });
+        quote_id
+    }
+
+    // accounts(1) may provide two quotes per minute
+    fn setup_quote_rate_limit() -> FusionSolver {
+        let mut contract = setup_quoting_solver();
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_quote_rate_limit(2, U64(60 * NANOS_PER_SECOND));
+        quote_for_at(&mut contract, accounts(3), 0);
+        quote_for_at(&mut contract, accounts(4), 10 * NANOS_PER_SECOND);
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote rate limit reached, retry after 30 seconds")]
+    fn test_quote_beyond_rate_limit_rejected() {
+        let mut contract = setup_quote_rate_limit();
+        quote_for_at(&mut contract, accounts(5), 30 * NANOS_PER_SECOND);
+    }
+
+    #[test]
+    fn test_quote_budget_frees_up_as_window_slides() {
+        let mut contract = setup_quote_rate_limit();
+        let quote_id = quote_for_at(&mut contract, accounts(5), 60 * NANOS_PER_SECOND);
+        assert!(contract.quotes.get(&quote_id).is_some());
+    }
+
+    #[test]
+    fn test_self_test_passes_on_fresh_contract() {
+        testing_env!(get_context(accounts(0)).build());
+        let contract = FusionSolver::new(accounts(0), accounts(2));
+        assert!(contract.self_test().is_empty());
+    }
+
+    #[test]
+    fn test_self_test_reports_misconfiguration() {
+        testing_env!(get_context(accounts(0)).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(2));
+        contract.set_read_only(true);
+        contract.max_solver_fee = MAX_FEE_BPS + 1;
+        
+        let problems = contract.self_test();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], "contract is in read-only mode");
+        assert!(problems[1].starts_with("max_solver_fee"));
+    }
+
+    fn setup_expiring_orders() -> (FusionSolver, String, String) {
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        register_pool_solver(&mut contract, accounts(2), "pool2");
+        
+        let mut order_ids = Vec::new();
+        for (timestamp, deadline) in [(1, 400 * NANOS_PER_SECOND), (2, 1000 * NANOS_PER_SECOND)] {
+            testing_env!(get_context(accounts(1)).block_timestamp(timestamp).build());
+            let quote_id = quote_via(&mut contract, accounts(1), accounts(2), "pool2", U64(deadline));
+            order_ids.push(contract.create_order(CreateOrderArgs {
+                quote_id,
+                from_token: accounts(3),
+                to_token: accounts(4),
+                from_amount: U128(1000),
+                to_amount: U128(990),
+                min_to_amount: U128(980),
+                deadline: U64(deadline),
+                solver: accounts(2),
+            }));
+        }
+        let later = order_ids.pop().unwrap();
+        (contract, order_ids.pop().unwrap(), later)
+    }
+
+    #[test]
+    fn test_check_expiring_orders_reports_orders_in_window() {
+        let (mut contract, soon, _) = setup_expiring_orders();
+        
+        // 5 minute window: only the order due at 400s is inside it at 200s
+        testing_env!(get_context(accounts(5)).block_timestamp(200 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.check_expiring_orders(0, 10), 1);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"fusion-solver","version":"1.0.0","event":"order_near_expiry","data":{{"order_id":"{}","user":"bob","deadline":"400000000000"}}}}"#,
+                soon
+            )]
+        );
+        
+        // Already reported orders are not reported again
+        testing_env!(get_context(accounts(5)).block_timestamp(300 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.check_expiring_orders(0, 10), 0);
+    }
+
+    #[test]
+    fn test_check_expiring_orders_pages_by_index() {
+        let (mut contract, _, later) = setup_expiring_orders();
+        
+        // Only the second order is still inside the window at 800s
+        testing_env!(get_context(accounts(5)).block_timestamp(800 * NANOS_PER_SECOND).build());
+        assert_eq!(contract.check_expiring_orders(0, 1), 0);
+        assert_eq!(contract.check_expiring_orders(1, 1), 1);
+        assert_eq!(contract.check_expiring_orders(1, 1), 0);
+        assert!(contract.orders.get(&later).unwrap().expiry_notified);
+    }
+
+    #[test]
+    fn test_expire_order_after_deadline() {
+        let (mut contract, soon, later) = setup_expiring_orders();
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(400 * NANOS_PER_SECOND + 1).build());
+        contract.expire_order(soon.clone());
+        assert_eq!(contract.orders.get(&soon).unwrap().status, OrderStatus::Expired);
+        assert_eq!(contract.orders.get(&later).unwrap().status, OrderStatus::Pending);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order deadline has not passed")]
+    fn test_expire_order_before_deadline_rejected() {
+        let (mut contract, soon, _) = setup_expiring_orders();
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(400 * NANOS_PER_SECOND).build());
+        contract.expire_order(soon);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order status is Expired")]
+    fn test_execute_expired_order_rejected() {
+        let (mut contract, soon, _) = setup_expiring_orders();
+        testing_env!(get_context(accounts(5)).block_timestamp(400 * NANOS_PER_SECOND + 1).build());
+        contract.expire_order(soon.clone());
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(400 * NANOS_PER_SECOND + 1).build());
+        contract.execute_order(soon, settlement_proof("tx1"));
+    }
+
+    #[test]
+    fn test_sweep_expired_quote_requests() {
+        testing_env!(get_context(accounts(1)).block_timestamp(1).build());
+        let mut contract = FusionSolver::new(accounts(0), accounts(5));
+        let stale = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(100));
+        testing_env!(get_context(accounts(1)).block_timestamp(2).build());
+        let live = contract.request_quote(accounts(3), accounts(4), U128(1000), U64(1000));
+        
+        testing_env!(get_context(accounts(5)).block_timestamp(101).build());
+        assert_eq!(contract.sweep_expired_quote_requests(10), 1);
+        assert!(contract.pending_orders.get(&stale).is_none());
+        assert!(contract.pending_orders.get(&live).is_some());
+    }
+
+    // Accepts a pool1 quote requested by accounts(3), which is also the quoted from_token
+    fn accept_and_escrow(contract: &mut FusionSolver) -> String {
+        let quote_id = quote_from_pool1(contract, accounts(3));
+        let msg = SolverTransferMessage::AcceptAndEscrow(AcceptAndEscrowArgs {
+            quote_id,
+            min_to_amount: U128(980),
+            deadline: U64(1_000 * NANOS_PER_SECOND),
+            hashlock: "ab".repeat(32),
+            secret_length: 32,
+            timelock: U64(3600),
+        });
+        testing_env!(get_context(accounts(3)).build());
+        drop(contract.ft_on_transfer(accounts(3), U128(1000), serde_json::to_string(&msg).unwrap()));
+        contract.user_orders.get(&accounts(3)).unwrap().pop().unwrap()
+    }
+
+    fn resolve_transfer_with(result: PromiseResult) {
+        testing_env!(
+            get_context(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_accept_and_escrow_links_escrow_order() {
+        let mut contract = setup_quoting_solver();
+        let order_id = accept_and_escrow(&mut contract);
+        
+        let receipt = &get_created_receipts()[0];
+        assert_eq!(receipt.receiver_id, accounts(3));
+        let MockAction::FunctionCallWeight { method_name, args, .. } = &receipt.actions[0] else {
+            panic!("Expected ft_transfer_call");
+        };
+        assert_eq!(method_name, b"ft_transfer_call");
+        let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+        assert_eq!(args["receiver_id"], accounts(2).as_str());
+        let msg: serde_json::Value = serde_json::from_str(args["msg"].as_str().unwrap()).unwrap();
+        assert_eq!(msg["create_order"]["order_id"], order_id.as_str());
+        assert_eq!(msg["create_order"]["taker"], accounts(1).as_str());
+        
+        resolve_transfer_with(PromiseResult::Successful(b"\"1000\"".to_vec()));
+        assert_eq!(contract.on_order_escrowed(order_id.clone(), U128(1000)), U128(0));
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Pending);
+        assert_eq!(order.escrow_order_id, Some(order_id));
+    }
+
+    #[test]
+    fn test_accept_and_escrow_message_format() {
+        let msg = r#"{"accept_and_escrow":{"quote_id":"q","min_to_amount":"980","deadline":"1","hashlock":"ab","secret_length":32,"timelock":"3600"}}"#;
+        let Ok(SolverTransferMessage::AcceptAndEscrow(args)) = serde_json::from_str(msg) else {
+            panic!("Expected accept_and_escrow");
+        };
+        assert_eq!(args.quote_id, "q");
+        assert_eq!(args.min_to_amount, U128(980));
+        assert_eq!(args.secret_length, 32);
+    }
+
+    #[test]
+    fn test_accept_and_escrow_rolls_back_when_escrow_rejects() {
+        let mut contract = setup_quoting_solver();
+        let order_id = accept_and_escrow(&mut contract);
+        
+        // The escrow refunded everything, so the user gets the full amount back
+        resolve_transfer_with(PromiseResult::Successful(b"\"0\"".to_vec()));
+        assert_eq!(contract.on_order_escrowed(order_id.clone(), U128(1000)), U128(1000));
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Failed);
+        assert!(order.escrow_order_id.is_none());
+        assert_eq!(contract.pools.get(&"pool1".to_string()).unwrap().pending_order_count, 0);
+        assert_eq!(contract.internal_token_obligations(&accounts(3)), 0);
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let mut contract = setup_limited_pool();
+        let order_id = order_against_pool1(&mut contract, accounts(3));
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.cancel_order(order_id.clone());
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Cancelled);
+        assert_eq!(contract.pools.get(&"pool1".to_string()).unwrap().pending_order_count, 0);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"fusion-solver","version":"1.0.0","event":"order_cancelled","data":{{"order_id":"{}","user":"danny","from_token":"danny","to_token":"eugene"}}}}"#,
+                order_id
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Order not pending")]
+    fn test_cancel_filled_order_rejected() {
+        let mut contract = setup_limited_pool();
+        let order_id = order_against_pool1(&mut contract, accounts(3));
+        testing_env!(get_context(accounts(1)).build());
+        contract.execute_order(order_id.clone(), settlement_proof("tx1"));
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.cancel_order(order_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order escrow funding in progress")]
+    fn test_cancel_rejected_while_escrow_in_flight() {
+        let mut contract = setup_quoting_solver();
+        let order_id = accept_and_escrow(&mut contract);
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.cancel_order(order_id);
+    }
+
+    #[test]
+    fn test_failed_escrow_leaves_settled_order_alone() {
+        let mut contract = setup_limited_pool();
+        let order_id = accept_and_escrow(&mut contract);
+        let other = order_against_pool1(&mut contract, accounts(4));
+        
+        // The solver filled the order while the escrow transfer was in flight
+        testing_env!(get_context(accounts(1)).build());
+        contract.execute_order(order_id.clone(), settlement_proof("tx1"));
+        
+        resolve_transfer_with(PromiseResult::Successful(b"\"0\"".to_vec()));
+        assert_eq!(contract.on_order_escrowed(order_id.clone(), U128(1000)), U128(1000));
+        assert_eq!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Filled);
+        assert_eq!(contract.orders.get(&other).unwrap().status, OrderStatus::Pending);
+        assert_eq!(contract.pools.get(&"pool1".to_string()).unwrap().pending_order_count, 1);
+    }
+}
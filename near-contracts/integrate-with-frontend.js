@@ -74,13 +74,18 @@ class NEARContractIntegration {
             contractId: this.contracts.escrow,
             methodName: 'create_order',
             args: {
-                taker: params.taker,
-                from_token: params.fromToken,
-                to_token: params.toToken,
-                from_amount: params.fromAmount,
-                to_amount: params.toAmount,
-                hashlock: params.hashlock,
-                timelock: params.timelock
+                args: {
+                    taker: params.taker,
+                    from_token: params.fromToken,
+                    to_token: params.toToken,
+                    from_amount: params.fromAmount,
+                    to_amount: params.toAmount,
+                    hashlock: params.hashlock,
+                    secret_length: params.secretLength,
+                    timelock: params.timelock,
+                    refund_bounty_opt_in: params.refundBountyOptIn || false,
+                    memo: params.memo
+                }
             },
             gas: '300000000000000',
             attachedDeposit: '1'
@@ -90,6 +95,40 @@ class NEARContractIntegration {
         return result;
     }
 
+    async createEscrowOrderWithNear(params) {
+        console.log('📝 Creating escrow order funded with NEAR...');
+
+        const wnear = await this.account.viewFunction({
+            contractId: this.contracts.escrow,
+            methodName: 'get_wnear_contract',
+            args: {}
+        });
+
+        const result = await this.account.functionCall({
+            contractId: this.contracts.escrow,
+            methodName: 'create_order_with_near',
+            args: {
+                args: {
+                    taker: params.taker,
+                    from_token: wnear,
+                    to_token: params.toToken,
+                    from_amount: params.fromAmount,
+                    to_amount: params.toAmount,
+                    hashlock: params.hashlock,
+                    secret_length: params.secretLength,
+                    timelock: params.timelock,
+                    refund_bounty_opt_in: params.refundBountyOptIn || false,
+                    memo: params.memo
+                }
+            },
+            gas: '300000000000000',
+            attachedDeposit: params.fromAmount
+        });
+
+        console.log('✅ Escrow order created and funded');
+        return result;
+    }
+
     async fundEscrowOrder(orderId) {
         console.log(`💰 Funding escrow order: ${orderId}`);
         
@@ -200,14 +239,16 @@ class NEARContractIntegration {
             contractId: this.contracts.solver,
             methodName: 'create_order',
             args: {
-                quote_id: params.quoteId,
-                from_token: params.fromToken,
-                to_token: params.toToken,
-                from_amount: params.fromAmount,
-                to_amount: params.toAmount,
-                min_to_amount: params.minToAmount,
-                deadline: params.deadline,
-                solver: params.solver
+                args: {
+                    quote_id: params.quoteId,
+                    from_token: params.fromToken,
+                    to_token: params.toToken,
+                    from_amount: params.fromAmount,
+                    to_amount: params.toAmount,
+                    min_to_amount: params.minToAmount,
+                    deadline: params.deadline,
+                    solver: params.solver
+                }
             },
             gas: '300000000000000',
             attachedDeposit: '1'
@@ -225,13 +266,16 @@ class NEARContractIntegration {
             contractId: this.contracts.pool,
             methodName: 'create_pool',
             args: {
-                pool_id: params.poolId,
-                name: params.name,
-                description: params.description,
-                token: params.token,
-                fee_rate: params.feeRate,
-                min_deposit: params.minDeposit,
-                max_deposit: params.maxDeposit
+                args: {
+                    pool_id: params.poolId,
+                    name: params.name,
+                    description: params.description,
+                    token: params.token,
+                    fee_rate: params.feeRate,
+                    min_deposit: params.minDeposit,
+                    max_deposit: params.maxDeposit,
+                    max_total_liquidity: params.maxTotalLiquidity
+                }
             },
             gas: '300000000000000',
             attachedDeposit: '1'
@@ -358,6 +402,15 @@ class NEARContractIntegration {
         return { secret, hashlock };
     }
 
+    // Whether makers must confirm they hold the preimage before an order is created
+    async requiresCreatorProof() {
+        return this.account.viewFunction({
+            contractId: this.contracts.escrow,
+            methodName: 'get_require_creator_proof',
+            args: {}
+        });
+    }
+
     async getQuote(fromToken, toToken, amount) {
         console.log('💬 Getting quote...');
         
@@ -404,6 +457,7 @@ class NEARContractIntegration {
                 fromAmount: '1000000000000000000000000',
                 toAmount: '980000000000000000000000',
                 hashlock: hashlock,
+                secretLength: secret.length,
                 timelock: 3600
             });
             console.log('📝 Escrow order created');
@@ -0,0 +1 @@
+// Sandbox tests for the fusion contracts live in tests/
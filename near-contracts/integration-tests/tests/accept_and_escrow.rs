@@ -0,0 +1,114 @@
+mod common;
+
+use common::{
+    deploy_wnear, ft_balance_of, quote, setup, view_order, Env, FAR_FUTURE, HASHLOCK, SECRET,
+};
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, Contract};
+use serde_json::json;
+
+const AMOUNT: u128 = 1_000_000;
+
+// Wrap NEAR for the user and quote AMOUNT of wNEAR for `to_token`; escrow accepts `to_token` if `supported`
+async fn setup_quoted(supported: bool) -> anyhow::Result<(Env, Contract, Account, String)> {
+    let env = setup().await?;
+    let wnear = deploy_wnear(&env, &[env.escrow.id(), env.solver.id(), env.user.id()]).await?;
+    let to_token = env.worker.dev_create_account().await?;
+
+    let mut tokens = vec![wnear.id().clone()];
+    if supported {
+        tokens.push(to_token.id().clone());
+    }
+    env.escrow
+        .call("add_supported_tokens")
+        .args_json(json!({ "tokens": tokens }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.user
+        .call(wnear.id(), "near_deposit")
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let quote_id = quote(&env, wnear.id(), to_token.id(), AMOUNT).await?;
+    Ok((env, wnear, to_token, quote_id))
+}
+
+// Send AMOUNT of wNEAR to the solver with an accept_and_escrow message, returning the amount kept
+async fn accept_and_escrow(env: &Env, wnear: &Contract, quote_id: &str) -> anyhow::Result<u128> {
+    let msg = json!({
+        "accept_and_escrow": {
+            "quote_id": quote_id,
+            "min_to_amount": AMOUNT.to_string(),
+            "deadline": FAR_FUTURE.to_string(),
+            "hashlock": HASHLOCK,
+            "secret_length": SECRET.len(),
+            "timelock": "3600",
+        }
+    });
+    let used: String = env
+        .user
+        .call(wnear.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": env.solver.id(),
+            "amount": AMOUNT.to_string(),
+            "msg": msg.to_string(),
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    Ok(used.parse()?)
+}
+
+async fn user_order(env: &Env) -> anyhow::Result<String> {
+    let order_ids: Vec<String> = env
+        .solver
+        .view("get_user_orders")
+        .args_json(json!({ "user": env.user.id() }))
+        .await?
+        .json()?;
+    Ok(order_ids[0].clone())
+}
+
+#[tokio::test]
+async fn test_accept_and_escrow_funds_escrow_order() -> anyhow::Result<()> {
+    let (env, wnear, _to_token, quote_id) = setup_quoted(true).await?;
+
+    assert_eq!(accept_and_escrow(&env, &wnear, &quote_id).await?, AMOUNT);
+
+    let order_id = user_order(&env).await?;
+    let solver_order = view_order(&env.solver, &order_id).await?;
+    assert_eq!(solver_order["escrow_order_id"], order_id.as_str());
+    assert_eq!(solver_order["status"], "Pending");
+
+    let escrow_order = view_order(&env.escrow, &order_id).await?;
+    assert_eq!(escrow_order["status"], "Funded");
+    assert_eq!(escrow_order["maker"], env.user.id().as_str());
+    assert_eq!(escrow_order["taker"], env.solver_account.id().as_str());
+    assert_eq!(escrow_order["from_amount"], AMOUNT.to_string());
+    assert_eq!(ft_balance_of(&wnear, env.escrow.id()).await?, AMOUNT);
+    assert_eq!(ft_balance_of(&wnear, env.solver.id()).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accept_and_escrow_rolls_back_when_escrow_rejects() -> anyhow::Result<()> {
+    // Escrow does not support the quoted to_token, so it refuses to open the order
+    let (env, wnear, _to_token, quote_id) = setup_quoted(false).await?;
+    let balance_before = ft_balance_of(&wnear, env.user.id()).await?;
+
+    assert_eq!(accept_and_escrow(&env, &wnear, &quote_id).await?, 0);
+
+    let order_id = user_order(&env).await?;
+    let solver_order = view_order(&env.solver, &order_id).await?;
+    assert_eq!(solver_order["status"], "Failed");
+    assert!(solver_order["escrow_order_id"].is_null());
+    assert!(view_order(&env.escrow, &order_id).await?.is_null());
+    assert_eq!(ft_balance_of(&wnear, env.user.id()).await?, balance_before);
+    assert_eq!(ft_balance_of(&wnear, env.escrow.id()).await?, 0);
+    Ok(())
+}
@@ -0,0 +1,173 @@
+// Sandbox deployment shared by the integration tests
+#![allow(dead_code)]
+
+use near_workspaces::network::Sandbox;
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, AccountId, Contract, Worker};
+use serde_json::{json, Value};
+
+pub const SECRET: &str = "0123456789abcdef0123456789abcdef";
+// sha256 of SECRET
+pub const HASHLOCK: &str = "3eb1bd439947eb762998e566ccc2e099c791118b2f40579cc4f7da2b5061b7f9";
+// Deadlines in nanoseconds that never expire during a test
+pub const FAR_FUTURE: u64 = u64::MAX / 2;
+
+pub struct Env {
+    pub worker: Worker<Sandbox>,
+    pub escrow: Contract,
+    pub solver: Contract,
+    pub solver_account: Account,
+    pub user: Account,
+}
+
+// Deploy fusion-escrow and fusion-solver wired to each other, with a registered solver running `pool1`
+pub async fn setup() -> anyhow::Result<Env> {
+    let worker = near_workspaces::sandbox().await?;
+    let escrow = worker
+        .dev_deploy(&near_workspaces::compile_project("../fusion-escrow").await?)
+        .await?;
+    let solver = worker
+        .dev_deploy(&near_workspaces::compile_project("../fusion-solver").await?)
+        .await?;
+
+    escrow
+        .call("new")
+        .args_json(json!({ "owner": escrow.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    solver
+        .call("new")
+        .args_json(json!({ "owner": solver.id(), "escrow_contract": escrow.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    escrow
+        .call("set_solver_contract")
+        .args_json(json!({ "solver_contract": solver.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let solver_account = worker.dev_create_account().await?;
+    solver_account
+        .call(solver.id(), "register_solver")
+        .args_json(json!({
+            "name": "Solver",
+            "description": "Sandbox solver",
+            "version": "1.0.0",
+            "code_hash": "hash",
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    solver_account
+        .call(solver.id(), "create_pool")
+        .args_json(json!({
+            "pool_id": "pool1",
+            "fee_rate": 30,
+            "min_order_size": "1",
+            "max_order_size": NearToken::from_near(1_000).as_yoctonear().to_string(),
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let user = worker.dev_create_account().await?;
+    Ok(Env {
+        worker,
+        escrow,
+        solver,
+        solver_account,
+        user,
+    })
+}
+
+// Request a quote as the user and have the solver answer it from `pool1`
+pub async fn quote(
+    env: &Env,
+    from_token: &AccountId,
+    to_token: &AccountId,
+    amount: u128,
+) -> anyhow::Result<String> {
+    let quote_id: String = env
+        .user
+        .call(env.solver.id(), "request_quote")
+        .args_json(json!({
+            "from_token": from_token,
+            "to_token": to_token,
+            "amount": amount.to_string(),
+            "deadline": FAR_FUTURE.to_string(),
+        }))
+        .transact()
+        .await?
+        .json()?;
+    let request: Value = env
+        .solver
+        .view("get_quote_request")
+        .args_json(json!({ "quote_id": quote_id }))
+        .await?
+        .json()?;
+
+    env.solver_account
+        .call(env.solver.id(), "provide_quote")
+        .args_json(json!({
+            "args": {
+                "quote_id": quote_id,
+                "nonce": request["nonce"],
+                "to_amount": amount.to_string(),
+                "price": "1.0",
+                "gas_estimate": "30000000000000",
+                "pool_id": "pool1",
+                "fee": "0",
+                "valid_until": FAR_FUTURE.to_string(),
+            }
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(quote_id)
+}
+
+// Parse a contract's JSON-string `get_order` view
+pub async fn view_order(contract: &Contract, order_id: &str) -> anyhow::Result<Value> {
+    let order: String = contract
+        .view("get_order")
+        .args_json(json!({ "order_id": order_id }))
+        .await?
+        .json()?;
+    Ok(serde_json::from_str(&order)?)
+}
+
+// Import the mainnet wNEAR contract, initialize it and register `accounts` for storage
+pub async fn deploy_wnear(env: &Env, accounts: &[&AccountId]) -> anyhow::Result<Contract> {
+    let mainnet = near_workspaces::mainnet().await?;
+    let wnear_id: AccountId = "wrap.near".parse()?;
+    let wnear = env
+        .worker
+        .import_contract(&wnear_id, &mainnet)
+        .initial_balance(NearToken::from_near(100))
+        .transact()
+        .await?;
+    wnear.call("new").transact().await?.into_result()?;
+
+    for account_id in accounts {
+        env.user
+            .call(wnear.id(), "storage_deposit")
+            .args_json(json!({ "account_id": account_id }))
+            .deposit(NearToken::from_millinear(125))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    Ok(wnear)
+}
+
+pub async fn ft_balance_of(token: &Contract, account_id: &AccountId) -> anyhow::Result<u128> {
+    let balance: String = token
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": account_id }))
+        .await?
+        .json()?;
+    Ok(balance.parse()?)
+}
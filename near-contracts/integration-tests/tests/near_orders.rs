@@ -0,0 +1,65 @@
+mod common;
+
+use common::{deploy_wnear, ft_balance_of, setup, view_order, HASHLOCK, SECRET};
+use near_workspaces::types::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_create_order_with_near_funds_order_in_wnear() -> anyhow::Result<()> {
+    let env = setup().await?;
+    let wnear = deploy_wnear(&env, &[env.escrow.id()]).await?;
+    let to_token = env.worker.dev_create_account().await?;
+    let taker = env.worker.dev_create_account().await?;
+
+    env.escrow
+        .call("set_wnear_contract")
+        .args_json(json!({ "wnear_contract": wnear.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.escrow
+        .call("add_supported_tokens")
+        .args_json(json!({ "tokens": [wnear.id(), to_token.id()] }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let deposit = NearToken::from_near(5);
+    let funded: bool = env
+        .user
+        .call(env.escrow.id(), "create_order_with_near")
+        .args_json(json!({
+            "args": {
+                "taker": taker.id(),
+                "from_token": wnear.id(),
+                "to_token": to_token.id(),
+                "from_amount": deposit.as_yoctonear().to_string(),
+                "to_amount": "1000",
+                "hashlock": HASHLOCK,
+                "secret_length": SECRET.len(),
+                "timelock": "3600",
+            }
+        }))
+        .deposit(deposit)
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    assert!(funded);
+
+    let order_ids: Vec<String> = env
+        .escrow
+        .view("get_user_orders")
+        .args_json(json!({ "account_id": env.user.id() }))
+        .await?
+        .json()?;
+    let order = view_order(&env.escrow, &order_ids[0]).await?;
+    assert_eq!(order["status"], "Funded");
+    assert_eq!(order["from_token"], wnear.id().as_str());
+    assert_eq!(order["from_amount"], deposit.as_yoctonear().to_string());
+    assert_eq!(
+        ft_balance_of(&wnear, env.escrow.id()).await?,
+        deposit.as_yoctonear()
+    );
+    Ok(())
+}
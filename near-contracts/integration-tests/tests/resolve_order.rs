@@ -0,0 +1,66 @@
+mod common;
+
+use common::{quote, setup, view_order, FAR_FUTURE};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn test_resolve_order_falls_back_to_solver() -> anyhow::Result<()> {
+    let env = setup().await?;
+    let from_token = env.worker.dev_create_account().await?;
+    let to_token = env.worker.dev_create_account().await?;
+
+    // The order only ever exists in fusion-solver
+    let quote_id = quote(&env, from_token.id(), to_token.id(), 1_000).await?;
+    let order_id: String = env
+        .user
+        .call(env.solver.id(), "create_order")
+        .args_json(json!({
+            "args": {
+                "quote_id": quote_id,
+                "from_token": from_token.id(),
+                "to_token": to_token.id(),
+                "from_amount": "1000",
+                "to_amount": "1000",
+                "min_to_amount": "990",
+                "deadline": FAR_FUTURE.to_string(),
+                "solver": env.solver_account.id(),
+            }
+        }))
+        .transact()
+        .await?
+        .json()?;
+    assert!(view_order(&env.escrow, &order_id).await?.is_null());
+
+    let view: Option<Value> = env
+        .user
+        .call(env.escrow.id(), "resolve_order")
+        .args_json(json!({ "order_id": order_id }))
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    let view = view.expect("Order should resolve through the solver");
+    assert_eq!(view["id"], order_id.as_str());
+    assert_eq!(view["source"], "Solver");
+    assert_eq!(view["maker"], env.user.id().as_str());
+    assert_eq!(view["from_token"], from_token.id().as_str());
+    assert_eq!(view["from_amount"], "1000");
+    assert_eq!(view["status"], "Pending");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resolve_order_unknown_in_both_contracts() -> anyhow::Result<()> {
+    let env = setup().await?;
+
+    let view: Option<Value> = env
+        .user
+        .call(env.escrow.id(), "resolve_order")
+        .args_json(json!({ "order_id": "missing" }))
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    assert!(view.is_none());
+    Ok(())
+}
@@ -1,8 +1,16 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint as MintState,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
+// Upper bound on extra redeem recipients, so the HTLC account has a fixed size
+pub const MAX_ALLOWED_RECIPIENTS: usize = 8;
+
 #[program]
 pub mod htlc {
     use super::*;
@@ -11,6 +19,79 @@ pub mod htlc {
         let htlc_account = &mut ctx.accounts.htlc_account;
         htlc_account.authority = ctx.accounts.authority.key();
         htlc_account.bump = *ctx.bumps.get("htlc_account").unwrap();
+        htlc_account.min_safety_deposit = 0;
+        htlc_account.max_safety_deposit = u64::MAX;
+        Ok(())
+    }
+
+    // Grow a singleton created before the safety deposit bounds existed; the bounds start fully open
+    pub fn migrate_htlc_account(ctx: Context<MigrateHTLCAccount>) -> Result<()> {
+        let info = ctx.accounts.htlc_account.to_account_info();
+        let space = 8 + HTLCAccount::INIT_SPACE;
+        require!(info.data_len() < space, HTLCError::AlreadyMigrated);
+
+        let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+        if shortfall > 0 {
+            let rent_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: info.clone(),
+                },
+            );
+            system_program::transfer(rent_ctx, shortfall)?;
+        }
+        info.realloc(space, true)?;
+
+        // The old layout is a prefix of the new one, so it reads back with zeroed bounds
+        let mut htlc_account = HTLCAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require_keys_eq!(
+            htlc_account.authority,
+            ctx.accounts.authority.key(),
+            HTLCError::InvalidAuthority
+        );
+        htlc_account.min_safety_deposit = 0;
+        htlc_account.max_safety_deposit = u64::MAX;
+        htlc_account.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    // Grow an HTLC created before the redeem minimums, recipient set and safety deposit were appended,
+    // so redeem can write its preimage back; anyone may pay the extra rent
+    pub fn migrate_htlc(ctx: Context<MigrateHTLC>, _hashlock: [u8; 32]) -> Result<()> {
+        let info = ctx.accounts.htlc.to_account_info();
+        let space = 8 + HTLC::INIT_SPACE;
+        require!(info.data_len() < space, HTLCError::AlreadyMigrated);
+
+        let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+        if shortfall > 0 {
+            let rent_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: info.clone(),
+                },
+            );
+            system_program::transfer(rent_ctx, shortfall)?;
+        }
+        info.realloc(space, true)?;
+
+        // The old layout is a prefix of the new one, so the zeroed tail reads back as unset fields:
+        // no minimums, no extra recipients and no safety deposit
+        HTLC::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        Ok(())
+    }
+
+    pub fn set_safety_deposit_bounds(
+        ctx: Context<SetSafetyDepositBounds>,
+        min_safety_deposit: u64,
+        max_safety_deposit: u64,
+    ) -> Result<()> {
+        require!(min_safety_deposit <= max_safety_deposit, HTLCError::InvalidSafetyDepositBounds);
+
+        let htlc_account = &mut ctx.accounts.htlc_account;
+        htlc_account.min_safety_deposit = min_safety_deposit;
+        htlc_account.max_safety_deposit = max_safety_deposit;
         Ok(())
     }
 
@@ -19,18 +100,50 @@ pub mod htlc {
         hashlock: [u8; 32],
         timelock: i64,
         amount: u64,
+        min_redeem_amount: Option<u64>,
+        allowed_recipients: Vec<Pubkey>,
+        fee_slippage_bps: u16,
+        safety_deposit: u64,
     ) -> Result<()> {
         let htlc = &mut ctx.accounts.htlc;
         let clock = Clock::get()?;
 
         require!(timelock > clock.unix_timestamp, HTLCError::InvalidTimelock);
         require!(amount > 0, HTLCError::InvalidAmount);
+        require!(fee_slippage_bps <= 10000, HTLCError::InvalidFeeSlippage);
+        require!(
+            safety_deposit >= ctx.accounts.htlc_account.min_safety_deposit,
+            HTLCError::SafetyDepositTooLow
+        );
+        require!(
+            safety_deposit <= ctx.accounts.htlc_account.max_safety_deposit,
+            HTLCError::SafetyDepositTooHigh
+        );
+        require!(
+            min_redeem_amount.map_or(true, |min| min <= amount),
+            HTLCError::InvalidMinRedeemAmount
+        );
+        require!(
+            allowed_recipients.len() <= MAX_ALLOWED_RECIPIENTS,
+            HTLCError::TooManyAllowedRecipients
+        );
+
+        // Token-2022 transfer fees are withheld on the way in and again on redeem
+        let mint_info = ctx.accounts.mint.to_account_info();
+        let held_amount = amount_after_transfer_fee(&mint_info, amount, clock.epoch)?;
+        let expected_recipient_amount = amount_after_transfer_fee(&mint_info, held_amount, clock.epoch)?;
 
         htlc.sender = ctx.accounts.sender.key();
         htlc.recipient = ctx.accounts.recipient.key();
         htlc.hashlock = hashlock;
         htlc.timelock = timelock;
-        htlc.amount = amount;
+        htlc.amount = held_amount;
+        // Slack for a fee change taking effect before redeem
+        htlc.min_recipient_amount =
+            (expected_recipient_amount as u128 * (10000 - fee_slippage_bps as u128) / 10000) as u64;
+        htlc.min_redeem_amount = min_redeem_amount;
+        htlc.allowed_recipients = allowed_recipients;
+        htlc.safety_deposit = safety_deposit;
         htlc.withdrawn = false;
         htlc.refunded = false;
         htlc.created_at = clock.unix_timestamp;
@@ -38,13 +151,26 @@ pub mod htlc {
         // Transfer tokens to HTLC account
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.sender_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.htlc_token_account.to_account_info(),
                 authority: ctx.accounts.sender.to_account_info(),
             },
         );
-        token::transfer(transfer_ctx, amount)?;
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.mint.decimals)?;
+
+        // Lamports held by the HTLC and paid to whoever settles it
+        if safety_deposit > 0 {
+            let deposit_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.sender.to_account_info(),
+                    to: htlc.to_account_info(),
+                },
+            );
+            system_program::transfer(deposit_ctx, safety_deposit)?;
+        }
 
         emit!(HTLCCreated {
             htlc: htlc.key(),
@@ -64,7 +190,11 @@ pub mod htlc {
 
         require!(!htlc.withdrawn, HTLCError::AlreadyWithdrawn);
         require!(!htlc.refunded, HTLCError::AlreadyRefunded);
-        require!(htlc.recipient == ctx.accounts.recipient.key(), HTLCError::InvalidRecipient);
+        let recipient = ctx.accounts.recipient.key();
+        require!(
+            htlc.recipient == recipient || htlc.allowed_recipients.contains(&recipient),
+            HTLCError::InvalidRecipient
+        );
 
         // Verify preimage matches hashlock
         let computed_hashlock = anchor_lang::solana_program::hash::hash(&preimage).to_bytes();
@@ -73,21 +203,42 @@ pub mod htlc {
         htlc.withdrawn = true;
         htlc.preimage = Some(preimage);
 
+        let balance_before = ctx.accounts.recipient_token_account.amount;
+
         // Transfer tokens to recipient
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.htlc_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.recipient_token_account.to_account_info(),
                 authority: ctx.accounts.htlc_account.to_account_info(),
             },
         );
-        token::transfer(transfer_ctx, htlc.amount)?;
+        token_interface::transfer_checked(transfer_ctx, htlc.amount, ctx.accounts.mint.decimals)?;
+
+        // Abort if the recipient received less than the fee-adjusted or agreed minimum
+        ctx.accounts.recipient_token_account.reload()?;
+        let received = ctx
+            .accounts
+            .recipient_token_account
+            .amount
+            .saturating_sub(balance_before);
+        require!(received >= htlc.min_recipient_amount, HTLCError::InsufficientRedeemAmount);
+        if let Some(min_redeem_amount) = htlc.min_redeem_amount {
+            require!(received >= min_redeem_amount, HTLCError::InsufficientRedeemAmount);
+        }
+
+        release_safety_deposit(
+            &htlc.to_account_info(),
+            &ctx.accounts.recipient.to_account_info(),
+            htlc.safety_deposit,
+        )?;
 
         emit!(HTLCRedeemed {
             htlc: htlc.key(),
             preimage,
-            recipient: htlc.recipient,
+            recipient,
         });
 
         Ok(())
@@ -107,13 +258,20 @@ pub mod htlc {
         // Transfer tokens back to sender
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.htlc_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.sender_token_account.to_account_info(),
                 authority: ctx.accounts.htlc_account.to_account_info(),
             },
         );
-        token::transfer(transfer_ctx, htlc.amount)?;
+        token_interface::transfer_checked(transfer_ctx, htlc.amount, ctx.accounts.mint.decimals)?;
+
+        release_safety_deposit(
+            &htlc.to_account_info(),
+            &ctx.accounts.sender.to_account_info(),
+            htlc.safety_deposit,
+        )?;
 
         emit!(HTLCRefunded {
             htlc: htlc.key(),
@@ -140,6 +298,40 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+pub struct MigrateHTLCAccount<'info> {
+    /// CHECK: Still on the old layout, so it is deserialized by hand once grown
+    #[account(mut, seeds = [b"htlc"], bump, owner = crate::ID)]
+    pub htlc_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(hashlock: [u8; 32])]
+pub struct MigrateHTLC<'info> {
+    /// CHECK: Possibly still on the old layout, so it is deserialized by hand once grown
+    #[account(mut, seeds = [b"htlc", hashlock.as_ref()], bump, owner = crate::ID)]
+    pub htlc: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetSafetyDepositBounds<'info> {
+    #[account(
+        mut,
+        seeds = [b"htlc"],
+        bump = htlc_account.bump,
+        has_one = authority @ HTLCError::InvalidAuthority,
+    )]
+    pub htlc_account: Account<'info, HTLCAccount>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(hashlock: [u8; 32])]
 pub struct CreateHTLC<'info> {
     #[account(
         init,
@@ -154,33 +346,33 @@ pub struct CreateHTLC<'info> {
         payer = sender,
         token::mint = mint,
         token::authority = htlc_account,
+        token::token_program = token_program,
         seeds = [b"htlc_token", htlc.key().as_ref()],
         bump
     )]
-    pub htlc_token_account: Account<'info, TokenAccount>,
+    pub htlc_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         seeds = [b"htlc"],
         bump = htlc_account.bump,
-        has_one = authority @ HTLCError::InvalidAuthority,
     )]
     pub htlc_account: Account<'info, HTLCAccount>,
     #[account(mut)]
     pub sender: Signer<'info>,
     /// CHECK: This is the recipient of the HTLC
     pub recipient: UncheckedAccount<'info>,
-    pub mint: Account<'info, token::Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         constraint = sender_token_account.owner == sender.key(),
         constraint = sender_token_account.mint == mint.key(),
     )]
-    pub sender_token_account: Account<'info, TokenAccount>,
+    pub sender_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         constraint = recipient_token_account.owner == recipient.key(),
         constraint = recipient_token_account.mint == mint.key(),
     )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -201,21 +393,23 @@ pub struct RedeemHTLC<'info> {
         bump,
         constraint = htlc_token_account.amount >= htlc.amount,
     )]
-    pub htlc_token_account: Account<'info, TokenAccount>,
+    pub htlc_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         seeds = [b"htlc"],
         bump = htlc_account.bump,
-        has_one = authority @ HTLCError::InvalidAuthority,
     )]
     pub htlc_account: Account<'info, HTLCAccount>,
+    #[account(address = htlc_token_account.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
     pub recipient: Signer<'info>,
     #[account(
         mut,
         constraint = recipient_token_account.owner == recipient.key(),
         constraint = recipient_token_account.mint == htlc_token_account.mint,
     )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -234,21 +428,23 @@ pub struct RefundHTLC<'info> {
         bump,
         constraint = htlc_token_account.amount >= htlc.amount,
     )]
-    pub htlc_token_account: Account<'info, TokenAccount>,
+    pub htlc_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         seeds = [b"htlc"],
         bump = htlc_account.bump,
-        has_one = authority @ HTLCError::InvalidAuthority,
     )]
     pub htlc_account: Account<'info, HTLCAccount>,
+    #[account(address = htlc_token_account.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
     pub sender: Signer<'info>,
     #[account(
         mut,
         constraint = sender_token_account.owner == sender.key(),
         constraint = sender_token_account.mint == htlc_token_account.mint,
     )]
-    pub sender_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub sender_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[account]
@@ -256,6 +452,8 @@ pub struct RefundHTLC<'info> {
 pub struct HTLCAccount {
     pub authority: Pubkey,
     pub bump: u8,
+    pub min_safety_deposit: u64, // Lamports
+    pub max_safety_deposit: u64,
 }
 
 #[account]
@@ -265,11 +463,18 @@ pub struct HTLC {
     pub recipient: Pubkey,
     pub hashlock: [u8; 32],
     pub timelock: i64,
-    pub amount: u64,
+    pub amount: u64, // Held by the HTLC token account, after any transfer fee on deposit
     pub withdrawn: bool,
     pub refunded: bool,
     pub preimage: Option<[u8; 32]>,
     pub created_at: i64,
+    // Fields below were appended after launch; `migrate_htlc` grows older HTLCs to fit them
+    pub min_redeem_amount: Option<u64>,
+    pub min_recipient_amount: u64, // Expected post-fee redeem amount, less the creator's fee slippage
+    // Accounts besides `recipient` that may redeem, empty for single-recipient HTLCs
+    #[max_len(8)] // MAX_ALLOWED_RECIPIENTS
+    pub allowed_recipients: Vec<Pubkey>,
+    pub safety_deposit: u64, // Lamports on top of rent, paid to the redeemer or refunded sender
 }
 
 #[event]
@@ -315,4 +520,46 @@ pub enum HTLCError {
     TimelockNotExpired,
     #[msg("Invalid authority")]
     InvalidAuthority,
-} 
\ No newline at end of file
+    #[msg("Min redeem amount exceeds amount")]
+    InvalidMinRedeemAmount,
+    #[msg("Redeemed amount below minimum")]
+    InsufficientRedeemAmount,
+    #[msg("Too many allowed recipients")]
+    TooManyAllowedRecipients,
+    #[msg("Fee slippage exceeds 10000 bps")]
+    InvalidFeeSlippage,
+    #[msg("Min safety deposit exceeds max")]
+    InvalidSafetyDepositBounds,
+    #[msg("Safety deposit below minimum")]
+    SafetyDepositTooLow,
+    #[msg("Safety deposit above maximum")]
+    SafetyDepositTooHigh,
+    #[msg("HTLC account already migrated")]
+    AlreadyMigrated,
+}
+
+// Amount left after the mint's Token-2022 transfer fee; unchanged for mints without one
+fn amount_after_transfer_fee(mint: &AccountInfo, amount: u64, epoch: u64) -> Result<u64> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(amount);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&data)?;
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_epoch_fee(epoch, amount)
+            .ok_or(HTLCError::InvalidAmount)?,
+        Err(_) => 0,
+    };
+    Ok(amount - fee)
+} 
+
+// Move the safety deposit out of the program-owned HTLC account
+fn release_safety_deposit(htlc: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    **htlc.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
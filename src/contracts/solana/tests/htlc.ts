@@ -0,0 +1,285 @@
+import * as anchor from "@coral-xyz/anchor";
+import { AnchorError, BN, Program } from "@coral-xyz/anchor";
+import {
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createMint,
+  ExtensionType,
+  getAccount,
+  getMintLen,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  sendAndConfirmTransaction,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+  Transaction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import { createHash, randomBytes } from "crypto";
+import { Htlc } from "../target/types/htlc";
+
+const U64_MAX = new BN("18446744073709551615");
+const AMOUNT = 10_000;
+
+describe("htlc", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Htlc as Program<Htlc>;
+  const connection = provider.connection;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const [htlcAccount] = PublicKey.findProgramAddressSync([Buffer.from("htlc")], program.programId);
+
+  type Token = { mint: PublicKey; programId: PublicKey };
+  let classic: Token;
+  // 1% transfer fee, so 10_000 sent in arrives as 9_900 and redeems as 9_801
+  let feeToken: Token;
+
+  async function createFeeMint(feeBps: number): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const space = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports = await connection.getMinimumBalanceForRentExemption(space);
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        mint.publicKey,
+        payer.publicKey,
+        payer.publicKey,
+        feeBps,
+        BigInt(1_000_000_000),
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(mint.publicKey, 6, payer.publicKey, null, TOKEN_2022_PROGRAM_ID)
+    );
+    await sendAndConfirmTransaction(connection, tx, [payer, mint]);
+    return mint.publicKey;
+  }
+
+  async function tokenAccount(token: Token, owner: PublicKey): Promise<PublicKey> {
+    const account = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      token.mint,
+      owner,
+      false,
+      undefined,
+      undefined,
+      token.programId
+    );
+    return account.address;
+  }
+
+  async function balance(token: Token, account: PublicKey): Promise<bigint> {
+    return (await getAccount(connection, account, undefined, token.programId)).amount;
+  }
+
+  type HtlcOptions = {
+    token?: Token;
+    minRedeemAmount?: number | null;
+    allowedRecipients?: PublicKey[];
+    safetyDeposit?: number;
+  };
+
+  // Lock AMOUNT from the wallet for a fresh recipient, returning what a redeem needs
+  async function createHtlc(options: HtlcOptions = {}) {
+    const token = options.token ?? classic;
+    const recipient = Keypair.generate();
+    const preimage = randomBytes(32);
+    const hashlock = createHash("sha256").update(preimage).digest();
+    const [htlc] = PublicKey.findProgramAddressSync([Buffer.from("htlc"), hashlock], program.programId);
+    const [htlcTokenAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("htlc_token"), htlc.toBuffer()],
+      program.programId
+    );
+
+    const senderTokenAccount = await tokenAccount(token, payer.publicKey);
+    await mintTo(connection, payer, token.mint, senderTokenAccount, payer, AMOUNT, [], undefined, token.programId);
+    const recipientTokenAccount = await tokenAccount(token, recipient.publicKey);
+
+    await program.methods
+      .createHtlc(
+        [...hashlock],
+        new BN(Math.floor(Date.now() / 1000) + 3600),
+        new BN(AMOUNT),
+        options.minRedeemAmount == null ? null : new BN(options.minRedeemAmount),
+        options.allowedRecipients ?? [],
+        0,
+        new BN(options.safetyDeposit ?? 0)
+      )
+      .accounts({
+        htlc,
+        htlcTokenAccount,
+        htlcAccount,
+        sender: payer.publicKey,
+        recipient: recipient.publicKey,
+        mint: token.mint,
+        senderTokenAccount,
+        recipientTokenAccount,
+        tokenProgram: token.programId,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    return { token, htlc, htlcTokenAccount, preimage, recipient, recipientTokenAccount };
+  }
+
+  type CreatedHtlc = Awaited<ReturnType<typeof createHtlc>>;
+
+  // Redeem as `redeemer` (the HTLC recipient by default), returning the redeemer's token account
+  async function redeem(created: CreatedHtlc, redeemer: Keypair = created.recipient): Promise<PublicKey> {
+    const redeemerTokenAccount =
+      redeemer === created.recipient
+        ? created.recipientTokenAccount
+        : await tokenAccount(created.token, redeemer.publicKey);
+    await program.methods
+      .redeemHtlc([...created.preimage])
+      .accounts({
+        htlc: created.htlc,
+        htlcTokenAccount: created.htlcTokenAccount,
+        htlcAccount,
+        mint: created.token.mint,
+        recipient: redeemer.publicKey,
+        recipientTokenAccount: redeemerTokenAccount,
+        tokenProgram: created.token.programId,
+      })
+      .signers([redeemer])
+      .rpc();
+    return redeemerTokenAccount;
+  }
+
+  async function expectError(promise: Promise<unknown>, code: string) {
+    let error: unknown;
+    try {
+      await promise;
+    } catch (err) {
+      error = err;
+    }
+    expect(error, `expected ${code}`).to.be.instanceOf(AnchorError);
+    expect((error as AnchorError).error.errorCode.code).to.equal(code);
+  }
+
+  async function setSafetyDepositBounds(min: BN, max: BN) {
+    await program.methods
+      .setSafetyDepositBounds(min, max)
+      .accounts({ htlcAccount, authority: payer.publicKey })
+      .rpc();
+  }
+
+  before(async () => {
+    await program.methods
+      .initialize()
+      .accounts({ htlcAccount, authority: payer.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    classic = {
+      mint: await createMint(connection, payer, payer.publicKey, null, 6),
+      programId: TOKEN_PROGRAM_ID,
+    };
+    feeToken = { mint: await createFeeMint(100), programId: TOKEN_2022_PROGRAM_ID };
+  });
+
+  describe("min_redeem_amount", () => {
+    it("redeems when the received amount meets the minimum", async () => {
+      const created = await createHtlc({ minRedeemAmount: AMOUNT });
+      await redeem(created);
+      expect(await balance(classic, created.recipientTokenAccount)).to.equal(BigInt(AMOUNT));
+    });
+
+    it("rejects a redeem that receives less than the minimum", async () => {
+      // Fees leave 9_801 for the recipient
+      const created = await createHtlc({ token: feeToken, minRedeemAmount: 9_900 });
+      await expectError(redeem(created), "InsufficientRedeemAmount");
+    });
+  });
+
+  describe("allowed_recipients", () => {
+    it("lets any allowed recipient redeem", async () => {
+      const allowed = [Keypair.generate(), Keypair.generate()];
+      const created = await createHtlc({ allowedRecipients: allowed.map((key) => key.publicKey) });
+      const received = await redeem(created, allowed[1]);
+      expect(await balance(classic, received)).to.equal(BigInt(AMOUNT));
+    });
+
+    it("rejects a recipient outside the set", async () => {
+      const created = await createHtlc({ allowedRecipients: [Keypair.generate().publicKey] });
+      await expectError(redeem(created, Keypair.generate()), "InvalidRecipient");
+    });
+  });
+
+  describe("transfer-fee mints", () => {
+    it("stores the post-fee minimum and pays at least that much", async () => {
+      const created = await createHtlc({ token: feeToken });
+      const htlc = await program.account.htlc.fetch(created.htlc);
+      expect(htlc.amount.toNumber()).to.equal(9_900);
+      expect(htlc.minRecipientAmount.toNumber()).to.equal(9_801);
+
+      await redeem(created);
+      expect(await balance(feeToken, created.recipientTokenAccount)).to.equal(BigInt(9_801));
+    });
+
+    it("accepts a min_redeem_amount covering the fees", async () => {
+      const created = await createHtlc({ token: feeToken, minRedeemAmount: 9_801 });
+      await redeem(created);
+      expect(await balance(feeToken, created.recipientTokenAccount)).to.equal(BigInt(9_801));
+    });
+  });
+
+  describe("safety deposit bounds", () => {
+    before(() => setSafetyDepositBounds(new BN(1_000), new BN(1_000_000)));
+    after(() => setSafetyDepositBounds(new BN(0), U64_MAX));
+
+    it("rejects a deposit below the minimum", async () => {
+      await expectError(createHtlc({ safetyDeposit: 999 }), "SafetyDepositTooLow");
+    });
+
+    it("rejects a deposit above the maximum", async () => {
+      await expectError(createHtlc({ safetyDeposit: 1_000_001 }), "SafetyDepositTooHigh");
+    });
+
+    it("pays a deposit within the bounds to the redeemer", async () => {
+      // The redeemer starts empty, so the deposit has to cover its rent exemption
+      const created = await createHtlc({ safetyDeposit: 1_000_000 });
+      await redeem(created);
+      expect(await connection.getBalance(created.recipient.publicKey)).to.equal(1_000_000);
+    });
+  });
+
+  describe("migrate_htlc_account", () => {
+    it("rejects an account already on the current layout", async () => {
+      await expectError(
+        program.methods
+          .migrateHtlcAccount()
+          .accounts({ htlcAccount, authority: payer.publicKey, systemProgram: SystemProgram.programId })
+          .rpc(),
+        "AlreadyMigrated"
+      );
+    });
+  });
+
+  describe("migrate_htlc", () => {
+    it("rejects an HTLC already on the current layout", async () => {
+      const created = await createHtlc();
+      const { hashlock } = await program.account.htlc.fetch(created.htlc);
+      await expectError(
+        program.methods
+          .migrateHtlc(hashlock)
+          .accounts({ htlc: created.htlc, payer: payer.publicKey, systemProgram: SystemProgram.programId })
+          .rpc(),
+        "AlreadyMigrated"
+      );
+    });
+  });
+});
@@ -277,13 +277,16 @@ export class NEARService {
       
       // Create order using the smart contract
       const result = await (this.contracts.escrow as any).create_order({
-        taker: params.userAddress,
-        from_token: params.fromToken,
-        to_token: params.toToken,
-        from_amount: params.fromAmount,
-        to_amount: params.toAmount,
-        hashlock: hashlock,
-        timelock: params.timelock
+        args: {
+          taker: params.userAddress,
+          from_token: params.fromToken,
+          to_token: params.toToken,
+          from_amount: params.fromAmount,
+          to_amount: params.toAmount,
+          hashlock: hashlock,
+          secret_length: secret.length,
+          timelock: params.timelock
+        }
       });
 
       const order: NEARFusionOrder = {